@@ -0,0 +1,44 @@
+//! 合并数据源去重的性能基准测试
+
+use chrono::Utc;
+use criterion::{Criterion, criterion_group, criterion_main};
+use hosts_updater_rs::conflict::dedup_sources;
+use hosts_updater_rs::fetcher::SourceResult;
+use std::hint::black_box;
+
+/// 生成指定数量的数据源，相邻数据源之间有一半的条目重复
+fn generate_sources(count: usize, lines: usize) -> Vec<SourceResult> {
+    (0..count)
+        .map(|index| {
+            let mut content = String::from("# 基准测试数据\n");
+            for i in 0..lines {
+                let id = index * lines / 2 + i;
+                content.push_str(&format!(
+                    "0.0.0.0 ads{}.tracker-{}.example.com\n",
+                    id,
+                    id % 997
+                ));
+            }
+            SourceResult {
+                url: format!("https://source{}.example/hosts", index),
+                bytes: content.len(),
+                content,
+                fetched_at: Utc::now(),
+                from_cache: false,
+                status: 200,
+                max_age_secs: None,
+            }
+        })
+        .collect()
+}
+
+fn bench_dedup_sources(c: &mut Criterion) {
+    let sources = generate_sources(8, 100_000);
+
+    c.bench_function("dedup_sources_8x100k", |b| {
+        b.iter(|| dedup_sources(black_box(&sources)))
+    });
+}
+
+criterion_group!(benches, bench_dedup_sources);
+criterion_main!(benches);
@@ -0,0 +1,45 @@
+//! 移除自动管理区域的性能基准测试
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use hosts_updater_rs::hosts::{END_MARKER, START_MARKER, remove_auto_managed_section};
+use std::hint::black_box;
+
+/// 生成指定行数的用户内容，中间插入一个自动管理区域
+fn generate_hosts(lines: usize) -> String {
+    let mut content = String::from("127.0.0.1 localhost\n");
+    for i in 0..lines {
+        if i == lines / 2 {
+            content.push_str(&format!(
+                "\n{}\n# 最后更新: 2024-01-01 00:00:00\n\n",
+                START_MARKER
+            ));
+        }
+        if i % 100 == 0 {
+            content.push_str("# 分组注释\n\n");
+        }
+        content.push_str(&format!(
+            "0.0.0.0 ads{}.tracker-{}.example.com\n",
+            i,
+            i % 997
+        ));
+        if i == lines / 2 + lines / 10 {
+            content.push_str(&format!("{}\n\n", END_MARKER));
+        }
+    }
+    content
+}
+
+fn bench_remove_auto_managed_section(c: &mut Criterion) {
+    let with_section = generate_hosts(1_000_000);
+    let without_section = remove_auto_managed_section(&with_section);
+
+    c.bench_function("remove_auto_managed_section_1m", |b| {
+        b.iter(|| remove_auto_managed_section(black_box(&with_section)))
+    });
+    c.bench_function("remove_auto_managed_section_1m_no_markers", |b| {
+        b.iter(|| remove_auto_managed_section(black_box(&without_section)))
+    });
+}
+
+criterion_group!(benches, bench_remove_auto_managed_section);
+criterion_main!(benches);
@@ -0,0 +1,33 @@
+//! hosts 内容验证的性能基准测试
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use hosts_updater_rs::validation::validate_hosts_content;
+use std::hint::black_box;
+
+/// 生成指定行数的 hosts 内容
+fn generate_hosts(lines: usize) -> String {
+    let mut content = String::from("# 基准测试数据\n");
+    for i in 0..lines {
+        if i % 100 == 0 {
+            content.push_str("# 分组注释\n\n");
+        }
+        content.push_str(&format!(
+            "0.0.0.0\tads{}.tracker-{}.example.com  cdn{}.example.net\n",
+            i,
+            i % 997,
+            i
+        ));
+    }
+    content
+}
+
+fn bench_validate_hosts_content(c: &mut Criterion) {
+    let content = generate_hosts(200_000);
+
+    c.bench_function("validate_hosts_content_200k", |b| {
+        b.iter(|| validate_hosts_content(black_box(&content), "https://example.com").unwrap())
+    });
+}
+
+criterion_group!(benches, bench_validate_hosts_content);
+criterion_main!(benches);
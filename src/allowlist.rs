@@ -0,0 +1,142 @@
+//! 全局白名单模块
+//!
+//! 从 `allowlist_file` 读取必须正常解析的域名，在写入前从所有数据源的条目中移除匹配的域名，
+//! 不论其来自哪个数据源。
+
+use crate::conflict::retain_domains;
+use crate::fetcher::SourceResult;
+use anyhow::{Context, Result};
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+/// 读取白名单文件
+///
+/// 每行一个域名或 `*.example.com` 形式的通配符（同时匹配 `example.com` 本身），
+/// 空行与 `#` 开头的注释行被忽略。
+pub fn load_allowlist(path: &str) -> Result<Vec<String>> {
+    let content =
+        std::fs::read_to_string(path).with_context(|| format!("读取白名单文件失败: {}", path))?;
+    Ok(parse_allowlist(&content))
+}
+
+/// 解析白名单内容
+fn parse_allowlist(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|pattern| !pattern.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// 白名单匹配表，匹配规则与 [`crate::preprocess::rule_matches`] 一致
+///
+/// 精确域名与 `*.` 通配符的后缀分别以小写存入集合，匹配时依次查找域名本身及其各级后缀，
+/// 每个域名的查找次数只与其标签数有关，与白名单的规则数量无关。
+struct Allowlist {
+    exact: HashSet<String>,
+    suffixes: HashSet<String>,
+}
+
+impl Allowlist {
+    fn new(patterns: &[String]) -> Self {
+        let mut exact = HashSet::new();
+        let mut suffixes = HashSet::new();
+        for pattern in patterns {
+            match pattern.strip_prefix("*.") {
+                Some(suffix) => suffixes.insert(suffix.to_ascii_lowercase()),
+                None => exact.insert(pattern.to_ascii_lowercase()),
+            };
+        }
+        Allowlist { exact, suffixes }
+    }
+
+    /// 域名是否匹配白名单，`*.example.com` 同时匹配 `example.com` 本身
+    fn matches(&self, domain: &str) -> bool {
+        let domain = if domain.bytes().any(|b| b.is_ascii_uppercase()) {
+            Cow::Owned(domain.to_ascii_lowercase())
+        } else {
+            Cow::Borrowed(domain)
+        };
+        if self.exact.contains(domain.as_ref()) {
+            return true;
+        }
+
+        let mut rest = domain.as_ref();
+        loop {
+            if self.suffixes.contains(rest) {
+                return true;
+            }
+            match rest.split_once('.') {
+                Some((_, parent)) => rest = parent,
+                None => return false,
+            }
+        }
+    }
+}
+
+/// 从各数据源中移除匹配白名单的域名，返回处理后的数据源与移除的域名数量
+pub fn apply_allowlist(
+    sources: &[SourceResult],
+    patterns: &[String],
+) -> (Vec<SourceResult>, usize) {
+    let allowlist = Allowlist::new(patterns);
+    let mut removed = 0;
+    let sources = sources
+        .iter()
+        .map(|source| {
+            let mut source = source.clone();
+            source.content = retain_domains(&source.content, |_, domain| {
+                let allowed = allowlist.matches(domain);
+                if allowed {
+                    removed += 1;
+                }
+                !allowed
+            });
+            source
+        })
+        .collect();
+    (sources, removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_allowlist() {
+        let patterns =
+            parse_allowlist("# 白名单\n\nkeep.example.com\n*.cdn.example.org # 整个 CDN\n");
+        assert_eq!(patterns, vec!["keep.example.com", "*.cdn.example.org"]);
+
+        let content = "# 注释\n0.0.0.0 ads.example.com KEEP.example.com\n0.0.0.0 cdn.example.org\n\
+                       0.0.0.0 a.cdn.example.org\n";
+        let source = SourceResult::for_test("https://a.example/hosts", content);
+
+        let (sources, removed) = apply_allowlist(&[source], &patterns);
+        assert_eq!(removed, 3);
+        assert_eq!(sources[0].content, "# 注释\n0.0.0.0 ads.example.com\n");
+
+        // 与逐条规则匹配的结果一致
+        let patterns: Vec<String> = ["Exact.example.com", "*.CDN.example.org", "*.", "*.a..b"]
+            .map(str::to_string)
+            .to_vec();
+        let allowlist = Allowlist::new(&patterns);
+        for domain in [
+            "exact.example.com",
+            "a.exact.example.com",
+            "cdn.example.org",
+            "X.Y.cdn.example.org",
+            "notcdn.example.org",
+            "example.org",
+            "trailing.dot.",
+            "x.a..b",
+            "a..b",
+        ] {
+            let expected = patterns
+                .iter()
+                .any(|pattern| crate::preprocess::rule_matches(pattern, domain));
+            assert_eq!(allowlist.matches(domain), expected, "{}", domain);
+        }
+    }
+}
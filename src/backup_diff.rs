@@ -0,0 +1,89 @@
+//! 备份对比模块
+//!
+//! 比较两个 hosts 备份文件的自动管理区域，生成统一格式的差异，并汇总新增与移除的域名，
+//! 便于排查某次更新后网站开始或停止被屏蔽的原因。
+
+use crate::hosts::{LAST_UPDATE_FORMAT, extract_auto_section, parse_managed_section};
+use std::collections::BTreeSet;
+
+/// 两个备份之间的差异
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupDiff {
+    /// 自动管理区域的统一格式差异，没有差异时为空
+    pub unified: String,
+    /// 新备份中新增的域名（按字母顺序）
+    pub added: Vec<String>,
+    /// 新备份中移除的域名（按字母顺序）
+    pub removed: Vec<String>,
+}
+
+impl BackupDiff {
+    /// 自动管理区域是否完全相同
+    pub fn is_empty(&self) -> bool {
+        self.unified.is_empty()
+    }
+}
+
+/// 比较两个备份的自动管理区域，`old_name` 与 `new_name` 用作差异的文件头
+pub fn diff_backups(old: &str, new: &str, old_name: &str, new_name: &str) -> BackupDiff {
+    let old_section = extract_auto_section(old);
+    let new_section = extract_auto_section(new);
+
+    let unified = if old_section == new_section {
+        String::new()
+    } else {
+        similar::TextDiff::from_lines(&old_section, &new_section)
+            .unified_diff()
+            .context_radius(3)
+            .header(old_name, new_name)
+            .to_string()
+    };
+
+    let old_domains = section_domains(old);
+    let new_domains = section_domains(new);
+
+    BackupDiff {
+        unified,
+        added: new_domains.difference(&old_domains).cloned().collect(),
+        removed: old_domains.difference(&new_domains).cloned().collect(),
+    }
+}
+
+/// 收集自动管理区域中条目的所有域名
+fn section_domains(content: &str) -> BTreeSet<String> {
+    parse_managed_section(content, LAST_UPDATE_FORMAT)
+        .domains()
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_backups() {
+        let old = "127.0.0.1 localhost\n\
+                   # >>> hosts_updater_rs START >>>\n\
+                   0.0.0.0 a.com b.com\n\
+                   0.0.0.0 c.com # 注释中的 x.com 不算\n\
+                   # <<< hosts_updater_rs END <<<\n";
+        let new = "127.0.0.1 localhost\n\
+                   192.168.1.1 router.lan\n\
+                   # >>> hosts_updater_rs START >>>\n\
+                   0.0.0.0 a.com\n\
+                   0.0.0.0 c.com # 注释中的 x.com 不算\n\
+                   0.0.0.0 d.com\n\
+                   # <<< hosts_updater_rs END <<<\n";
+
+        let diff = diff_backups(old, new, "old", "new");
+        assert_eq!(diff.added, vec!["d.com"]);
+        assert_eq!(diff.removed, vec!["b.com"]);
+        assert!(diff.unified.starts_with("--- old\n+++ new\n"));
+        assert!(diff.unified.contains("-0.0.0.0 a.com b.com\n+0.0.0.0 a.com\n"));
+        assert!(!diff.unified.contains("router.lan"));
+
+        // 区域外的改动不视为差异
+        assert!(diff_backups(old, &old.replace("localhost", "local"), "old", "new").is_empty());
+    }
+}
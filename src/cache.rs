@@ -0,0 +1,204 @@
+//! 本地缓存模块
+//!
+//! 每次成功更新后将合并后的数据源内容压缩保存到缓存文件，
+//! 以便重启后所有数据源都不可用时仍能用缓存写入 hosts 文件。
+
+use crate::fetcher::SourceResult;
+use crate::state::get_cache_dir;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+
+/// 缓存格式版本，格式不兼容时递增
+pub const CACHE_VERSION: u32 = 2;
+
+/// 缓存文件名
+const CACHE_FILE_NAME: &str = "hosts_cache.json.gz";
+
+/// 缓存内容
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HostsCache {
+    /// 缓存格式版本
+    pub version: u32,
+    /// 缓存创建时间
+    pub created_at: DateTime<Utc>,
+    /// 各数据源的获取结果
+    pub sources: Vec<SourceResult>,
+}
+
+/// 保存缓存
+///
+/// 先写入同目录下的临时文件再重命名，写入中断时不会留下损坏的缓存文件。
+pub fn save_cache(cache_dir: &Option<String>, sources: &[SourceResult]) -> Result<()> {
+    let dir = get_cache_dir(cache_dir);
+    if !dir.exists() {
+        fs::create_dir_all(&dir).with_context(|| format!("创建缓存目录失败: {:?}", dir))?;
+    }
+
+    let cache = HostsCache {
+        version: CACHE_VERSION,
+        created_at: Utc::now(),
+        sources: sources.to_vec(),
+    };
+    let json = serde_json::to_vec(&cache).context("序列化缓存失败")?;
+
+    let path = dir.join(CACHE_FILE_NAME);
+    let temp_path = dir.join(format!("{}.tmp", CACHE_FILE_NAME));
+    let file =
+        File::create(&temp_path).with_context(|| format!("创建缓存文件失败: {:?}", temp_path))?;
+    let mut encoder = GzEncoder::new(file, Compression::default());
+    let written = encoder
+        .write_all(&json)
+        .and_then(|_| encoder.finish())
+        .and_then(|file| file.sync_all())
+        .with_context(|| format!("写入缓存文件失败: {:?}", temp_path))
+        .and_then(|_| {
+            fs::rename(&temp_path, &path)
+                .with_context(|| format!("替换缓存文件失败: {:?}", path))
+        });
+    if written.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    written
+}
+
+/// 读取缓存
+///
+/// 缓存不存在、版本不匹配或超过 `ttl_hours` 时返回 `None`；
+/// 返回的获取结果均标记为来自缓存。
+pub fn load_cache(cache_dir: &Option<String>, ttl_hours: u64) -> Result<Option<Vec<SourceResult>>> {
+    let Some(cache) = read_cache(cache_dir)? else {
+        return Ok(None);
+    };
+
+    let ttl = i64::try_from(ttl_hours)
+        .ok()
+        .and_then(chrono::TimeDelta::try_hours)
+        .ok_or_else(|| anyhow::anyhow!("cache_ttl_hours 超出范围: {}", ttl_hours))?;
+    let age = Utc::now() - cache.created_at;
+    if age > ttl {
+        tracing::warn!(
+            "缓存已过期（创建于 {}，有效期 {} 小时），忽略缓存",
+            cache.created_at,
+            ttl_hours
+        );
+        return Ok(None);
+    }
+
+    Ok(Some(cached_sources(cache)))
+}
+
+/// 读取缓存中的所有数据源，不检查缓存有效期
+///
+/// 用于按各数据源的获取时间判断是否需要重新获取；缓存不存在或版本不匹配时返回空列表。
+pub fn load_cached_sources(cache_dir: &Option<String>) -> Result<Vec<SourceResult>> {
+    Ok(read_cache(cache_dir)?.map(cached_sources).unwrap_or_default())
+}
+
+/// 读取并解压缓存文件，缓存不存在或版本不匹配时返回 `None`
+fn read_cache(cache_dir: &Option<String>) -> Result<Option<HostsCache>> {
+    let path = get_cache_dir(cache_dir).join(CACHE_FILE_NAME);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let file = File::open(&path).with_context(|| format!("打开缓存文件失败: {:?}", path))?;
+    let mut json = Vec::new();
+    GzDecoder::new(file)
+        .read_to_end(&mut json)
+        .with_context(|| format!("解压缓存文件失败: {:?}", path))?;
+    let cache: HostsCache =
+        serde_json::from_slice(&json).with_context(|| format!("解析缓存文件失败: {:?}", path))?;
+
+    if cache.version != CACHE_VERSION {
+        tracing::warn!(
+            "缓存版本不匹配（{} != {}），忽略缓存",
+            cache.version,
+            CACHE_VERSION
+        );
+        return Ok(None);
+    }
+
+    Ok(Some(cache))
+}
+
+/// 取出缓存中的数据源，均标记为来自缓存
+fn cached_sources(cache: HostsCache) -> Vec<SourceResult> {
+    cache
+        .sources
+        .into_iter()
+        .map(|source| SourceResult {
+            from_cache: true,
+            ..source
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sources() -> Vec<SourceResult> {
+        vec![SourceResult::for_test("https://example.com/hosts", "127.0.0.1 example.com\n")]
+    }
+
+    #[test]
+    fn test_cache_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_dir = Some(dir.path().to_string_lossy().to_string());
+
+        assert!(load_cache(&cache_dir, 24).unwrap().is_none());
+
+        let sources = sources();
+        save_cache(&cache_dir, &sources).unwrap();
+
+        assert!(!dir.path().join(format!("{}.tmp", CACHE_FILE_NAME)).exists());
+
+        let cached = load_cache(&cache_dir, 24).unwrap().unwrap();
+        assert_eq!(cached.len(), 1);
+        assert_eq!(cached[0].content, sources[0].content);
+        assert!(cached[0].from_cache);
+    }
+
+    #[test]
+    fn test_cache_expired() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_dir = Some(dir.path().to_string_lossy().to_string());
+
+        save_cache(&cache_dir, &sources()).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        assert!(load_cache(&cache_dir, 0).unwrap().is_none());
+
+        // 超出范围的有效期返回错误而不是溢出，验证配置时即被拒绝
+        assert!(load_cache(&cache_dir, u64::MAX).is_err());
+        let url = "https://a.example/hosts".to_string();
+        let mut config = crate::config::Config::from_sources(vec![url]);
+        config.cache_ttl_hours = u64::MAX;
+        assert!(crate::config::validate_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_cache_version_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_dir = Some(dir.path().to_string_lossy().to_string());
+
+        let cache = HostsCache {
+            version: CACHE_VERSION + 1,
+            created_at: Utc::now(),
+            sources: sources(),
+        };
+        let file = File::create(dir.path().join(CACHE_FILE_NAME)).unwrap();
+        let mut encoder = GzEncoder::new(file, Compression::default());
+        encoder
+            .write_all(&serde_json::to_vec(&cache).unwrap())
+            .unwrap();
+        encoder.finish().unwrap();
+
+        assert!(load_cache(&cache_dir, 24).unwrap().is_none());
+    }
+}
@@ -0,0 +1,156 @@
+//! 变更日志模块
+//!
+//! 每次写入后比较新旧自动管理区域中的域名，将新增与移除的域名带时间戳追加到变更日志文件，
+//! 形成可供人工审阅的审计记录。文件超过大小上限时轮转为 `<文件名>.1`。
+
+use crate::hosts::{ManagedSection, ManagedSource};
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// 一次更新中新增与移除的域名
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DomainChanges {
+    /// 新增的域名（按字母顺序）
+    pub added: Vec<String>,
+    /// 移除的域名（按字母顺序）
+    pub removed: Vec<String>,
+}
+
+impl DomainChanges {
+    /// 比较写入前的自动管理区域与本次写入的条目区块
+    pub fn between(previous: &ManagedSection, entries: &str) -> Self {
+        let current = ManagedSection {
+            last_update: None,
+            sources: vec![ManagedSource::from_content("", entries)],
+        };
+        Self::between_sections(previous, &current)
+    }
+
+    /// 比较两个解析后的自动管理区域
+    pub fn between_sections(previous: &ManagedSection, current: &ManagedSection) -> Self {
+        let old: BTreeSet<&str> = previous.domains().collect();
+        let new: BTreeSet<&str> = current.domains().collect();
+
+        DomainChanges {
+            added: new
+                .difference(&old)
+                .map(|domain| domain.to_string())
+                .collect(),
+            removed: old
+                .difference(&new)
+                .map(|domain| domain.to_string())
+                .collect(),
+        }
+    }
+
+    /// 域名是否没有任何变化
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+
+    /// 生成一条日志记录：标题行为时间戳与数量，之后每行一个以 `+` / `-` 开头的域名
+    fn render(&self, timestamp: &str) -> String {
+        let mut record = format!(
+            "[{}] +{} -{}\n",
+            timestamp,
+            self.added.len(),
+            self.removed.len()
+        );
+        for domain in &self.added {
+            record.push_str(&format!("+ {}\n", domain));
+        }
+        for domain in &self.removed {
+            record.push_str(&format!("- {}\n", domain));
+        }
+        record
+    }
+}
+
+/// 轮转后的变更日志文件路径
+fn rotated_path(path: &Path) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(".1");
+    PathBuf::from(rotated)
+}
+
+/// 将本次的域名变化追加到变更日志，没有变化时不写入
+///
+/// 追加前文件已达到 `max_bytes` 时，先将其重命名为 `<文件名>.1`（覆盖之前轮转的文件）。
+pub fn append_change_log(
+    path: &Path,
+    changes: &DomainChanges,
+    timestamp: &str,
+    max_bytes: u64,
+) -> Result<()> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    if fs::metadata(path).is_ok_and(|metadata| metadata.len() >= max_bytes) {
+        let rotated = rotated_path(path);
+        fs::rename(path, &rotated)
+            .with_context(|| format!("轮转变更日志失败: {:?} -> {:?}", path, rotated))?;
+    }
+
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).with_context(|| format!("创建变更日志目录失败: {:?}", dir))?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("打开变更日志失败: {:?}", path))?;
+    file.write_all(changes.render(timestamp).as_bytes())
+        .with_context(|| format!("写入变更日志失败: {:?}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hosts::parse_managed_section;
+
+    #[test]
+    fn test_change_log_append_and_rotate() {
+        let previous = parse_managed_section(
+            "# >>> hosts_updater_rs START >>>\n\
+             0.0.0.0 a.com b.com\n\
+             0.0.0.0 c.com\n\
+             # <<< hosts_updater_rs END <<<\n",
+            crate::hosts::LAST_UPDATE_FORMAT,
+        );
+        let changes = DomainChanges::between(&previous, "0.0.0.0 a.com\n0.0.0.0 c.com d.com\n");
+        assert_eq!(changes.added, vec!["d.com"]);
+        assert_eq!(changes.removed, vec!["b.com"]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("changes.log");
+        append_change_log(&path, &changes, "2024-01-01 00:00:00", 1024).unwrap();
+        append_change_log(
+            &path,
+            &DomainChanges::default(),
+            "2024-01-01 01:00:00",
+            1024,
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "[2024-01-01 00:00:00] +1 -1\n+ d.com\n- b.com\n"
+        );
+
+        // 达到上限后轮转，新记录写入新文件
+        append_change_log(&path, &changes, "2024-01-01 02:00:00", 10).unwrap();
+        assert!(
+            fs::read_to_string(rotated_path(&path))
+                .unwrap()
+                .starts_with("[2024-01-01 00")
+        );
+        assert!(
+            fs::read_to_string(&path)
+                .unwrap()
+                .starts_with("[2024-01-01 02")
+        );
+    }
+}
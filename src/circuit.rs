@@ -0,0 +1,172 @@
+//! 数据源熔断模块
+//!
+//! 为每个数据源维护一个熔断器：连续失败达到阈值后进入熔断状态，在冷却期内跳过该数据源；
+//! 冷却结束后尝试获取一次，仍然失败则以加倍的冷却时间再次熔断，获取成功则恢复正常。
+//! 熔断状态持久化在缓存目录中，重启后冷却期不会被重置。
+
+use crate::state::get_cache_dir;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+
+/// 熔断状态文件名
+const CIRCUIT_FILE_NAME: &str = "circuit_breaker.json";
+
+/// 冷却时间上限，连续多次熔断后不再继续加倍
+const MAX_COOLDOWN_HOURS: i64 = 24;
+
+/// 单个数据源的熔断状态
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct Breaker {
+    /// 连续失败次数（每次获取在所有镜像均失败后计一次）
+    consecutive_failures: u32,
+    /// 自上次成功以来的熔断次数，决定冷却时间的倍数
+    trips: u32,
+    /// 冷却结束时间，之前跳过该数据源
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    open_until: Option<DateTime<Utc>>,
+}
+
+/// 所有数据源的熔断状态
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CircuitBreakers {
+    #[serde(default)]
+    sources: BTreeMap<String, Breaker>,
+}
+
+impl CircuitBreakers {
+    /// 读取熔断状态，文件不存在时返回空状态
+    pub fn load(cache_dir: &Option<String>) -> Result<Self> {
+        let path = get_cache_dir(cache_dir).join(CIRCUIT_FILE_NAME);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content =
+            fs::read_to_string(&path).with_context(|| format!("读取熔断状态失败: {:?}", path))?;
+        serde_json::from_str(&content).with_context(|| format!("解析熔断状态失败: {:?}", path))
+    }
+
+    /// 保存熔断状态
+    pub fn save(&self, cache_dir: &Option<String>) -> Result<()> {
+        let dir = get_cache_dir(cache_dir);
+        fs::create_dir_all(&dir).with_context(|| format!("创建缓存目录失败: {:?}", dir))?;
+
+        let path = dir.join(CIRCUIT_FILE_NAME);
+        let content = serde_json::to_string_pretty(self).context("序列化熔断状态失败")?;
+        fs::write(&path, content).with_context(|| format!("写入熔断状态失败: {:?}", path))
+    }
+
+    /// 数据源处于熔断冷却期时返回冷却结束时间
+    ///
+    /// 冷却期已过的数据源返回 `None` 并记录日志，本次获取作为试探。
+    pub fn open_until(&mut self, url: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let breaker = self.sources.get_mut(url)?;
+        let until = breaker.open_until?;
+        if now < until {
+            return Some(until);
+        }
+
+        tracing::info!("数据源熔断冷却结束，尝试重新获取: {}", url);
+        breaker.open_until = None;
+        None
+    }
+
+    /// 记录获取成功，关闭熔断器
+    pub fn record_success(&mut self, url: &str) {
+        if let Some(breaker) = self.sources.remove(url)
+            && breaker.trips > 0
+        {
+            tracing::info!("数据源恢复正常，熔断器已关闭: {}", url);
+        }
+    }
+
+    /// 记录获取失败，连续失败达到 `threshold` 次时熔断
+    ///
+    /// 第 N 次熔断的冷却时间为 `cooldown` 的 2^(N-1) 倍，最长 24 小时。
+    pub fn record_failure(
+        &mut self,
+        url: &str,
+        threshold: u32,
+        cooldown: Duration,
+        now: DateTime<Utc>,
+    ) {
+        let breaker = self.sources.entry(url.to_string()).or_default();
+        breaker.consecutive_failures += 1;
+        if breaker.consecutive_failures < threshold {
+            return;
+        }
+
+        // 先按上限截断再加倍，溢出时取上限
+        let max = Duration::hours(MAX_COOLDOWN_HOURS);
+        let factor = 2_i32.saturating_pow(breaker.trips.min(16));
+        let cooldown = cooldown
+            .min(max)
+            .checked_mul(factor)
+            .map_or(max, |cooldown| cooldown.min(max));
+        breaker.trips += 1;
+        breaker.open_until = Some(now + cooldown);
+        tracing::warn!(
+            "数据源连续失败 {} 次，熔断 {} 分钟（第 {} 次熔断）: {}",
+            breaker.consecutive_failures,
+            cooldown.num_minutes(),
+            breaker.trips,
+            url
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_circuit_breaker_transitions() {
+        let url = "https://a.example/hosts";
+        let cooldown = Duration::minutes(10);
+        let now = Utc::now();
+        let mut breakers = CircuitBreakers::default();
+
+        breakers.record_failure(url, 2, cooldown, now);
+        assert_eq!(breakers.open_until(url, now), None);
+        breakers.record_failure(url, 2, cooldown, now);
+        assert_eq!(breakers.open_until(url, now), Some(now + cooldown));
+
+        // 冷却结束后试探失败，冷却时间加倍
+        let later = now + Duration::minutes(11);
+        assert_eq!(breakers.open_until(url, later), None);
+        breakers.record_failure(url, 2, cooldown, later);
+        assert_eq!(
+            breakers.open_until(url, later),
+            Some(later + Duration::minutes(20))
+        );
+
+        // 熔断状态跨运行保留
+        let dir = tempfile::tempdir().unwrap();
+        let cache_dir = Some(dir.path().to_string_lossy().to_string());
+        breakers.save(&cache_dir).unwrap();
+        let mut loaded = CircuitBreakers::load(&cache_dir).unwrap();
+        assert_eq!(
+            loaded.open_until(url, later),
+            Some(later + Duration::minutes(20))
+        );
+
+        // 冷却时间很长或熔断次数很多时不会溢出，最长 24 小时
+        let mut long = CircuitBreakers::default();
+        for _ in 0..40 {
+            long.record_failure(url, 1, Duration::MAX, now);
+        }
+        assert_eq!(long.open_until(url, now), Some(now + Duration::hours(24)));
+        let mut config = crate::config::Config::from_sources(vec![url.to_string()]);
+        config.circuit_breaker_cooldown_minutes = u64::MAX;
+        assert!(crate::config::validate_config(&config).is_err());
+        assert_eq!(config.circuit_breaker_cooldown(), Duration::hours(24));
+
+        loaded.record_success(url);
+        assert_eq!(loaded.open_until(url, later), None);
+        loaded.record_failure(url, 2, cooldown, later);
+        assert_eq!(loaded.open_until(url, later), None);
+    }
+}
@@ -2,23 +2,632 @@
 //!
 //! 提供配置文件的加载、解析和管理功能。
 
+use crate::dns::validate_resolver;
+use crate::fetcher::load_ca_certificates;
+use crate::git::GitSource;
+use crate::paths::user_config_dir;
+use crate::scheduler::UpdateWindow;
+use crate::validation::{is_valid_domain, is_valid_ip};
 use anyhow::{Context, Result};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::Path;
 
 /// 配置结构体
-#[derive(Debug, Deserialize, Clone)]
+///
+/// 未知字段会导致解析失败，避免拼写错误的配置项被静默忽略。
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
     /// 更新间隔时间（小时）
     #[serde(default = "default_interval")]
     pub update_interval_hours: u64,
-    /// hosts 数据源 URL 列表
-    pub hosts_sources: Vec<String>,
+    /// hosts 数据源列表
+    pub hosts_sources: Vec<HostsSource>,
+    /// 跳过数据源的正则表达式列表：数据源的 URL（首个 URL）匹配任一表达式时不获取该数据源
+    #[serde(default)]
+    pub disabled_source_patterns: Vec<String>,
+    /// 停用的数据源标签：带有其中任一标签的数据源不获取
+    #[serde(default)]
+    pub disabled_tags: Vec<String>,
     /// 更新前是否备份现有 hosts
     #[serde(default = "default_backup")]
     pub backup_before_update: bool,
     /// 备份文件保存路径
     #[serde(default)]
     pub backup_path: Option<String>,
+    /// 事务更新：写入后检查自动管理区域并抽查域名解析，检查失败时恢复写入前的内容
+    #[serde(default)]
+    pub transactional: bool,
+    /// 独立的 include 文件：配置后自动管理区域单独写入该文件，不修改系统 hosts 文件
+    #[serde(default)]
+    pub output_file: Option<String>,
+    /// 条目数量下降保护的最小比例（本次条目数 / 上次条目数）
+    #[serde(default = "default_min_entry_ratio")]
+    pub min_entry_ratio: f64,
+    /// 内容大小下降保护：本次数据源内容总字节数比上次缩小超过该比例时拒绝写入，未配置时不检查
+    #[serde(default)]
+    pub max_shrink_ratio: Option<f64>,
+    /// 规模警告：本次条目总数超过该值时输出警告
+    #[serde(default = "default_warn_entry_count")]
+    pub warn_entry_count: usize,
+    /// 规模警告：本次数据源内容（预处理后）总字节数超过该值时输出警告
+    #[serde(default = "default_warn_content_bytes")]
+    pub warn_content_bytes: u64,
+    /// 规模上限：本次条目总数超过该值时拒绝写入，未配置时不限制
+    #[serde(default)]
+    pub max_entry_count: Option<usize>,
+    /// 规模上限：本次数据源内容（预处理后）总字节数超过该值时拒绝写入，未配置时不限制
+    #[serde(default)]
+    pub max_content_bytes: Option<u64>,
+    /// 缓存与状态文件目录
+    #[serde(default)]
+    pub cache_dir: Option<String>,
+    /// 本地缓存有效期（小时），超过后不再用于启动时的回退写入
+    #[serde(default = "default_cache_ttl_hours")]
+    pub cache_ttl_hours: u64,
+    /// 是否遵循数据源响应的 `Cache-Control: max-age`，未过期时复用缓存内容而不重新获取
+    #[serde(default = "default_honor_cache_control")]
+    pub honor_cache_control: bool,
+    /// 建立连接的超时（秒），未配置时只受请求总超时限制
+    #[serde(default)]
+    pub connect_timeout_secs: Option<u64>,
+    /// 单个请求的总超时（秒，含读取响应内容），未配置时为 `DEFAULT_TIMEOUT_SECS`
+    #[serde(default)]
+    pub read_timeout_secs: Option<u64>,
+    /// 任一数据源获取失败时立即终止本次更新；关闭后跳过失败的数据源，使用其余数据源写入
+    #[serde(default = "default_fail_fast")]
+    pub fail_fast: bool,
+    /// 数据源连续失败多少次后熔断，冷却期内跳过该数据源；未配置时不熔断
+    #[serde(default)]
+    pub circuit_breaker_threshold: Option<u32>,
+    /// 首次熔断的冷却时间（分钟），之后每次熔断加倍，最长 24 小时
+    #[serde(default = "default_circuit_breaker_cooldown_minutes")]
+    pub circuit_breaker_cooldown_minutes: u64,
+    /// 响应内容下载中断时，通过 HTTP Range 请求从断点续传的最大次数，0 表示不续传
+    #[serde(default = "default_max_resume_attempts")]
+    pub max_resume_attempts: u32,
+    /// 一次更新中所有重试（改用后续镜像、断点续传）的总耗时上限（秒），未配置时不限制，0 表示不重试
+    #[serde(default)]
+    pub max_total_retry_secs: Option<u64>,
+    /// 是否跳过 TLS 证书校验（危险，仅用于自签名证书的内部镜像）
+    #[serde(default)]
+    pub tls_insecure: bool,
+    /// 额外信任的 CA 证书路径（PEM 格式）
+    #[serde(default)]
+    pub tls_ca_cert: Option<String>,
+    /// 获取数据源时允许的最低 TLS 版本，未配置时使用 TLS 库的默认值（TLS 1.2）
+    #[serde(default)]
+    pub min_tls_version: Option<TlsVersion>,
+    /// 解析数据源主机名使用的 DNS 服务器，未配置时使用系统 DNS（需启用 `doh` 特性）
+    #[serde(default)]
+    pub dns_resolver: Option<DnsResolverConfig>,
+    /// 是否将国际化域名转换为 punycode
+    #[serde(default)]
+    pub idn: bool,
+    /// 全局白名单文件：每行一个域名或通配符，匹配的域名从所有数据源的条目中移除
+    #[serde(default)]
+    pub allowlist_file: Option<String>,
+    /// 域名规则表：按顺序匹配，首个命中的规则覆盖数据源提供的 IP
+    #[serde(default)]
+    pub sink_rules: Vec<SinkRule>,
+    /// 是否为指向 `0.0.0.0` 或 `127.0.0.1` 的条目同时生成指向 `::` 或 `::1` 的 IPv6 条目
+    #[serde(default)]
+    pub dual_stack_sink: bool,
+    /// hosts 文件为符号链接时是否允许通过链接写入
+    #[serde(default)]
+    pub follow_symlinks: bool,
+    /// 是否规范化管理区域条目的空白（单个空格分隔、去除行尾空白）
+    #[serde(default = "default_normalize_whitespace")]
+    pub normalize_whitespace: bool,
+    /// 是否最小化管理区域：去除所有注释（包括头部与 `# Source:` 标注）和空行，仅保留标记与条目
+    #[serde(default)]
+    pub minimize: bool,
+    /// `minimize` 时保留紧凑的来源标注：区域开头列出 `# Sources: s1=url1, s2=url2`，条目行尾添加 `#s1` 等标签
+    #[serde(default)]
+    pub compact_attribution: bool,
+    /// 管理区域中每个条目行最多包含的域名数量，超出时拆分为多行，未配置时不限制
+    #[serde(default)]
+    pub max_domains_per_line: Option<usize>,
+    /// 管理区域中每个条目行的最大长度（字节），超出时拆分为多行，未配置时不限制
+    #[serde(default)]
+    pub max_line_length: Option<usize>,
+    /// 管理区域的空行布局
+    #[serde(default)]
+    pub section_spacing: SectionSpacing,
+    /// 管理区域在 hosts 文件中的位置
+    #[serde(default)]
+    pub block_position: BlockPosition,
+    /// 将管理区域放在该注释行（如其他工具的结束标记）之后，未找到时按 `block_position` 放置
+    #[serde(default)]
+    pub anchor_after: Option<String>,
+    /// 将管理区域放在该注释行（如其他工具的开始标记）之前，未找到时按 `block_position` 放置
+    #[serde(default)]
+    pub anchor_before: Option<String>,
+    /// 是否去除域名末尾的点（`example.com.` → `example.com`），使两种写法可以去重
+    #[serde(default = "default_strip_trailing_dot")]
+    pub strip_trailing_dot: bool,
+    /// 是否丢弃没有已知公共后缀（如 `foo.invalidtld`）的域名，公共后缀列表下载后缓存在本地
+    #[serde(default)]
+    pub require_public_suffix: bool,
+    /// 多层配置合并时，本层 hosts_sources 与之前各层的合并方式
+    #[serde(default)]
+    pub sources_merge: SourcesMerge,
+    /// 同一域名在不同数据源中指向不同 IP 时的处理策略
+    #[serde(default)]
+    pub conflict_policy: ConflictPolicy,
+    /// 自动管理区域的头部模板，支持 `{last_update}`、`{source_count}`、`{tool_version}` 占位符
+    #[serde(default)]
+    pub header_template: Option<String>,
+    /// 是否在结束标记前添加来源注脚（程序版本、主机名与配置文件路径），便于审计
+    #[serde(default)]
+    pub provenance_footer: bool,
+    /// 加载该配置的文件路径，由调用方在加载后设置，不从配置文件读取
+    #[serde(skip)]
+    pub config_path: Option<String>,
+    /// 覆盖 HTTP 请求的总超时（秒，优先于 `read_timeout_secs`），由命令行设置，不从配置文件读取
+    #[serde(skip)]
+    pub fetch_timeout_secs: Option<u64>,
+    /// 时间戳使用的时区（头部的最后更新时间与备份文件名中的时间）
+    #[serde(default)]
+    pub timestamp_tz: TimestampTz,
+    /// 头部最后更新时间的 strftime 格式
+    #[serde(default = "default_timestamp_format")]
+    pub timestamp_format: String,
+    /// 界面语言，决定默认头部模板等文本
+    #[serde(default)]
+    pub locale: Locale,
+    /// 仅追加模式：写入前检查标记，本工具的区域与其他工具的区域嵌套、交叉或标记异常时拒绝写入
+    #[serde(default)]
+    pub append_only: bool,
+    /// 相同错误的日志去重窗口（分钟），窗口内只输出一次，0 表示不去重
+    #[serde(default = "default_log_dedup_window_minutes")]
+    pub log_dedup_window_minutes: u64,
+    /// hosts 文件超过多少小时未更新时发出警告，未配置时为更新间隔的 2 倍
+    #[serde(default)]
+    pub stale_after_hours: Option<u64>,
+    /// 当前网络按流量计费时是否跳过定时更新（仅在系统能提供计费状态时生效）
+    #[serde(default)]
+    pub skip_on_metered: bool,
+    /// 每天允许定时更新的时间范围（本地时间，如 `02:00-05:00`），到期时不在范围内则推迟
+    #[serde(default)]
+    pub allowed_hours: Option<String>,
+    /// 允许定时更新的星期几（如 `["sat", "sun"]`），为空时不限制
+    #[serde(default)]
+    pub allowed_days: Vec<String>,
+    /// Prometheus 文本格式指标文件路径（供 node_exporter textfile collector 采集），每次更新后写入
+    #[serde(default)]
+    pub metrics_file: Option<String>,
+    /// 变更日志文件路径，每次写入后追加新增与移除的域名
+    #[serde(default)]
+    pub change_log: Option<String>,
+    /// 变更日志的大小上限（字节），达到后轮转为 `<文件名>.1`
+    #[serde(default = "default_change_log_max_bytes")]
+    pub change_log_max_bytes: u64,
+    /// 内置 HTTP 服务监听端口（需启用 `serve` 特性并使用 `--serve` 运行）
+    #[serde(default = "default_serve_port")]
+    pub serve_port: u16,
+    /// `check-update` 查询最新版本的发布 API 地址，未配置时为项目的 GitHub Releases
+    #[serde(default)]
+    pub release_api_url: Option<String>,
+    /// 命名配置档：选中时其中的配置项按分层合并的规则覆盖共享配置
+    #[serde(default, skip_serializing)]
+    pub profiles: std::collections::BTreeMap<String, serde_json::Value>,
+}
+
+impl Config {
+    /// 仅由数据源列表构造配置，其余字段均使用默认值
+    pub fn from_sources(hosts_sources: Vec<String>) -> Self {
+        serde_json::from_value(serde_json::json!({ "hosts_sources": hosts_sources }))
+            .expect("仅包含数据源的配置必然可以解析")
+    }
+
+    /// 从 JSON 解析测试用的配置，不做验证
+    #[cfg(test)]
+    pub(crate) fn for_test(json: &str) -> Self {
+        serde_json::from_str(json).unwrap()
+    }
+
+    /// 由 `allowed_hours` 与 `allowed_days` 构造的允许更新时段，均未配置时为 `None`
+    pub fn update_window(&self) -> Result<Option<UpdateWindow>> {
+        UpdateWindow::parse(self.allowed_hours.as_deref(), &self.allowed_days)
+    }
+
+    /// 相同错误的日志去重窗口
+    pub fn log_dedup_window(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.log_dedup_window_minutes.saturating_mul(60))
+    }
+
+    /// 首次熔断的冷却时间，超出范围的配置按上限处理
+    pub fn circuit_breaker_cooldown(&self) -> chrono::TimeDelta {
+        let minutes = self
+            .circuit_breaker_cooldown_minutes
+            .min(MAX_CIRCUIT_BREAKER_COOLDOWN_MINUTES);
+        chrono::TimeDelta::try_minutes(minutes as i64).unwrap_or(chrono::TimeDelta::MAX)
+    }
+
+    /// 数据源带有的第一个已停用标签，没有时返回 `None`
+    pub fn disabled_tag<'a>(&self, source: &'a HostsSource) -> Option<&'a str> {
+        source
+            .tags()
+            .iter()
+            .find(|tag| self.disabled_tags.contains(tag))
+            .map(String::as_str)
+    }
+
+    /// 应用命令行的 `--enable-tag` / `--disable-tag`：先停用，再启用，同一标签同时指定时以启用为准
+    pub fn apply_tag_overrides(&mut self, enable: &[String], disable: &[String]) {
+        for tag in disable {
+            if !self.disabled_tags.contains(tag) {
+                self.disabled_tags.push(tag.clone());
+            }
+        }
+        self.disabled_tags.retain(|tag| !enable.contains(tag));
+    }
+
+    /// 编译 `disabled_source_patterns`，表达式无效时返回错误
+    pub fn disabled_sources(&self) -> Result<regex::RegexSet> {
+        regex::RegexSet::new(&self.disabled_source_patterns)
+            .context("disabled_source_patterns 中的正则表达式无效")
+    }
+
+    /// hosts 文件被视为过期的小时数
+    pub fn stale_threshold_hours(&self) -> u64 {
+        self.stale_after_hours
+            .unwrap_or(self.update_interval_hours.saturating_mul(2))
+    }
+
+    /// 将命名配置档合并到共享配置之上，返回合并后的配置（不再包含 `profiles`）
+    ///
+    /// 合并规则与分层配置相同：标量与数组整体替换、对象递归合并，
+    /// 配置档中设置 `sources_merge = "append"` 时追加 `hosts_sources`。
+    pub fn with_profile(&self, name: &str) -> Result<Config> {
+        let profile = self.profiles.get(name).ok_or_else(|| {
+            let names: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            anyhow::anyhow!(
+                "配置档不存在: {}（可用的配置档: {}）",
+                name,
+                if names.is_empty() {
+                    "无".to_string()
+                } else {
+                    names.join(", ")
+                }
+            )
+        })?;
+        if !profile.is_object() || profile.get("profiles").is_some() {
+            return Err(anyhow::anyhow!(
+                "配置档 {} 必须是不包含 profiles 的配置项映射",
+                name
+            ));
+        }
+
+        let mut value = serde_json::to_value(self).context("序列化配置失败")?;
+        merge_config_values(&mut value, profile.clone());
+        let mut config: Config = serde_json::from_value(value)
+            .map_err(|e| anyhow::anyhow!("解析配置档失败: {}: {}", name, e))?;
+        config.config_path = self.config_path.clone();
+        config.fetch_timeout_secs = self.fetch_timeout_secs;
+        Ok(config)
+    }
+}
+
+/// hosts 数据源
+///
+/// 可以是单个 URL，也可以是 `{ urls = [...] }` 形式的详细配置：`urls` 为一组镜像，
+/// 获取时按顺序尝试，首个成功的镜像即为该数据源的内容。
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum HostsSource {
+    /// 单个 URL
+    Url(String),
+    /// 带镜像或逐行替换规则的数据源
+    Detailed(SourceSpec),
+}
+
+/// 数据源的详细配置
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct SourceSpec {
+    /// 按尝试顺序排列的 URL（主地址与备用镜像）
+    pub urls: Vec<String>,
+    /// 返回内容的格式，未配置时为 hosts 纯文本
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<SourceFormat>,
+    /// `format = "json"` 时域名数组所在位置的 JSON Pointer（如 `/data/domains`），未配置时为整个文档
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json_pointer: Option<String>,
+    /// 验证前对每行应用的正则表达式
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line_regex: Option<String>,
+    /// 替换 `line_regex` 首个匹配的内容，支持 `$1`、`${name}` 等捕获组引用，未配置时删除匹配内容
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line_replace: Option<String>,
+    /// 下载内容（预处理前）应有的 SHA-256，十六进制表示；不一致时拒绝使用该数据源
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// 两次获取之间的最短间隔（如 `6h`、`1d`），未到间隔时复用本地缓存中该数据源的内容
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_refresh_interval: Option<String>,
+    /// 只保留验证后的前 N 条条目，便于试用或限制条目过多的数据源
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_entries: Option<usize>,
+    /// 分类标签（如 `ads`、`malware`），可通过 `disabled_tags` 或 `--disable-tag` 按标签批量停用
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+/// 数据源返回内容的格式
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceFormat {
+    /// hosts 纯文本
+    Hosts,
+    /// 域名字符串组成的 JSON 数组，每个域名转换为指向 0.0.0.0 的条目
+    Json,
+}
+
+/// 编译后的逐行替换规则
+#[derive(Debug, Clone)]
+pub struct LineRewrite {
+    regex: regex::Regex,
+    replace: String,
+}
+
+impl LineRewrite {
+    /// 对内容逐行应用替换规则，每行只替换首个匹配
+    pub fn apply(&self, content: &str) -> String {
+        let mut result = String::with_capacity(content.len());
+        for line in content.lines() {
+            result.push_str(&self.regex.replace(line, self.replace.as_str()));
+            result.push('\n');
+        }
+        result
+    }
+}
+
+impl HostsSource {
+    /// 数据源标识（首个 URL），用于日志、缓存与状态记录
+    pub fn url(&self) -> &str {
+        self.urls().first().map_or("", String::as_str)
+    }
+
+    /// 按尝试顺序排列的所有 URL
+    pub fn urls(&self) -> &[String] {
+        match self {
+            HostsSource::Url(url) => std::slice::from_ref(url),
+            HostsSource::Detailed(spec) => &spec.urls,
+        }
+    }
+
+    /// 固定的内容 SHA-256
+    pub fn sha256(&self) -> Option<&str> {
+        match self {
+            HostsSource::Url(_) => None,
+            HostsSource::Detailed(spec) => spec.sha256.as_deref(),
+        }
+    }
+
+    /// `format = "json"` 时域名数组的 JSON Pointer（未配置时为空字符串，即整个文档），
+    /// 其他格式返回 `None`
+    pub fn json_pointer(&self) -> Option<&str> {
+        match self {
+            HostsSource::Detailed(spec) if spec.format == Some(SourceFormat::Json) => {
+                Some(spec.json_pointer.as_deref().unwrap_or_default())
+            }
+            _ => None,
+        }
+    }
+
+    /// 分类标签
+    pub fn tags(&self) -> &[String] {
+        match self {
+            HostsSource::Url(_) => &[],
+            HostsSource::Detailed(spec) => &spec.tags,
+        }
+    }
+
+    /// 保留的最大条目数量
+    pub fn max_entries(&self) -> Option<usize> {
+        match self {
+            HostsSource::Url(_) => None,
+            HostsSource::Detailed(spec) => spec.max_entries,
+        }
+    }
+
+    /// 两次获取之间的最短间隔（小时），未配置时返回 `None`
+    ///
+    /// 间隔不能超过 [`MAX_MIN_REFRESH_HOURS`]。
+    pub fn min_refresh_hours(&self) -> Result<Option<u64>> {
+        let HostsSource::Detailed(spec) = self else {
+            return Ok(None);
+        };
+
+        spec.min_refresh_interval
+            .as_deref()
+            .map(|interval| {
+                parse_interval_hours(interval)
+                    .and_then(|hours| {
+                        if hours > MAX_MIN_REFRESH_HOURS {
+                            return Err(anyhow::anyhow!(
+                                "间隔不能超过 {} 小时（365 天）: {}",
+                                MAX_MIN_REFRESH_HOURS,
+                                interval
+                            ));
+                        }
+                        Ok(hours)
+                    })
+                    .with_context(|| {
+                        format!("数据源 {} 的 min_refresh_interval 无效", self.url())
+                    })
+            })
+            .transpose()
+    }
+
+    /// 编译数据源的逐行替换规则，未配置时返回 `None`
+    pub fn line_rewrite(&self) -> Result<Option<LineRewrite>> {
+        let HostsSource::Detailed(spec) = self else {
+            return Ok(None);
+        };
+
+        match (&spec.line_regex, &spec.line_replace) {
+            (None, None) => Ok(None),
+            (None, Some(_)) => Err(anyhow::anyhow!(
+                "数据源 {} 配置了 line_replace 但缺少 line_regex",
+                self.url()
+            )),
+            (Some(pattern), replace) => {
+                let regex = regex::Regex::new(pattern).with_context(|| {
+                    format!("数据源 {} 的 line_regex 无效: {}", self.url(), pattern)
+                })?;
+                Ok(Some(LineRewrite {
+                    regex,
+                    replace: replace.clone().unwrap_or_default(),
+                }))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for HostsSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.urls().join(" | "))
+    }
+}
+
+/// 多层配置合并时数据源列表的合并方式
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SourcesMerge {
+    /// 替换之前各层的数据源
+    #[default]
+    Replace,
+    /// 追加到之前各层的数据源之后（跳过重复 URL）
+    Append,
+}
+
+/// 自动管理区域的空行布局
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SectionSpacing {
+    /// 头部之后及各数据源区块之后各空一行
+    #[default]
+    Normal,
+    /// 不插入空行
+    Compact,
+}
+
+/// 自动管理区域在 hosts 文件中的位置
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockPosition {
+    /// 置于用户内容之前，使管理的条目优先生效
+    Top,
+    /// 追加在用户内容之后
+    #[default]
+    Bottom,
+}
+
+/// 时间戳使用的时区
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TimestampTz {
+    /// 本机时区
+    #[default]
+    Local,
+    /// UTC
+    Utc,
+}
+
+impl TimestampTz {
+    /// 该时区的当前时间
+    pub fn now(self) -> chrono::DateTime<chrono::FixedOffset> {
+        match self {
+            TimestampTz::Local => chrono::Local::now().fixed_offset(),
+            TimestampTz::Utc => chrono::Utc::now().fixed_offset(),
+        }
+    }
+}
+
+/// TLS 协议版本
+///
+/// 使用的 TLS 库不支持 TLS 1.2 以下的版本，因此只能配置为 1.2 或 1.3。
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TlsVersion {
+    /// TLS 1.2
+    #[serde(rename = "1.2")]
+    Tls12,
+    /// TLS 1.3
+    #[serde(rename = "1.3")]
+    Tls13,
+}
+
+impl TlsVersion {
+    /// 配置中的写法，如 `1.2`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TlsVersion::Tls12 => "1.2",
+            TlsVersion::Tls13 => "1.3",
+        }
+    }
+}
+
+/// 自定义 DNS 解析器
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct DnsResolverConfig {
+    /// DNS 服务器地址（IP 或 IP:端口），按顺序尝试
+    pub servers: Vec<String>,
+    /// 查询协议
+    #[serde(default)]
+    pub protocol: DnsProtocol,
+    /// DoH 服务器证书中的域名，`protocol = "https"` 时必填
+    #[serde(default)]
+    pub tls_name: Option<String>,
+}
+
+/// DNS 查询协议
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DnsProtocol {
+    /// 传统 UDP 查询
+    #[default]
+    Udp,
+    /// DNS-over-HTTPS
+    Https,
+}
+
+/// 界面语言
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    /// 中文
+    #[default]
+    Zh,
+    /// 英文
+    En,
+}
+
+/// 域名 IP 冲突的处理策略
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    /// 采用最后一个数据源中的 IP
+    #[default]
+    Last,
+    /// 采用第一个数据源中的 IP
+    First,
+    /// 存在冲突时中止本次更新
+    Error,
+}
+
+/// 域名规则：将匹配的域名指向指定 IP
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SinkRule {
+    /// 域名匹配模式，`*.example.com` 匹配该域名及其所有子域名，否则精确匹配
+    pub pattern: String,
+    /// 目标 IP
+    pub ip: String,
 }
 
 fn default_interval() -> u64 {
@@ -29,79 +638,450 @@ fn default_backup() -> bool {
     true
 }
 
+fn default_min_entry_ratio() -> f64 {
+    0.5
+}
+
+fn default_warn_entry_count() -> usize {
+    300_000
+}
+
+fn default_warn_content_bytes() -> u64 {
+    16 * 1024 * 1024
+}
+
+fn default_cache_ttl_hours() -> u64 {
+    168
+}
+
+fn default_honor_cache_control() -> bool {
+    true
+}
+
+fn default_fail_fast() -> bool {
+    true
+}
+
+/// 更新间隔 `update_interval_hours` 的上限（小时）
+pub const MAX_UPDATE_INTERVAL_HOURS: u64 = 365 * 24;
+
+/// 本地缓存有效期 `cache_ttl_hours` 的上限（小时）
+pub const MAX_CACHE_TTL_HOURS: u64 = 365 * 24;
+
+/// 数据源 `min_refresh_interval` 的上限（小时）
+pub const MAX_MIN_REFRESH_HOURS: u64 = 365 * 24;
+
+/// 首次熔断冷却时间的上限（分钟），与熔断冷却时间的上限 24 小时一致
+pub const MAX_CIRCUIT_BREAKER_COOLDOWN_MINUTES: u64 = 24 * 60;
+
+fn default_circuit_breaker_cooldown_minutes() -> u64 {
+    30
+}
+
+fn default_max_resume_attempts() -> u32 {
+    3
+}
+
+fn default_serve_port() -> u16 {
+    8080
+}
+
+fn default_normalize_whitespace() -> bool {
+    true
+}
+
+fn default_timestamp_format() -> String {
+    crate::hosts::LAST_UPDATE_FORMAT.to_string()
+}
+
+fn default_strip_trailing_dot() -> bool {
+    true
+}
+
+fn default_log_dedup_window_minutes() -> u64 {
+    60
+}
+
+fn default_change_log_max_bytes() -> u64 {
+    1024 * 1024
+}
+
 /// 加载配置
 ///
 /// 按优先级顺序查找配置文件：
 /// 1. 当前目录 (config.json/toml/yaml)
-/// 2. 用户配置目录 (~/.config/hosts_updater/)
+/// 2. 用户配置目录 (`$XDG_CONFIG_HOME/hosts_updater/`，默认 ~/.config/hosts_updater/)
 /// 3. 系统配置目录 (/etc/hosts_updater/)
-pub fn load_config() -> Result<Config> {
-    // 1. 尝试当前目录
-    if let Some(config) = try_load_config("./config")? {
-        return Ok(config);
+///
+/// 每个位置按 JSON → TOML → YAML 的顺序查找，找到第一个配置文件即停止；
+/// 指定 `format` 时每个位置只查找该格式的文件，在较慢的网络文件系统上减少无谓的文件探测。
+pub fn load_config(format: Option<ConfigFormat>) -> Result<Config> {
+    for path in config_search_paths() {
+        if let Some(config) = try_load_config(&path, format)? {
+            return Ok(config);
+        }
     }
 
-    // 2. 尝试用户配置目录
+    Err(ConfigNotFound {
+        searched: config_search_paths(),
+        formats: search_formats(format).to_vec(),
+    }
+    .into())
+}
+
+/// 按查找顺序未找到任何配置文件
+///
+/// 作为 `load_config` 与 `load_merged_config` 返回的错误，调用方可通过
+/// `downcast_ref::<ConfigNotFound>()` 识别，给出生成配置等引导而不是直接退出。
+#[derive(Debug, Clone)]
+pub struct ConfigNotFound {
+    /// 查找过的路径（不含扩展名）
+    pub searched: Vec<String>,
+    /// 每个路径下查找过的格式
+    pub formats: Vec<ConfigFormat>,
+}
+
+impl std::fmt::Display for ConfigNotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let extensions: Vec<&str> = self.formats.iter().map(|format| format.extension()).collect();
+        let searched: Vec<String> = self
+            .searched
+            .iter()
+            .map(|path| match extensions.as_slice() {
+                [extension] => format!("{}.{}", path, extension),
+                _ => format!("{}.{{{}}}", path, extensions.join(",")),
+            })
+            .collect();
+        write!(f, "未找到配置文件（已查找: {}）", searched.join("、"))
+    }
+}
+
+impl std::error::Error for ConfigNotFound {}
+
+/// 查找配置文件时尝试的格式（按优先级排列），指定格式时只尝试该格式
+fn search_formats(format: Option<ConfigFormat>) -> &'static [ConfigFormat] {
+    match format {
+        Some(ConfigFormat::Json) => &[ConfigFormat::Json],
+        Some(ConfigFormat::Toml) => &[ConfigFormat::Toml],
+        Some(ConfigFormat::Yaml) => &[ConfigFormat::Yaml],
+        None => &ConfigFormat::ALL,
+    }
+}
+
+/// 按优先级从高到低返回配置文件搜索路径（不含扩展名）
+fn config_search_paths() -> Vec<String> {
+    let mut paths = vec!["./config".to_string()];
+
+    if let Some(dir) = user_config_dir() {
+        paths.push(dir.join("config").to_string_lossy().to_string());
+    }
+    // 其他平台的用户配置目录不在 ~/.config 时，仍兼容旧版本使用的位置
     if let Some(dir) = directories::UserDirs::new() {
-        let user_config_path = dir.home_dir().join(".config/hosts_updater/config");
-        if let Some(config) = try_load_config(&user_config_path.to_string_lossy())? {
-            return Ok(config);
+        let legacy = dir.home_dir().join(".config/hosts_updater/config");
+        let legacy = legacy.to_string_lossy().to_string();
+        if !paths.contains(&legacy) {
+            paths.push(legacy);
         }
     }
 
-    // 3. 尝试系统配置目录
-    if let Some(config) = try_load_config("/etc/hosts_updater/config")? {
-        return Ok(config);
+    paths.push("/etc/hosts_updater/config".to_string());
+    paths
+}
+
+/// 分层加载并合并配置
+///
+/// 按优先级从低到高（系统 → 用户 → 当前目录）依次合并所有找到的配置文件，
+/// 后加载的配置逐字段覆盖之前的配置：
+/// - 标量与数组字段整体替换（如 `sink_rules`）
+/// - 对象字段递归合并
+/// - `hosts_sources` 根据该层的 `sources_merge` 决定替换（默认）或追加
+///
+/// 指定 `format` 时每个位置只查找该格式的文件。
+pub fn load_merged_config(format: Option<ConfigFormat>) -> Result<Config> {
+    let mut paths = config_search_paths();
+    paths.reverse();
+    load_merged_config_from(&paths, format)
+}
+
+/// 按给定顺序（优先级从低到高）合并配置文件
+fn load_merged_config_from(paths: &[String], format: Option<ConfigFormat>) -> Result<Config> {
+    let mut merged: Option<serde_json::Value> = None;
+
+    for path in paths {
+        let Some((file, value)) = try_load_config_value(path, format)? else {
+            continue;
+        };
+        tracing::info!("合并配置文件: {}", file);
+
+        match merged.as_mut() {
+            Some(base) => merge_config_values(base, value),
+            None => merged = Some(value),
+        }
     }
 
-    Err(anyhow::anyhow!("未找到配置文件"))
+    let merged = merged.ok_or_else(|| ConfigNotFound {
+        searched: paths.to_vec(),
+        formats: search_formats(format).to_vec(),
+    })?;
+    serde_json::from_value(merged).map_err(|e| anyhow::anyhow!("解析合并后的配置失败: {}", e))
+}
+
+/// 尝试将指定路径的配置文件加载为通用值，返回实际文件路径和内容
+fn try_load_config_value(
+    path: &str,
+    format: Option<ConfigFormat>,
+) -> Result<Option<(String, serde_json::Value)>> {
+    let Some((file, format)) = existing_config_file(path, format) else {
+        return Ok(None);
+    };
+
+    let content = read_config_file(&file)?;
+    let value = match format {
+        ConfigFormat::Json => serde_json::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("解析 JSON 配置失败: {}: {}", file, e))?,
+        ConfigFormat::Toml => {
+            let value: toml::Value = toml::from_str(&content)
+                .map_err(|e| anyhow::anyhow!("解析 TOML 配置失败: {}: {}", file, e))?;
+            serde_json::to_value(value)
+                .with_context(|| format!("转换 TOML 配置失败: {}", file))?
+        }
+        ConfigFormat::Yaml => {
+            let value = parse_yaml_value(&content, &file)?;
+            serde_json::to_value(value)
+                .with_context(|| format!("转换 YAML 配置失败: {}", file))?
+        }
+    };
+    Ok(Some((file, value)))
+}
+
+/// 将上层配置合并到基础配置中
+fn merge_config_values(base: &mut serde_json::Value, overlay: serde_json::Value) {
+    let serde_json::Value::Object(overlay) = overlay else {
+        *base = overlay;
+        return;
+    };
+    let Some(base) = base.as_object_mut() else {
+        return;
+    };
+
+    let append_sources =
+        overlay.get("sources_merge").and_then(|v| v.as_str()) == Some("append");
+
+    for (key, value) in overlay {
+        if key == "hosts_sources"
+            && append_sources
+            && let (Some(existing), serde_json::Value::Array(new_sources)) =
+                (base.get_mut(&key).and_then(|v| v.as_array_mut()), &value)
+        {
+            for source in new_sources {
+                if !existing.contains(source) {
+                    existing.push(source.clone());
+                }
+            }
+            continue;
+        }
+
+        match base.get_mut(&key) {
+            Some(existing) if existing.is_object() && value.is_object() => {
+                merge_config_values(existing, value);
+            }
+            _ => {
+                base.insert(key, value);
+            }
+        }
+    }
 }
 
 /// 尝试加载指定路径的配置
-fn try_load_config(path: &str) -> Result<Option<Config>> {
-    // 尝试 JSON 格式
-    if let Ok(config) = load_json_config(&format!("{}.json", path)) {
-        return Ok(Some(config));
+///
+/// 文件不存在时继续尝试下一种格式；文件存在但解析失败时直接返回错误，
+/// 避免配置错误被静默跳过。
+fn try_load_config(path: &str, format: Option<ConfigFormat>) -> Result<Option<Config>> {
+    match existing_config_file(path, format) {
+        Some((file, _)) => load_config_file(&file).map(Some),
+        None => Ok(None),
     }
+}
 
-    // 尝试 TOML 格式
-    if let Ok(config) = load_toml_config(&format!("{}.toml", path)) {
-        return Ok(Some(config));
+/// 读取并解析单个配置文件
+fn load_config_file(path: &str) -> Result<Config> {
+    let content = read_config_file(path)?;
+    parse_config_content(path, &content)
+}
+
+/// 按 JSON → TOML → YAML 的顺序返回指定路径（不含扩展名）下存在的配置文件及其格式
+///
+/// 只检查文件是否存在，不读取内容；指定 `format` 时只检查该格式。
+fn existing_config_file(
+    path: &str,
+    format: Option<ConfigFormat>,
+) -> Option<(String, ConfigFormat)> {
+    search_formats(format)
+        .iter()
+        .map(|&format| (format!("{}.{}", path, format.extension()), format))
+        .find(|(file, _)| Path::new(file).exists())
+}
+
+/// 查找当前生效的配置文件路径（与 `load_config` 的查找顺序一致）
+pub fn find_config_file(format: Option<ConfigFormat>) -> Option<String> {
+    config_search_paths()
+        .iter()
+        .find_map(|path| existing_config_file(path, format))
+        .map(|(file, _)| file)
+}
+
+/// 读取配置文件内容
+fn read_config_file(path: &str) -> Result<String> {
+    std::fs::read_to_string(path).with_context(|| format!("读取配置文件失败: {}", path))
+}
+
+/// 按文件扩展名解析配置内容
+pub fn parse_config_content(path: &str, content: &str) -> Result<Config> {
+    match ConfigFormat::from_path(path) {
+        Some(format) => format.parse(content, path),
+        None => Err(anyhow::anyhow!("不支持的配置文件格式: {}", path)),
     }
+}
 
-    // 尝试 YAML 格式
-    if let Ok(config) = load_yaml_config(&format!("{}.yaml", path)) {
-        return Ok(Some(config));
+/// 从指定路径加载配置，`-` 表示从标准输入读取
+///
+/// 未指定格式时，文件按扩展名判断；标准输入依次尝试 JSON、TOML、YAML，
+/// 全部失败时返回包含各格式错误的汇总。
+pub fn load_config_from(path: &str, format: Option<ConfigFormat>) -> Result<Config> {
+    if path == STDIN_PATH {
+        let mut content = String::new();
+        std::io::stdin()
+            .read_to_string(&mut content)
+            .context("从标准输入读取配置失败")?;
+        return parse_config_with_format(&content, format, "<stdin>");
     }
 
-    Ok(None)
+    let content = read_config_file(path)?;
+    match format {
+        Some(format) => format.parse(&content, path),
+        None => parse_config_content(path, &content),
+    }
 }
 
-/// 加载 JSON 格式配置
-fn load_json_config(path: &str) -> Result<Config> {
-    let content =
-        std::fs::read_to_string(path).with_context(|| format!("读取配置文件失败: {}", path))?;
-    serde_json::from_str(&content).with_context(|| format!("解析 JSON 配置失败: {}", path))
+/// 按指定格式解析配置，未指定时依次尝试所有格式
+pub fn parse_config_with_format(
+    content: &str,
+    format: Option<ConfigFormat>,
+    name: &str,
+) -> Result<Config> {
+    if let Some(format) = format {
+        return format.parse(content, name);
+    }
+
+    let mut errors = Vec::new();
+    for format in ConfigFormat::ALL {
+        match format.parse(content, name) {
+            Ok(config) => return Ok(config),
+            Err(e) => errors.push(format!("  {}", e)),
+        }
+    }
+
+    Err(anyhow::anyhow!(
+        "无法识别配置格式，可通过 --config-format 指定: {}\n{}",
+        name,
+        errors.join("\n")
+    ))
 }
 
-/// 加载 TOML 格式配置
-fn load_toml_config(path: &str) -> Result<Config> {
-    let content =
-        std::fs::read_to_string(path).with_context(|| format!("读取配置文件失败: {}", path))?;
-    toml::from_str(&content).with_context(|| format!("解析 TOML 配置失败: {}", path))
+/// 表示标准输入的配置路径
+pub const STDIN_PATH: &str = "-";
+
+/// 配置文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Json,
+    Toml,
+    Yaml,
 }
 
-/// 加载 YAML 格式配置
-fn load_yaml_config(path: &str) -> Result<Config> {
-    let content =
-        std::fs::read_to_string(path).with_context(|| format!("读取配置文件失败: {}", path))?;
-    let docs = yaml_rust::YamlLoader::load_from_str(&content)
+impl ConfigFormat {
+    /// 未指定格式时的尝试顺序
+    const ALL: [ConfigFormat; 3] = [ConfigFormat::Json, ConfigFormat::Toml, ConfigFormat::Yaml];
+
+    /// 该格式的文件扩展名
+    pub fn extension(self) -> &'static str {
+        match self {
+            ConfigFormat::Json => "json",
+            ConfigFormat::Toml => "toml",
+            ConfigFormat::Yaml => "yaml",
+        }
+    }
+
+    /// 该格式的示例配置，列出所有配置项及其默认值
+    ///
+    /// TOML 与 YAML 示例带有逐项说明的注释；JSON 不支持注释，未配置的可选项以 `null` 表示。
+    pub fn sample(self) -> &'static str {
+        match self {
+            ConfigFormat::Json => include_str!("sample/config.json"),
+            ConfigFormat::Toml => include_str!("sample/config.toml"),
+            ConfigFormat::Yaml => include_str!("sample/config.yaml"),
+        }
+    }
+
+    /// 根据文件扩展名判断格式
+    pub fn from_path(path: &str) -> Option<Self> {
+        Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| ext.parse().ok())
+    }
+
+    /// 根据 HTTP Content-Type 判断格式（如 `application/json`、`application/toml`、`text/yaml`）
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let mime = content_type.split(';').next()?.trim().to_ascii_lowercase();
+        let subtype = mime.rsplit(['/', '+']).next()?;
+        match subtype.strip_prefix("x-").unwrap_or(subtype) {
+            "json" => Some(ConfigFormat::Json),
+            "toml" => Some(ConfigFormat::Toml),
+            "yaml" | "yml" => Some(ConfigFormat::Yaml),
+            _ => None,
+        }
+    }
+
+    /// 按该格式解析配置内容，`name` 用于错误信息
+    fn parse(self, content: &str, name: &str) -> Result<Config> {
+        match self {
+            ConfigFormat::Json => serde_json::from_str(content)
+                .map_err(|e| anyhow::anyhow!("解析 JSON 配置失败: {}: {}", name, e)),
+            ConfigFormat::Toml => toml::from_str(content)
+                .map_err(|e| anyhow::anyhow!("解析 TOML 配置失败: {}: {}", name, e)),
+            ConfigFormat::Yaml => {
+                let value = parse_yaml_value(content, name)?;
+                serde_yaml::from_value(value)
+                    .map_err(|e| anyhow::anyhow!("转换 YAML 配置失败: {}: {}", name, e))
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for ConfigFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "json" => Ok(ConfigFormat::Json),
+            "toml" => Ok(ConfigFormat::Toml),
+            "yaml" | "yml" => Ok(ConfigFormat::Yaml),
+            _ => Err(anyhow::anyhow!("不支持的配置格式: {}（可选 json、toml、yaml）", s)),
+        }
+    }
+}
+
+/// 解析 YAML 内容为 serde_yaml::Value
+fn parse_yaml_value(content: &str, path: &str) -> Result<serde_yaml::Value> {
+    let docs = yaml_rust::YamlLoader::load_from_str(content)
         .with_context(|| format!("解析 YAML 配置失败: {}", path))?;
-    let doc = &docs[0];
+    let doc = docs
+        .first()
+        .ok_or_else(|| anyhow::anyhow!("YAML 配置为空: {}", path))?;
 
     // 将 yaml_rust::Yaml 转换为 serde_yaml::Value
-    let value = convert_yaml_to_value(doc);
-    serde_yaml::from_value(value).with_context(|| format!("转换 YAML 配置失败: {}", path))
+    Ok(convert_yaml_to_value(doc))
 }
 
 /// 将 yaml_rust::Yaml 转换为 serde_yaml::Value
@@ -134,17 +1114,570 @@ fn convert_yaml_to_value(yaml: &yaml_rust::Yaml) -> serde_yaml::Value {
     }
 }
 
+/// 解析更新间隔，返回小时数
+///
+/// 支持 `6`、`6h`、`1d` 等形式，不带单位时按小时计算。
+pub fn parse_interval_hours(value: &str) -> Result<u64> {
+    let value = value.trim();
+    let (number, multiplier) = if let Some(hours) = value.strip_suffix('h') {
+        (hours, 1)
+    } else if let Some(days) = value.strip_suffix('d') {
+        (days, 24)
+    } else {
+        (value, 1)
+    };
+
+    let hours = number
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .filter(|&hours| hours > 0)
+        .ok_or_else(|| anyhow::anyhow!("无效的更新间隔: {}（示例: 6h、1d）", value))?;
+
+    Ok(hours)
+}
+
 /// 检查配置是否有效
 pub fn validate_config(config: &Config) -> Result<()> {
+    if config.update_interval_hours == 0 {
+        return Err(anyhow::anyhow!("update_interval_hours 必须大于 0"));
+    }
+    if config.update_interval_hours > MAX_UPDATE_INTERVAL_HOURS {
+        return Err(anyhow::anyhow!(
+            "update_interval_hours 不能超过 {}（365 天）",
+            MAX_UPDATE_INTERVAL_HOURS
+        ));
+    }
+
     if config.hosts_sources.is_empty() {
         return Err(anyhow::anyhow!("hosts_sources 不能为空"));
     }
 
-    for url in &config.hosts_sources {
-        if !url.starts_with("http://") && !url.starts_with("https://") {
-            return Err(anyhow::anyhow!("无效的 URL: {}", url));
+    config.disabled_sources()?;
+
+    if config.compact_attribution && !config.minimize {
+        return Err(anyhow::anyhow!(
+            "compact_attribution 需要同时启用 minimize（未最小化的区域已带有 # Source: 标注）"
+        ));
+    }
+
+    if let Some(path) = &config.output_file {
+        if path.trim().is_empty() {
+            return Err(anyhow::anyhow!("output_file 不能为空"));
         }
+        if Path::new(path) == crate::hosts::get_hosts_path() {
+            return Err(anyhow::anyhow!(
+                "output_file 不能是系统 hosts 文件: {}（省略该配置项即写入系统 hosts 文件）",
+                path
+            ));
+        }
+        if config.transactional {
+            return Err(anyhow::anyhow!(
+                "transactional 仅适用于系统 hosts 文件，不能与 output_file 同时使用"
+            ));
+        }
+    }
+
+    if config.circuit_breaker_threshold == Some(0) {
+        return Err(anyhow::anyhow!("circuit_breaker_threshold 必须大于 0"));
+    }
+    if config.cache_ttl_hours > MAX_CACHE_TTL_HOURS {
+        return Err(anyhow::anyhow!(
+            "cache_ttl_hours 不能超过 {}（365 天）",
+            MAX_CACHE_TTL_HOURS
+        ));
+    }
+
+    let cooldown = config.circuit_breaker_cooldown_minutes;
+    if !(1..=MAX_CIRCUIT_BREAKER_COOLDOWN_MINUTES).contains(&cooldown) {
+        return Err(anyhow::anyhow!(
+            "circuit_breaker_cooldown_minutes 必须在 1 到 {} 之间",
+            MAX_CIRCUIT_BREAKER_COOLDOWN_MINUTES
+        ));
+    }
+
+    for source in &config.hosts_sources {
+        if source.urls().is_empty() {
+            return Err(anyhow::anyhow!("数据源的镜像列表 urls 不能为空"));
+        }
+        for url in source.urls() {
+            if GitSource::is_git_url(url) {
+                GitSource::parse(url)?;
+                if !cfg!(feature = "git") {
+                    return Err(anyhow::anyhow!("使用 Git 数据源需要启用 git 特性: {}", url));
+                }
+            } else if !url.starts_with("http://") && !url.starts_with("https://") {
+                return Err(anyhow::anyhow!("无效的 URL: {}", url));
+            }
+        }
+        source.line_rewrite()?;
+        source.min_refresh_hours()?;
+        if source.tags().iter().any(|tag| tag.trim().is_empty()) {
+            return Err(anyhow::anyhow!("数据源 {} 的 tags 中有空标签", source.url()));
+        }
+        if let HostsSource::Detailed(spec) = source
+            && let Some(pointer) = &spec.json_pointer
+        {
+            if source.json_pointer().is_none() {
+                return Err(anyhow::anyhow!(
+                    "数据源 {} 配置了 json_pointer 但 format 不是 json",
+                    source.url()
+                ));
+            }
+            if !pointer.is_empty() && !pointer.starts_with('/') {
+                return Err(anyhow::anyhow!(
+                    "数据源 {} 的 json_pointer 必须为空或以 / 开头: {}",
+                    source.url(),
+                    pointer
+                ));
+            }
+        }
+        if source.max_entries() == Some(0) {
+            return Err(anyhow::anyhow!("数据源 {} 的 max_entries 必须大于 0", source.url()));
+        }
+        if let Some(sha256) = source.sha256()
+            && (sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()))
+        {
+            return Err(anyhow::anyhow!(
+                "数据源 {} 的 sha256 必须是 64 位十六进制字符串: {}",
+                source.url(),
+                sha256
+            ));
+        }
+    }
+
+    for rule in &config.sink_rules {
+        let domain = rule.pattern.strip_prefix("*.").unwrap_or(&rule.pattern);
+        if !is_valid_domain(domain) {
+            return Err(anyhow::anyhow!("无效的规则模式: {}", rule.pattern));
+        }
+        if !is_valid_ip(&rule.ip) {
+            return Err(anyhow::anyhow!(
+                "规则 {} 的 IP 无效: {}",
+                rule.pattern,
+                rule.ip
+            ));
+        }
+    }
+
+    if let Some(path) = &config.tls_ca_cert {
+        load_ca_certificates(path)?;
+    }
+
+    if let Some(resolver) = &config.dns_resolver {
+        validate_resolver(resolver)?;
+    }
+
+    if config.stale_after_hours == Some(0) {
+        return Err(anyhow::anyhow!("stale_after_hours 必须大于 0"));
+    }
+
+    if let Some(template) = &config.header_template
+        && let Some(line) = template
+            .lines()
+            .find(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+    {
+        return Err(anyhow::anyhow!(
+            "header_template 的每一行都必须以 # 开头: {}",
+            line
+        ));
+    }
+
+    if config.timestamp_format.trim().is_empty()
+        || config.timestamp_format.contains('\n')
+        || chrono::format::StrftimeItems::new(&config.timestamp_format)
+            .any(|item| item == chrono::format::Item::Error)
+    {
+        return Err(anyhow::anyhow!(
+            "timestamp_format 不是有效的单行 strftime 格式: {:?}",
+            config.timestamp_format
+        ));
+    }
+
+    if !(0.0..=1.0).contains(&config.min_entry_ratio) {
+        return Err(anyhow::anyhow!(
+            "min_entry_ratio 必须在 0 到 1 之间: {}",
+            config.min_entry_ratio
+        ));
+    }
+
+    for name in config.profiles.keys() {
+        let profile = config.with_profile(name)?;
+        validate_config(&profile).with_context(|| format!("配置档 {} 无效", name))?;
+    }
+
+    if let Some(ratio) = config.max_shrink_ratio
+        && !(0.0..=1.0).contains(&ratio)
+    {
+        return Err(anyhow::anyhow!("max_shrink_ratio 必须在 0 到 1 之间: {}", ratio));
+    }
+
+    if config.max_domains_per_line == Some(0) || config.max_line_length == Some(0) {
+        return Err(anyhow::anyhow!("max_domains_per_line 与 max_line_length 必须大于 0"));
+    }
+
+    if config.connect_timeout_secs == Some(0) || config.read_timeout_secs == Some(0) {
+        return Err(anyhow::anyhow!("connect_timeout_secs 与 read_timeout_secs 必须大于 0"));
+    }
+
+    if config.max_entry_count == Some(0) || config.max_content_bytes == Some(0) {
+        return Err(anyhow::anyhow!("max_entry_count 与 max_content_bytes 必须大于 0"));
+    }
+
+    config.update_window()?;
+
+    if config.change_log_max_bytes == 0 {
+        return Err(anyhow::anyhow!("change_log_max_bytes 必须大于 0"));
+    }
+
+    if config.anchor_after.is_some() && config.anchor_before.is_some() {
+        return Err(anyhow::anyhow!("anchor_after 与 anchor_before 不能同时配置"));
+    }
+    if let Some(anchor) = config.anchor_after.as_ref().or(config.anchor_before.as_ref())
+        && (anchor.trim().is_empty() || anchor.contains('\n'))
+    {
+        return Err(anyhow::anyhow!("锚点必须是非空的单行文本: {:?}", anchor));
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_config(dir: &tempfile::TempDir, name: &str, content: &str) -> String {
+        let path = dir.path().join(name);
+        std::fs::write(&path, content).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn test_load_json_config_unknown_field() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_config(
+            &dir,
+            "config.json",
+            r#"{ "hosts_sources": ["https://example.com/hosts"], "update_interval_hour": 1 }"#,
+        );
+
+        let err = load_config_file(&path).unwrap_err().to_string();
+        assert!(err.contains("update_interval_hour"), "{}", err);
+    }
+
+    #[test]
+    fn test_load_toml_config_unknown_field() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_config(
+            &dir,
+            "config.toml",
+            "hosts_sources = [\"https://example.com/hosts\"]\nbackup_befor_update = false\n",
+        );
+
+        let err = load_config_file(&path).unwrap_err().to_string();
+        assert!(err.contains("backup_befor_update"), "{}", err);
+    }
+
+    #[test]
+    fn test_load_yaml_config_unknown_field() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_config(
+            &dir,
+            "config.yaml",
+            "hosts_sources:\n  - https://example.com/hosts\nsink_rules:\n  - pattern: a.com\n    addr: 0.0.0.0\n",
+        );
+
+        let err = load_config_file(&path).unwrap_err().to_string();
+        assert!(err.contains("addr"), "{}", err);
+    }
+
+    #[test]
+    fn test_merge_config_values_replace() {
+        let mut base = serde_json::json!({
+            "update_interval_hours": 2,
+            "hosts_sources": ["https://a.example/hosts"],
+            "idn": true
+        });
+        let overlay = serde_json::json!({
+            "update_interval_hours": 6,
+            "hosts_sources": ["https://b.example/hosts"]
+        });
+        merge_config_values(&mut base, overlay);
+
+        assert_eq!(base["update_interval_hours"], 6);
+        assert_eq!(base["idn"], true);
+        assert_eq!(
+            base["hosts_sources"],
+            serde_json::json!(["https://b.example/hosts"])
+        );
+    }
+
+    #[test]
+    fn test_merge_config_values_append_sources() {
+        let mut base = serde_json::json!({
+            "hosts_sources": ["https://a.example/hosts"]
+        });
+        let overlay = serde_json::json!({
+            "sources_merge": "append",
+            "hosts_sources": ["https://a.example/hosts", "https://b.example/hosts"]
+        });
+        merge_config_values(&mut base, overlay);
+
+        assert_eq!(
+            base["hosts_sources"],
+            serde_json::json!(["https://a.example/hosts", "https://b.example/hosts"])
+        );
+    }
+
+    #[test]
+    fn test_load_merged_config_from_layers() {
+        let base_dir = tempfile::tempdir().unwrap();
+        let override_dir = tempfile::tempdir().unwrap();
+        write_config(
+            &base_dir,
+            "config.toml",
+            "hosts_sources = [\"https://a.example/hosts\"]\nbackup_before_update = false\n",
+        );
+        write_config(
+            &override_dir,
+            "config.json",
+            r#"{ "sources_merge": "append", "hosts_sources": ["https://b.example/hosts"], "update_interval_hours": 1 }"#,
+        );
+
+        let paths = vec![
+            base_dir.path().join("config").to_string_lossy().to_string(),
+            base_dir.path().join("missing").to_string_lossy().to_string(),
+            override_dir.path().join("config").to_string_lossy().to_string(),
+        ];
+        let config = load_merged_config_from(&paths, None).unwrap();
+
+        assert_eq!(config.update_interval_hours, 1);
+        assert!(!config.backup_before_update);
+        assert_eq!(
+            config.hosts_sources,
+            vec![
+                HostsSource::Url("https://a.example/hosts".to_string()),
+                HostsSource::Url("https://b.example/hosts".to_string())
+            ]
+        );
+
+        // 所有层都不存在时返回可识别的 ConfigNotFound
+        let missing = vec![base_dir.path().join("missing").to_string_lossy().to_string()];
+        let err = load_merged_config_from(&missing, None).unwrap_err();
+        let not_found = err.downcast_ref::<ConfigNotFound>().unwrap();
+        assert_eq!(not_found.searched, missing);
+        assert!(err.to_string().ends_with("missing.{json,toml,yaml}）"));
+        let err = load_merged_config_from(&missing, Some(ConfigFormat::Yaml)).unwrap_err();
+        assert!(err.to_string().ends_with("missing.yaml）"));
+    }
+
+    #[test]
+    fn test_parse_interval_hours() {
+        assert_eq!(parse_interval_hours("6").unwrap(), 6);
+        assert_eq!(parse_interval_hours("1h").unwrap(), 1);
+        assert_eq!(parse_interval_hours("2d").unwrap(), 48);
+        assert!(parse_interval_hours("0h").is_err());
+        assert!(parse_interval_hours("30m").is_err());
+        assert!(parse_interval_hours("h").is_err());
+    }
+
+    #[test]
+    fn test_config_from_sources() {
+        let config = Config::from_sources(vec!["https://a.example/hosts".to_string()]);
+        assert_eq!(config.update_interval_hours, 2);
+        assert!(config.backup_before_update);
+        assert!(validate_config(&config).is_ok());
+    }
+
+    #[test]
+    fn test_hosts_source_mirrors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_config(
+            &dir,
+            "config.toml",
+            "hosts_sources = [\n    \"https://a.example/hosts\",\n    { urls = [\"https://b.example/hosts\", \"https://mirror.example/hosts\"] },\n]\n",
+        );
+        let config = load_config_file(&path).unwrap();
+
+        let mirrors = &config.hosts_sources[1];
+        assert_eq!(mirrors.url(), "https://b.example/hosts");
+        assert_eq!(mirrors.urls().len(), 2);
+        assert_eq!(config.hosts_sources[0].urls(), ["https://a.example/hosts"]);
+        assert!(validate_config(&config).is_ok());
+
+        let empty: Config =
+            serde_json::from_str(r#"{ "hosts_sources": [{ "urls": [] }] }"#).unwrap();
+        assert!(validate_config(&empty).is_err());
+    }
+
+    #[test]
+    fn test_parse_config_with_format() {
+        let toml = "hosts_sources = [\"https://a.example/hosts\"]\n";
+        let yaml = "hosts_sources:\n  - https://a.example/hosts\n";
+        let json = r#"{ "hosts_sources": ["https://a.example/hosts"] }"#;
+
+        for content in [toml, yaml, json] {
+            let config = parse_config_with_format(content, None, "<stdin>").unwrap();
+            assert_eq!(config.hosts_sources[0].url(), "https://a.example/hosts");
+        }
+
+        assert!(parse_config_with_format(toml, Some(ConfigFormat::Json), "<stdin>").is_err());
+        let err = parse_config_with_format("hosts_source = []", None, "<stdin>")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("--config-format"), "{}", err);
+        assert_eq!("yml".parse::<ConfigFormat>().unwrap(), ConfigFormat::Yaml);
+    }
+
+    #[test]
+    fn test_sample_configs() {
+        let json: serde_json::Value =
+            serde_json::from_str(ConfigFormat::Json.sample()).unwrap();
+
+        for format in ConfigFormat::ALL {
+            let config = format.parse(format.sample(), format.extension()).unwrap();
+            assert!(validate_config(&config).is_ok(), "{:?}", format);
+
+            // 每个配置项都应在示例中出现（TOML/YAML 中可以是注释）
+            for key in json.as_object().unwrap().keys() {
+                assert!(format.sample().contains(key.as_str()), "{:?}: {}", format, key);
+            }
+        }
+    }
+
+    #[test]
+    fn test_line_rewrite() {
+        let config: Config = serde_json::from_str(
+            r#"{ "hosts_sources": [{
+                "urls": ["https://a.example/list"],
+                "line_regex": "^\\|\\|([^\\^]+)\\^.*$",
+                "line_replace": "0.0.0.0 $1"
+            }] }"#,
+        )
+        .unwrap();
+        assert!(validate_config(&config).is_ok());
+
+        let rewrite = config.hosts_sources[0].line_rewrite().unwrap().unwrap();
+        let content = rewrite.apply("! 注释\n||ads.example.com^$third-party\n");
+        assert_eq!(content, "! 注释\n0.0.0.0 ads.example.com\n");
+
+        let invalid: Config = serde_json::from_str(
+            r#"{ "hosts_sources": [{ "urls": ["https://a.example/list"], "line_regex": "(" }] }"#,
+        )
+        .unwrap();
+        assert!(validate_config(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_validate_config_header_template() {
+        let mut config = Config::from_sources(vec!["https://a.example/hosts".to_string()]);
+        config.header_template = Some("# Managed by ACME\n# {last_update}".to_string());
+        assert!(validate_config(&config).is_ok());
+
+        config.header_template = Some("# Managed by ACME\n{last_update}".to_string());
+        assert!(validate_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_config_profiles() {
+        let config: Config = toml::from_str(
+            r#"
+            hosts_sources = ["https://example.com/base"]
+            update_interval_hours = 6
+
+            [profiles.dev]
+            update_interval_hours = 1
+            hosts_sources = ["https://example.com/dev"]
+
+            [profiles.prod]
+            sources_merge = "append"
+            hosts_sources = ["https://example.com/prod"]
+            "#,
+        )
+        .unwrap();
+        assert!(validate_config(&config).is_ok());
+
+        let dev = config.with_profile("dev").unwrap();
+        assert_eq!(dev.update_interval_hours, 1);
+        assert_eq!(dev.hosts_sources[0].url(), "https://example.com/dev");
+        assert!(dev.profiles.is_empty());
+
+        let prod = config.with_profile("prod").unwrap();
+        assert_eq!(prod.update_interval_hours, 6);
+        let urls: Vec<&str> = prod.hosts_sources.iter().map(HostsSource::url).collect();
+        assert_eq!(urls, ["https://example.com/base", "https://example.com/prod"]);
+
+        assert!(config.with_profile("staging").is_err());
+
+        // 配置档中的拼写错误在校验时即可发现
+        let mut invalid = config.clone();
+        invalid
+            .profiles
+            .insert("bad".to_string(), serde_json::json!({ "update_interval": 1 }));
+        assert!(validate_config(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_validate_config_timestamp_format() {
+        let mut config = Config::from_sources(vec!["https://a.example/hosts".to_string()]);
+        config.timestamp_format = "%Y-%m-%dT%H:%M:%S%z".to_string();
+        assert!(validate_config(&config).is_ok());
+
+        for format in ["%Y-%m-%d %Q", "%Y\n%H", " "] {
+            config.timestamp_format = format.to_string();
+            assert!(validate_config(&config).is_err(), "{}", format);
+        }
+    }
+
+    #[test]
+    fn test_validate_config_tls_ca_cert() {
+        let dir = tempfile::tempdir().unwrap();
+        let invalid = write_config(&dir, "ca.pem", "not a certificate");
+        let missing = dir.path().join("missing.pem").to_string_lossy().to_string();
+
+        for path in [invalid, missing] {
+            let config: Config = serde_json::from_value(serde_json::json!({
+                "hosts_sources": ["https://example.com/hosts"],
+                "tls_ca_cert": path
+            }))
+            .unwrap();
+            assert!(validate_config(&config).is_err());
+        }
+    }
+
+    #[test]
+    fn test_min_tls_version() {
+        let parse = |version: &str| {
+            serde_json::from_value::<Config>(serde_json::json!({
+                "hosts_sources": ["https://example.com/hosts"],
+                "min_tls_version": version
+            }))
+        };
+
+        let config = parse("1.3").unwrap();
+        assert_eq!(config.min_tls_version, Some(TlsVersion::Tls13));
+        assert!(crate::fetcher::build_client(&config).is_ok());
+
+        // TLS 库不支持 1.2 以下的版本，加载配置时即报错
+        for version in ["1.1", "tls1.2", ""] {
+            assert!(parse(version).is_err());
+        }
+    }
+
+    #[test]
+    fn test_try_load_config_reports_invalid_file() {
+        let dir = tempfile::tempdir().unwrap();
+        write_config(&dir, "config.json", r#"{ "hosts_source": [] }"#);
+        let base = dir.path().join("config");
+
+        assert!(try_load_config(&base.to_string_lossy(), None).is_err());
+        assert!(try_load_config(&dir.path().join("missing").to_string_lossy(), None)
+            .unwrap()
+            .is_none());
+
+        // 指定格式时只查找该格式，其他格式的文件不会被读取
+        assert!(try_load_config(&base.to_string_lossy(), Some(ConfigFormat::Toml))
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,388 @@
+//! 配置编辑模块
+//!
+//! 以编程方式增删配置文件中的 `hosts_sources`，并按原格式写回。
+//! TOML 通过 `toml_edit` 修改，保留原有格式与注释；JSON 保留字段顺序；
+//! YAML 会被重新生成，注释无法保留。
+//!
+//! 镜像形式的数据源以其首个 URL 作为标识：添加已是某数据源镜像的 URL 视为已存在，
+//! 移除时按首个 URL 匹配整个数据源。
+
+use crate::config::{ConfigFormat, HostsSource, SourceSpec, parse_config_content, validate_config};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+
+/// 向配置文件添加数据源，已存在时返回 `false` 且不修改文件
+pub fn add_source(path: &str, url: &str) -> Result<bool> {
+    edit_sources(path, |sources| {
+        if sources.iter().any(|s| s.urls().iter().any(|u| u == url)) {
+            return false;
+        }
+        sources.push(HostsSource::Url(url.to_string()));
+        true
+    })
+}
+
+/// 从配置文件移除数据源，不存在时返回 `false` 且不修改文件
+pub fn remove_source(path: &str, url: &str) -> Result<bool> {
+    edit_sources(path, |sources| {
+        let before = sources.len();
+        sources.retain(|s| s.url() != url);
+        sources.len() != before
+    })
+}
+
+/// 将数据源内容的 SHA-256 写入配置文件，均未变化时返回 `false` 且不修改文件
+///
+/// `pins` 以数据源标识（首个 URL）为键，单个 URL 形式的数据源会改写为详细形式。
+pub fn set_source_pins(path: &str, pins: &HashMap<String, String>) -> Result<bool> {
+    edit_sources(path, |sources| {
+        let mut changed = false;
+        for source in sources.iter_mut() {
+            let Some(sha256) = pins.get(source.url()) else {
+                continue;
+            };
+            if source.sha256() == Some(sha256.as_str()) {
+                continue;
+            }
+
+            if let HostsSource::Url(url) = source {
+                *source = HostsSource::Detailed(SourceSpec {
+                    urls: vec![url.clone()],
+                    ..Default::default()
+                });
+            }
+            if let HostsSource::Detailed(spec) = source {
+                spec.sha256 = Some(sha256.clone());
+            }
+            changed = true;
+        }
+        changed
+    })
+}
+
+/// 读取配置文件，修改数据源列表，校验通过后写回
+///
+/// `edit` 返回 `false` 表示无需修改。
+fn edit_sources(path: &str, edit: impl FnOnce(&mut Vec<HostsSource>) -> bool) -> Result<bool> {
+    let content =
+        std::fs::read_to_string(path).with_context(|| format!("读取配置文件失败: {}", path))?;
+
+    let mut sources = parse_config_content(path, &content)?.hosts_sources;
+    if !edit(&mut sources) {
+        return Ok(false);
+    }
+
+    let updated = match ConfigFormat::from_path(path) {
+        Some(ConfigFormat::Json) => set_json_sources(&content, &sources, path)?,
+        Some(ConfigFormat::Toml) => set_toml_sources(&content, &sources, path)?,
+        Some(ConfigFormat::Yaml) => set_yaml_sources(&content, &sources, path)?,
+        None => return Err(anyhow::anyhow!("不支持的配置文件格式: {}", path)),
+    };
+
+    let config = parse_config_content(path, &updated)?;
+    validate_config(&config).context("修改后的配置无效，未写入")?;
+
+    std::fs::write(path, updated).with_context(|| format!("写入配置文件失败: {}", path))?;
+    Ok(true)
+}
+
+/// 更新 JSON 配置中的数据源列表（保留字段顺序）
+fn set_json_sources(content: &str, sources: &[HostsSource], path: &str) -> Result<String> {
+    let mut value: serde_json::Value = serde_json::from_str(content)
+        .map_err(|e| anyhow::anyhow!("解析 JSON 配置失败: {}: {}", path, e))?;
+    let object = value
+        .as_object_mut()
+        .ok_or_else(|| anyhow::anyhow!("JSON 配置顶层必须是对象: {}", path))?;
+    object.insert("hosts_sources".to_string(), serde_json::json!(sources));
+
+    let mut output = serde_json::to_string_pretty(&value).context("序列化 JSON 配置失败")?;
+    output.push('\n');
+    Ok(output)
+}
+
+/// 更新 TOML 配置中的数据源列表（保留格式与注释）
+///
+/// 删除被移除的条目、原位替换被修改的条目并在末尾追加新条目，其余条目保持原样。
+fn set_toml_sources(content: &str, sources: &[HostsSource], path: &str) -> Result<String> {
+    let mut doc: toml_edit::DocumentMut = content
+        .parse()
+        .map_err(|e| anyhow::anyhow!("解析 TOML 配置失败: {}: {}", path, e))?;
+
+    let Some(array) = doc
+        .get_mut("hosts_sources")
+        .and_then(|item| item.as_array_mut())
+    else {
+        doc["hosts_sources"] = toml_edit::value(
+            sources
+                .iter()
+                .map(toml_source)
+                .collect::<toml_edit::Array>(),
+        );
+        return Ok(doc.to_string());
+    };
+
+    array.retain(|v| {
+        toml_value_source(v).is_some_and(|old| sources.iter().any(|s| s.url() == old.url()))
+    });
+    // 被修改的条目保留其前后的空白与注释
+    for value in array.iter_mut() {
+        let Some(old) = toml_value_source(value) else {
+            continue;
+        };
+        if let Some(new) = sources.iter().find(|s| s.url() == old.url())
+            && *new != old
+        {
+            let decor = value.decor().clone();
+            *value = toml_source(new);
+            *value.decor_mut() = decor;
+        }
+    }
+    // 多行数组中沿用最后一个条目的缩进，使新条目独占一行
+    let indent = array
+        .iter()
+        .last()
+        .and_then(|v| v.decor().prefix())
+        .and_then(|prefix| prefix.as_str())
+        .and_then(|prefix| prefix.rfind('\n').map(|pos| prefix[pos..].to_string()));
+    for source in sources {
+        if !array
+            .iter()
+            .any(|v| toml_value_source(v).is_some_and(|old| old.url() == source.url()))
+        {
+            let mut value = toml_source(source);
+            if let Some(indent) = &indent {
+                value.decor_mut().set_prefix(indent.as_str());
+            }
+            array.push_formatted(value);
+        }
+    }
+
+    Ok(doc.to_string())
+}
+
+/// 将数据源转换为 TOML 值，详细形式转换为内联表
+fn toml_source(source: &HostsSource) -> toml_edit::Value {
+    match source {
+        HostsSource::Url(url) => toml_edit::Value::from(url.as_str()),
+        HostsSource::Detailed(spec) => {
+            let mut table = toml_edit::InlineTable::new();
+            table.insert(
+                "urls",
+                spec.urls
+                    .iter()
+                    .map(String::as_str)
+                    .collect::<toml_edit::Array>()
+                    .into(),
+            );
+            for (key, value) in spec_options(spec) {
+                table.insert(key, value.into());
+            }
+            if let Some(max_entries) = spec.max_entries {
+                table.insert("max_entries", (max_entries as i64).into());
+            }
+            table.into()
+        }
+    }
+}
+
+/// 详细形式数据源中已配置的字符串可选项
+fn spec_options(spec: &SourceSpec) -> impl Iterator<Item = (&'static str, &str)> {
+    [
+        ("line_regex", spec.line_regex.as_deref()),
+        ("line_replace", spec.line_replace.as_deref()),
+        ("sha256", spec.sha256.as_deref()),
+        ("min_refresh_interval", spec.min_refresh_interval.as_deref()),
+    ]
+    .into_iter()
+    .filter_map(|(key, value)| value.map(|value| (key, value)))
+}
+
+/// 将 TOML 数组中的条目解析为数据源
+fn toml_value_source(value: &toml_edit::Value) -> Option<HostsSource> {
+    #[derive(serde::Deserialize)]
+    struct Entry {
+        source: HostsSource,
+    }
+
+    let mut value = value.clone();
+    value.decor_mut().clear();
+    toml::from_str::<Entry>(&format!("source = {}", value))
+        .ok()
+        .map(|entry| entry.source)
+}
+
+/// 更新 YAML 配置中的数据源列表（会重新生成文件，注释无法保留）
+fn set_yaml_sources(content: &str, sources: &[HostsSource], path: &str) -> Result<String> {
+    use yaml_rust::Yaml;
+
+    let mut docs = yaml_rust::YamlLoader::load_from_str(content)
+        .with_context(|| format!("解析 YAML 配置失败: {}", path))?;
+    let Some(Yaml::Hash(map)) = docs.first_mut() else {
+        return Err(anyhow::anyhow!("YAML 配置顶层必须是映射: {}", path));
+    };
+
+    let list = sources
+        .iter()
+        .map(|source| match source {
+            HostsSource::Url(url) => Yaml::String(url.clone()),
+            HostsSource::Detailed(spec) => {
+                let urls = spec
+                    .urls
+                    .iter()
+                    .map(|url| Yaml::String(url.clone()))
+                    .collect();
+                let mut map = yaml_rust::yaml::Hash::new();
+                map.insert(Yaml::String("urls".to_string()), Yaml::Array(urls));
+                for (key, value) in spec_options(spec) {
+                    map.insert(Yaml::String(key.to_string()), Yaml::String(value.to_string()));
+                }
+                if let Some(max_entries) = spec.max_entries {
+                    map.insert(
+                        Yaml::String("max_entries".to_string()),
+                        Yaml::Integer(max_entries as i64),
+                    );
+                }
+                Yaml::Hash(map)
+            }
+        })
+        .collect();
+    map.insert(Yaml::String("hosts_sources".to_string()), Yaml::Array(list));
+
+    let mut output = String::new();
+    yaml_rust::YamlEmitter::new(&mut output)
+        .dump(&docs[0])
+        .context("序列化 YAML 配置失败")?;
+    let mut output = output.trim_start_matches("---\n").to_string();
+    output.push('\n');
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_file(dir: &tempfile::TempDir, name: &str, content: &str) -> String {
+        let path = dir.path().join(name);
+        std::fs::write(&path, content).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    fn source_urls(config: &crate::config::Config) -> Vec<&str> {
+        config.hosts_sources.iter().map(HostsSource::url).collect()
+    }
+
+    #[test]
+    fn test_toml_edit_preserves_comments() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_file(
+            &dir,
+            "config.toml",
+            "# 更新间隔\nupdate_interval_hours = 4\n\nhosts_sources = [\n    # 主源\n    \"https://a.example/hosts\",\n]\n",
+        );
+
+        assert!(add_source(&path, "https://b.example/hosts").unwrap());
+        assert!(!add_source(&path, "https://b.example/hosts").unwrap());
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains("# 更新间隔"));
+        assert!(content.contains("# 主源"));
+
+        assert!(remove_source(&path, "https://b.example/hosts").unwrap());
+        let config = parse_config_content(&path, &std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(source_urls(&config), ["https://a.example/hosts"]);
+    }
+
+    #[test]
+    fn test_json_and_yaml_edit() {
+        let dir = tempfile::tempdir().unwrap();
+        let json = write_file(
+            &dir,
+            "config.json",
+            r#"{ "update_interval_hours": 4, "hosts_sources": ["https://a.example/hosts"] }"#,
+        );
+        let yaml = write_file(
+            &dir,
+            "config.yaml",
+            "update_interval_hours: 4\nhosts_sources:\n  - https://a.example/hosts\n",
+        );
+        // 加载配置同样接受 .yml 扩展名
+        let yml = write_file(
+            &dir,
+            "config.yml",
+            "update_interval_hours: 4\nhosts_sources:\n  - https://a.example/hosts\n",
+        );
+
+        for path in [json, yaml, yml] {
+            assert!(add_source(&path, "https://b.example/hosts").unwrap());
+            assert!(remove_source(&path, "https://a.example/hosts").unwrap());
+            assert!(!remove_source(&path, "https://a.example/hosts").unwrap());
+
+            let content = std::fs::read_to_string(&path).unwrap();
+            let config = parse_config_content(&path, &content).unwrap();
+            assert_eq!(source_urls(&config), ["https://b.example/hosts"]);
+            assert_eq!(config.update_interval_hours, 4);
+        }
+    }
+
+    #[test]
+    fn test_edit_keeps_mirror_sources() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_file(
+            &dir,
+            "config.toml",
+            "hosts_sources = [\n    { urls = [\"https://a.example/hosts\", \"https://mirror.example/hosts\"] },\n]\n",
+        );
+
+        assert!(!add_source(&path, "https://mirror.example/hosts").unwrap());
+        assert!(add_source(&path, "https://b.example/hosts").unwrap());
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let config = parse_config_content(&path, &content).unwrap();
+        assert_eq!(config.hosts_sources[0].urls().len(), 2);
+        assert_eq!(
+            source_urls(&config),
+            ["https://a.example/hosts", "https://b.example/hosts"]
+        );
+
+        assert!(remove_source(&path, "https://a.example/hosts").unwrap());
+        let content = std::fs::read_to_string(&path).unwrap();
+        let config = parse_config_content(&path, &content).unwrap();
+        assert_eq!(source_urls(&config), ["https://b.example/hosts"]);
+    }
+
+    #[test]
+    fn test_set_source_pins() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_file(
+            &dir,
+            "config.toml",
+            "hosts_sources = [\n    # 主源\n    \"https://a.example/hosts\",\n    \"https://b.example/hosts\",\n]\n",
+        );
+        let sha256 = "ab".repeat(32);
+        let pins = HashMap::from([("https://a.example/hosts".to_string(), sha256.clone())]);
+
+        assert!(set_source_pins(&path, &pins).unwrap());
+        assert!(!set_source_pins(&path, &pins).unwrap());
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains("# 主源"));
+        let config = parse_config_content(&path, &content).unwrap();
+        assert_eq!(config.hosts_sources[0].sha256(), Some(sha256.as_str()));
+        assert_eq!(config.hosts_sources[1].sha256(), None);
+        assert_eq!(
+            source_urls(&config),
+            ["https://a.example/hosts", "https://b.example/hosts"]
+        );
+    }
+
+    #[test]
+    fn test_edit_rejects_invalid_result() {
+        let dir = tempfile::tempdir().unwrap();
+        let original = "hosts_sources = [\"https://a.example/hosts\"]\n";
+        let path = write_file(&dir, "config.toml", original);
+
+        assert!(add_source(&path, "ftp://a.example/hosts").is_err());
+        assert!(remove_source(&path, "https://a.example/hosts").is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), original);
+    }
+}
@@ -0,0 +1,497 @@
+//! 冲突检测与去重模块
+//!
+//! 检测同一域名在不同数据源中指向不同 IP 的情况，按配置的策略选出生效的 IP，
+//! 并从其余数据源中移除落选的条目。IPv4 与 IPv6 地址分别处理，互不视为冲突。
+//! 冲突处理之后再移除重复的条目（相同 IP 与域名只保留首次出现的一条）。
+
+use crate::config::ConflictPolicy;
+use crate::fetcher::SourceResult;
+use anyhow::Result;
+use rayon::prelude::*;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+/// 单个域名的 IP 冲突
+#[derive(Debug, Clone, PartialEq)]
+pub struct DomainConflict {
+    /// 冲突的域名
+    pub domain: String,
+    /// 候选项（IP 与数据源 URL），按数据源顺序排列
+    pub candidates: Vec<(String, String)>,
+    /// 生效候选项在 `candidates` 中的下标
+    pub winner: usize,
+}
+
+impl DomainConflict {
+    /// 生效的 IP
+    pub fn winning_ip(&self) -> &str {
+        &self.candidates[self.winner].0
+    }
+}
+
+/// 冲突键：小写域名与是否为 IPv6
+type ConflictKey = (String, bool);
+
+/// 检测数据源之间的域名 IP 冲突
+pub fn find_conflicts(sources: &[SourceResult], policy: ConflictPolicy) -> Vec<DomainConflict> {
+    if sources.len() < 2 {
+        return Vec::new();
+    }
+
+    // 按首次出现顺序记录每个域名的候选项（数据源下标, IP）
+    let mut order: Vec<ConflictKey> = Vec::new();
+    let mut candidates: HashMap<ConflictKey, Vec<(usize, String)>> = HashMap::new();
+
+    for (index, source) in sources.iter().enumerate() {
+        for (ip, domains) in source.content.lines().filter_map(parse_entry) {
+            for domain in domains {
+                let key = (domain.to_ascii_lowercase(), ip.contains(':'));
+                let list = candidates.entry(key.clone()).or_insert_with(|| {
+                    order.push(key);
+                    Vec::new()
+                });
+                if !list
+                    .iter()
+                    .any(|(i, existing)| *i == index && existing == ip)
+                {
+                    list.push((index, ip.to_string()));
+                }
+            }
+        }
+    }
+
+    order
+        .into_iter()
+        .filter_map(|key| {
+            let list = candidates.remove(&key)?;
+            let first = &list[0];
+            let has_other_ip = list.iter().any(|(_, ip)| *ip != first.1);
+            let has_other_source = list.iter().any(|(i, _)| *i != first.0);
+            if !has_other_ip || !has_other_source {
+                return None;
+            }
+
+            let winner = match policy {
+                ConflictPolicy::First | ConflictPolicy::Error => 0,
+                ConflictPolicy::Last => list.len() - 1,
+            };
+            Some(DomainConflict {
+                domain: key.0,
+                candidates: list
+                    .into_iter()
+                    .map(|(i, ip)| (ip, sources[i].url.clone()))
+                    .collect(),
+                winner,
+            })
+        })
+        .collect()
+}
+
+/// 检测并处理域名 IP 冲突
+///
+/// 存在冲突时输出汇总警告，并返回移除了落选条目的数据源；
+/// 策略为 `error` 时返回错误。
+pub fn resolve_conflicts(
+    sources: &[SourceResult],
+    policy: ConflictPolicy,
+) -> Result<Vec<SourceResult>> {
+    let conflicts = find_conflicts(sources, policy);
+    if conflicts.is_empty() {
+        return Ok(sources.to_vec());
+    }
+
+    let details = conflicts
+        .iter()
+        .map(describe_conflict)
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    if policy == ConflictPolicy::Error {
+        return Err(anyhow::anyhow!(
+            "发现 {} 个域名在不同数据源中指向不同 IP（conflict_policy = error）:\n{}",
+            conflicts.len(),
+            details
+        ));
+    }
+
+    tracing::warn!(
+        "发现 {} 个域名在不同数据源中指向不同 IP，已采用{}出现的 IP:\n{}",
+        conflicts.len(),
+        if policy == ConflictPolicy::First {
+            "最先"
+        } else {
+            "最后"
+        },
+        details
+    );
+
+    // 落选的（数据源 URL, IP, 小写域名）
+    let losers: HashSet<(&str, &str, String)> = conflicts
+        .iter()
+        .flat_map(|conflict| {
+            let winning_ip = conflict.winning_ip();
+            conflict
+                .candidates
+                .iter()
+                .filter(move |(ip, _)| ip != winning_ip)
+                .map(|(ip, url)| (url.as_str(), ip.as_str(), conflict.domain.clone()))
+        })
+        .collect();
+
+    Ok(sources
+        .iter()
+        .map(|source| {
+            let mut source = source.clone();
+            let url = source.url.as_str();
+            source.content = retain_domains(&source.content, |ip, domain| {
+                !losers.contains(&(url, ip, domain.to_ascii_lowercase()))
+            });
+            source
+        })
+        .collect())
+}
+
+/// 并行去重时每个分块的目标大小（字节），分块在行边界处切分
+const DEDUP_CHUNK_BYTES: usize = 256 * 1024;
+
+/// 数据源内容总量不小于该值（字节）且有多个工作线程时才并行去重
+const PARALLEL_DEDUP_MIN_BYTES: usize = 4 * DEDUP_CHUNK_BYTES;
+
+/// 去重键：IP 与小写域名
+type DedupKey<'a> = (&'a str, Cow<'a, str>);
+
+/// 条目在所有分块中的位置：（分块序号, 分块内的条目序号），按数据源顺序递增
+type EntryPosition = (usize, usize);
+
+/// 移除重复的条目
+///
+/// 相同 IP 与域名（不区分大小写）的条目只保留首次出现的一条，包括同一数据源内的重复。
+/// 内容较多且有多个工作线程时并行处理，结果与顺序处理完全一致。
+pub fn dedup_sources(sources: &[SourceResult]) -> Vec<SourceResult> {
+    let total: usize = sources.iter().map(|source| source.content.len()).sum();
+    if total >= PARALLEL_DEDUP_MIN_BYTES && rayon::current_num_threads() > 1 {
+        dedup_parallel(sources)
+    } else {
+        dedup_sequential(sources)
+    }
+}
+
+/// 顺序去重
+fn dedup_sequential(sources: &[SourceResult]) -> Vec<SourceResult> {
+    let mut seen: HashSet<DedupKey> = HashSet::new();
+
+    sources
+        .iter()
+        .map(|source| SourceResult {
+            content: retain_domains(&source.content, |ip, domain| {
+                seen.insert(dedup_key(ip, domain))
+            }),
+            ..source.clone()
+        })
+        .collect()
+}
+
+/// 并行去重
+///
+/// 各数据源按行切分为分块后并行处理：先为每个分块记录各键首次出现的位置，合并时取最小位置，
+/// 再并行过滤出位于该位置的条目。取最小值与合并顺序无关，因此结果是确定的，
+/// 与顺序处理时“按数据源顺序首次出现”的判断一致。
+fn dedup_parallel(sources: &[SourceResult]) -> Vec<SourceResult> {
+    let chunks: Vec<(usize, &str)> = sources
+        .iter()
+        .enumerate()
+        .flat_map(|(index, source)| {
+            split_at_lines(&source.content, DEDUP_CHUNK_BYTES).map(move |chunk| (index, chunk))
+        })
+        .collect();
+
+    let first_seen: HashMap<DedupKey, EntryPosition> = chunks
+        .par_iter()
+        .enumerate()
+        .map(|(chunk, (_, content))| {
+            let mut first_seen = HashMap::new();
+            for (position, key) in entry_keys(content).enumerate() {
+                first_seen.entry(key).or_insert((chunk, position));
+            }
+            first_seen
+        })
+        .reduce(HashMap::new, |mut merged, mut other| {
+            if merged.len() < other.len() {
+                std::mem::swap(&mut merged, &mut other);
+            }
+            for (key, position) in other {
+                merged
+                    .entry(key)
+                    .and_modify(|first| *first = position.min(*first))
+                    .or_insert(position);
+            }
+            merged
+        });
+
+    let kept: Vec<String> = chunks
+        .par_iter()
+        .enumerate()
+        .map(|(chunk, (_, content))| {
+            let mut position = 0;
+            retain_domains(content, |ip, domain| {
+                let keep = first_seen.get(&dedup_key(ip, domain)) == Some(&(chunk, position));
+                position += 1;
+                keep
+            })
+        })
+        .collect();
+
+    let mut contents = vec![String::new(); sources.len()];
+    for ((index, _), content) in chunks.iter().zip(kept) {
+        contents[*index].push_str(&content);
+    }
+    sources
+        .iter()
+        .zip(contents)
+        .map(|(source, content)| SourceResult {
+            content,
+            ..source.clone()
+        })
+        .collect()
+}
+
+/// 生成去重键，域名已是小写时不复制
+fn dedup_key<'a>(ip: &'a str, domain: &'a str) -> DedupKey<'a> {
+    let domain = if domain.bytes().any(|b| b.is_ascii_uppercase()) {
+        Cow::Owned(domain.to_ascii_lowercase())
+    } else {
+        Cow::Borrowed(domain)
+    };
+    (ip, domain)
+}
+
+/// 按与 `retain_domains` 相同的顺序列出内容中每个 IP 与域名组合的去重键
+fn entry_keys(content: &str) -> impl Iterator<Item = DedupKey<'_>> {
+    content.lines().filter_map(parse_entry).flat_map(|(ip, domains)| {
+        domains.into_iter().map(move |domain| dedup_key(ip, domain))
+    })
+}
+
+/// 在换行处将内容切分为不小于 `target` 字节的分块（最后一块除外）
+fn split_at_lines(content: &str, target: usize) -> impl Iterator<Item = &str> {
+    let mut rest = content;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let end = rest.as_bytes()[target.min(rest.len())..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(rest.len(), |offset| target + offset + 1);
+        let (chunk, tail) = rest.split_at(end);
+        rest = tail;
+        Some(chunk)
+    })
+}
+
+/// 生成单个冲突的描述
+fn describe_conflict(conflict: &DomainConflict) -> String {
+    let candidates = conflict
+        .candidates
+        .iter()
+        .map(|(ip, url)| format!("{} ({})", ip, url))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let (ip, url) = &conflict.candidates[conflict.winner];
+    format!(
+        "  {}: {} → 采用 {} ({})",
+        conflict.domain, candidates, ip, url
+    )
+}
+
+/// 按条件保留数据源内容中的域名，所有域名都被移除的行整行删除
+///
+/// `keep` 对每个 IP 与域名组合按出现顺序调用一次。
+pub(crate) fn retain_domains<'a>(
+    content: &'a str,
+    mut keep: impl FnMut(&'a str, &'a str) -> bool,
+) -> String {
+    let mut result = String::with_capacity(content.len());
+
+    for line in content.lines() {
+        let Some((ip, domains)) = parse_entry(line) else {
+            result.push_str(line);
+            result.push('\n');
+            continue;
+        };
+
+        let kept: Vec<&str> = domains
+            .iter()
+            .copied()
+            .filter(|domain| keep(ip, domain))
+            .collect();
+        if kept.len() == domains.len() {
+            result.push_str(line);
+            result.push('\n');
+            continue;
+        }
+        if kept.is_empty() {
+            continue;
+        }
+
+        result.push_str(ip);
+        for domain in kept {
+            result.push(' ');
+            result.push_str(domain);
+        }
+        if let Some(pos) = line.find('#') {
+            result.push(' ');
+            result.push_str(&line[pos..]);
+        }
+        result.push('\n');
+    }
+
+    result
+}
+
+/// 解析 hosts 条目为 IP 与域名列表，注释行、空行和无域名的行返回 `None`
+fn parse_entry(line: &str) -> Option<(&str, Vec<&str>)> {
+    let entry = line.split('#').next()?;
+    let mut parts = entry.split_whitespace();
+    let ip = parts.next()?;
+    let domains: Vec<&str> = parts.collect();
+    if domains.is_empty() {
+        return None;
+    }
+    Some((ip, domains))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_sources() -> Vec<SourceResult> {
+        vec![
+            SourceResult::for_test(
+                "https://a.example/hosts",
+                "1.1.1.1 example.com a.com\n:: example.com\n",
+            ),
+            SourceResult::for_test(
+                "https://b.example/hosts",
+                "2.2.2.2 Example.com\n::1 other.com\n",
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_find_conflicts_ignores_same_ip_and_other_family() {
+        let sources = vec![
+            SourceResult::for_test("https://a.example/hosts", "0.0.0.0 a.com\n"),
+            SourceResult::for_test("https://b.example/hosts", "0.0.0.0 a.com\n:: a.com\n"),
+        ];
+        assert!(find_conflicts(&sources, ConflictPolicy::Last).is_empty());
+    }
+
+    #[test]
+    fn test_resolve_conflicts_last() {
+        let resolved = resolve_conflicts(&sample_sources(), ConflictPolicy::Last).unwrap();
+        assert_eq!(resolved[0].content, "1.1.1.1 a.com\n:: example.com\n");
+        assert_eq!(resolved[1].content, "2.2.2.2 Example.com\n::1 other.com\n");
+    }
+
+    #[test]
+    fn test_resolve_conflicts_first() {
+        let resolved = resolve_conflicts(&sample_sources(), ConflictPolicy::First).unwrap();
+        assert_eq!(
+            resolved[0].content,
+            "1.1.1.1 example.com a.com\n:: example.com\n"
+        );
+        assert_eq!(resolved[1].content, "::1 other.com\n");
+    }
+
+    #[test]
+    fn test_dedup_sources() {
+        let sources = vec![
+            SourceResult::for_test(
+                "https://a.example/hosts",
+                "0.0.0.0 a.com b.com\n0.0.0.0 a.com\n",
+            ),
+            SourceResult::for_test(
+                "https://b.example/hosts",
+                "0.0.0.0 A.com c.com\n0.0.0.0 b.com\n",
+            ),
+        ];
+        let deduped = dedup_sources(&sources);
+        assert_eq!(deduped[0].content, "0.0.0.0 a.com b.com\n");
+        assert_eq!(deduped[1].content, "0.0.0.0 c.com\n");
+    }
+
+    #[test]
+    fn test_parallel_dedup_with_conflicts() {
+        // 内容超过分块大小，重复与冲突的条目分布在不同分块与数据源中
+        let mut a = String::from("# a\n");
+        let mut b = String::from("# b\n");
+        for i in 0..10_000 {
+            a.push_str(&format!("0.0.0.0 ads{}.example.com shared{}.example.com\n", i, i % 500));
+            b.push_str(&format!("0.0.0.0 ADS{}.example.com\n", 9_999 - i));
+        }
+        a.push_str("1.1.1.1 conflict.example.com\n0.0.0.0 ads0.example.com\n");
+        b.push_str("2.2.2.2 conflict.example.com\n2.2.2.2 Conflict.example.com\n");
+        let sources = vec![
+            SourceResult::for_test("https://a.example/hosts", &a),
+            SourceResult::for_test("https://b.example/hosts", &b),
+            SourceResult::for_test(
+                "https://c.example/hosts",
+                "3.3.3.3 conflict.example.com other.com\n",
+            ),
+        ];
+        assert!(a.len() > DEDUP_CHUNK_BYTES);
+
+        // 顺序处理的参考实现
+        let mut seen = HashSet::new();
+        let resolved = resolve_conflicts(&sources, ConflictPolicy::Last).unwrap();
+        let expected: Vec<String> = resolved
+            .iter()
+            .map(|source| {
+                retain_domains(&source.content, |ip, domain| {
+                    seen.insert((ip.to_string(), domain.to_ascii_lowercase()))
+                })
+            })
+            .collect();
+
+        // 并行结果与线程调度无关，与顺序处理一致
+        for dedup in [dedup_parallel, dedup_parallel, dedup_sequential, dedup_sources] {
+            let deduped = dedup(&resolved);
+            let contents: Vec<&str> = deduped.iter().map(|s| s.content.as_str()).collect();
+            assert_eq!(contents, expected);
+        }
+        assert_eq!(expected[1], "# b\n");
+        assert_eq!(expected[2], "3.3.3.3 conflict.example.com other.com\n");
+        assert!(expected[0].ends_with("0.0.0.0 ads9999.example.com\n"));
+    }
+
+    #[test]
+    fn test_trailing_dot_dedup() {
+        let config = crate::config::Config::for_test(r#"{ "hosts_sources": [] }"#);
+        let sources: Vec<SourceResult> = [
+            ("https://a.example/hosts", "0.0.0.0 example.com.\n"),
+            ("https://b.example/hosts", "0.0.0.0 example.com\n"),
+        ]
+        .into_iter()
+        .map(|(url, content)| {
+            SourceResult::for_test(
+                url,
+                &crate::preprocess::preprocess_content(content, &config),
+            )
+        })
+        .collect();
+
+        let deduped = dedup_sources(&sources);
+        assert_eq!(deduped[0].content, "0.0.0.0 example.com\n");
+        assert_eq!(deduped[1].content, "");
+    }
+
+    #[test]
+    fn test_resolve_conflicts_error() {
+        let err = resolve_conflicts(&sample_sources(), ConflictPolicy::Error)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("example.com"), "{}", err);
+        assert!(err.contains("2.2.2.2"), "{}", err);
+    }
+}
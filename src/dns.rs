@@ -0,0 +1,168 @@
+//! 自定义 DNS 解析模块
+//!
+//! 系统 DNS 被污染或不可用时，获取数据源可能失败或被劫持。配置 `dns_resolver` 后，
+//! 数据源主机名改为通过指定的 DNS 服务器（UDP 或 DNS-over-HTTPS）解析，不读取系统 DNS 配置与 hosts 文件。
+//! 实际解析需启用 `doh` 特性。
+
+use crate::config::{DnsProtocol, DnsResolverConfig};
+use anyhow::Result;
+use std::net::{IpAddr, SocketAddr};
+
+impl DnsProtocol {
+    /// 配置中的写法
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DnsProtocol::Udp => "udp",
+            DnsProtocol::Https => "https",
+        }
+    }
+
+    /// 未指定端口时使用的默认端口
+    pub fn default_port(self) -> u16 {
+        match self {
+            DnsProtocol::Udp => 53,
+            DnsProtocol::Https => 443,
+        }
+    }
+}
+
+/// 解析配置中的 DNS 服务器地址
+///
+/// 每项为 IP（`1.1.1.1`、`2606:4700::1111`）或带端口的地址（`1.1.1.1:53`、`[2606:4700::1111]:443`），
+/// 不能是主机名，否则解析 DNS 服务器本身又依赖系统 DNS。
+pub fn server_addrs(config: &DnsResolverConfig) -> Result<Vec<SocketAddr>> {
+    if config.servers.is_empty() {
+        return Err(anyhow::anyhow!("dns_resolver.servers 不能为空"));
+    }
+
+    config
+        .servers
+        .iter()
+        .map(|server| {
+            let server = server.trim();
+            server
+                .parse::<SocketAddr>()
+                .or_else(|_| {
+                    server
+                        .parse::<IpAddr>()
+                        .map(|ip| SocketAddr::new(ip, config.protocol.default_port()))
+                })
+                .map_err(|_| {
+                    anyhow::anyhow!("无效的 DNS 服务器地址: {}（须为 IP 或 IP:端口）", server)
+                })
+        })
+        .collect()
+}
+
+/// 检查 DNS 解析器配置
+pub fn validate_resolver(config: &DnsResolverConfig) -> Result<()> {
+    server_addrs(config)?;
+
+    if config.protocol == DnsProtocol::Https
+        && config
+            .tls_name
+            .as_deref()
+            .is_none_or(|name| name.trim().is_empty())
+    {
+        return Err(anyhow::anyhow!(
+            "dns_resolver 使用 https 时需要配置 tls_name（DoH 服务器证书中的域名，如 cloudflare-dns.com）"
+        ));
+    }
+
+    if !cfg!(feature = "doh") {
+        return Err(anyhow::anyhow!("使用自定义 DNS 解析器需要启用 doh 特性"));
+    }
+
+    Ok(())
+}
+
+/// 基于 hickory 的 DNS 解析器，供 HTTP 客户端解析数据源主机名
+#[cfg(feature = "doh")]
+pub struct HickoryResolver(hickory_resolver::TokioResolver);
+
+#[cfg(feature = "doh")]
+impl HickoryResolver {
+    /// 按配置创建解析器，只查询配置的 DNS 服务器
+    pub fn new(config: &DnsResolverConfig) -> Result<Self> {
+        use hickory_resolver::config::{
+            NameServerConfig, ResolveHosts, ResolverConfig, ResolverOpts,
+        };
+        use hickory_resolver::name_server::TokioConnectionProvider;
+        use hickory_resolver::proto::xfer::Protocol;
+
+        let protocol = match config.protocol {
+            DnsProtocol::Udp => Protocol::Udp,
+            DnsProtocol::Https => Protocol::Https,
+        };
+        let mut resolver_config = ResolverConfig::new();
+        for addr in server_addrs(config)? {
+            let mut server = NameServerConfig::new(addr, protocol);
+            server.tls_dns_name = config.tls_name.clone();
+            resolver_config.add_name_server(server);
+        }
+
+        // 不读取 hosts 文件：其中可能包含本程序写入的条目
+        let mut options = ResolverOpts::default();
+        options.use_hosts_file = ResolveHosts::Never;
+
+        let resolver = hickory_resolver::Resolver::builder_with_config(
+            resolver_config,
+            TokioConnectionProvider::default(),
+        )
+        .with_options(options)
+        .build();
+        Ok(HickoryResolver(resolver))
+    }
+}
+
+#[cfg(feature = "doh")]
+impl reqwest::dns::Resolve for HickoryResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let resolver = self.0.clone();
+        Box::pin(async move {
+            let lookup = resolver.lookup_ip(name.as_str()).await?;
+            // 端口为 0 时由 HTTP 客户端替换为 URL 中的端口
+            let addrs: reqwest::dns::Addrs =
+                Box::new(lookup.into_iter().map(|ip| SocketAddr::new(ip, 0)));
+            Ok(addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_addrs() {
+        let mut config = DnsResolverConfig {
+            servers: vec![
+                "1.1.1.1".to_string(),
+                "9.9.9.9:5353".to_string(),
+                "2606:4700::1111".to_string(),
+            ],
+            protocol: DnsProtocol::Udp,
+            tls_name: None,
+        };
+        assert_eq!(
+            server_addrs(&config).unwrap(),
+            vec![
+                "1.1.1.1:53".parse::<SocketAddr>().unwrap(),
+                "9.9.9.9:5353".parse().unwrap(),
+                "[2606:4700::1111]:53".parse().unwrap(),
+            ]
+        );
+
+        // DoH 默认使用 443 端口，且必须配置证书域名
+        config.protocol = DnsProtocol::Https;
+        assert_eq!(server_addrs(&config).unwrap()[0].port(), 443);
+        assert!(validate_resolver(&config).is_err());
+        config.tls_name = Some("cloudflare-dns.com".to_string());
+        assert_eq!(validate_resolver(&config).is_ok(), cfg!(feature = "doh"));
+
+        config.servers = vec!["dns.google".to_string()];
+        assert!(server_addrs(&config).is_err());
+        config.servers.clear();
+        assert!(server_addrs(&config).is_err());
+    }
+}
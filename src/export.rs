@@ -0,0 +1,186 @@
+//! 导出模块
+//!
+//! 将合并、去重后的数据源条目转换为其他工具使用的格式（hosts、dnsmasq、纯域名列表、Adblock 规则），
+//! 供 `export` 子命令输出，便于将合并结果用于 dnsmasq、Pi-hole、AdGuard 等下游工具。
+
+use crate::config::Config;
+use crate::conflict::{dedup_sources, resolve_conflicts};
+use crate::fetcher::SourceResult;
+use crate::hosts::filter_allowlist;
+use anyhow::Result;
+use std::collections::HashSet;
+
+/// 导出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// hosts 条目，每行一个域名：`0.0.0.0 example.com`
+    Hosts,
+    /// dnsmasq 配置：`address=/example.com/0.0.0.0`，或按 [`DnsmasqMode::Nxdomain`] 输出 `server=/example.com/`
+    Dnsmasq(DnsmasqMode),
+    /// 纯域名列表，每行一个域名
+    Domains,
+    /// Adblock 规则：`||example.com^`
+    Adblock,
+}
+
+/// dnsmasq 导出中屏蔽条目的写法
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DnsmasqMode {
+    /// `address=/example.com/0.0.0.0`：解析到黑洞地址
+    #[default]
+    Sink,
+    /// `server=/example.com/`：不转发到上游，直接返回 NXDOMAIN
+    Nxdomain,
+}
+
+impl std::str::FromStr for DnsmasqMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "sink" => Ok(DnsmasqMode::Sink),
+            "nxdomain" => Ok(DnsmasqMode::Nxdomain),
+            _ => Err(anyhow::anyhow!(
+                "不支持的 dnsmasq 模式: {}（可选 sink、nxdomain）",
+                s
+            )),
+        }
+    }
+}
+
+impl ExportFormat {
+    /// 是否只能表达屏蔽（不包含目标 IP）
+    fn blocklist_only(self) -> bool {
+        matches!(self, ExportFormat::Domains | ExportFormat::Adblock)
+    }
+
+    /// 指向黑洞地址的条目是否只按域名输出（不区分 IPv4 与 IPv6 黑洞）
+    fn sink_by_domain(self) -> bool {
+        self.blocklist_only() || self == ExportFormat::Dnsmasq(DnsmasqMode::Nxdomain)
+    }
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "hosts" => Ok(ExportFormat::Hosts),
+            "dnsmasq" => Ok(ExportFormat::Dnsmasq(DnsmasqMode::default())),
+            "domains" => Ok(ExportFormat::Domains),
+            "adblock" => Ok(ExportFormat::Adblock),
+            _ => Err(anyhow::anyhow!(
+                "不支持的导出格式: {}（可选 hosts、dnsmasq、domains、adblock）",
+                s
+            )),
+        }
+    }
+}
+
+/// 黑洞地址：指向这些地址的条目表示屏蔽该域名
+fn is_sink_ip(ip: &str) -> bool {
+    matches!(ip, "0.0.0.0" | "127.0.0.1" | "::" | "::1")
+}
+
+/// 按冲突策略处理、去重并应用白名单后，将各数据源的条目转换为指定格式
+///
+/// 相同 IP 与域名（不区分大小写）只输出一次；`domains` 与 `adblock` 格式只能表达屏蔽，
+/// 每个域名只输出一次，指向非黑洞地址的条目会被跳过并记录数量。
+/// dnsmasq 的 `nxdomain` 模式下，指向黑洞地址的域名同样只输出一条 `server=` 规则，其他条目仍输出 `address=`。
+pub fn export_entries(
+    sources: &[SourceResult],
+    config: &Config,
+    format: ExportFormat,
+) -> Result<String> {
+    let sources = dedup_sources(&resolve_conflicts(sources, config.conflict_policy)?);
+    let sources = filter_allowlist(sources, config)?;
+
+    let mut seen: HashSet<(String, String)> = HashSet::new();
+    let mut skipped = 0;
+    let mut output = String::new();
+    for source in &sources {
+        for line in source.content.lines() {
+            let entry = line.split('#').next().unwrap_or_default();
+            let mut parts = entry.split_whitespace();
+            let Some(ip) = parts.next() else {
+                continue;
+            };
+
+            for domain in parts {
+                if format.blocklist_only() && !is_sink_ip(ip) {
+                    skipped += 1;
+                    continue;
+                }
+                // 只输出域名的格式中，双栈黑洞条目的 IPv4 与 IPv6 行视为同一个域名
+                let sink_by_domain = format.sink_by_domain() && is_sink_ip(ip);
+                let key_ip = if sink_by_domain { "" } else { ip };
+                if !seen.insert((key_ip.to_string(), domain.to_ascii_lowercase())) {
+                    continue;
+                }
+
+                let rendered = match format {
+                    ExportFormat::Hosts => format!("{} {}\n", ip, domain),
+                    ExportFormat::Dnsmasq(DnsmasqMode::Nxdomain) if sink_by_domain => {
+                        format!("server=/{}/\n", domain)
+                    }
+                    ExportFormat::Dnsmasq(_) => format!("address=/{}/{}\n", domain, ip),
+                    ExportFormat::Domains => format!("{}\n", domain),
+                    ExportFormat::Adblock => format!("||{}^\n", domain),
+                };
+                output.push_str(&rendered);
+            }
+        }
+    }
+
+    if skipped > 0 {
+        tracing::info!(
+            "该格式只能表达屏蔽，已跳过 {} 个指向非黑洞地址的域名",
+            skipped
+        );
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_formats() {
+        let config = Config::from_sources(Vec::new());
+        let sources = vec![
+            SourceResult::for_test(
+                "a",
+                "# 注释\n0.0.0.0 ads.example.com track.example.com\n:: ads.example.com\n",
+            ),
+            SourceResult::for_test("b", "0.0.0.0 ADS.example.com\n192.168.1.2 nas.lan # 内网\n"),
+        ];
+        let export = |format| export_entries(&sources, &config, format).unwrap();
+
+        assert_eq!(
+            export(ExportFormat::Hosts),
+            "0.0.0.0 ads.example.com\n0.0.0.0 track.example.com\n:: ads.example.com\n\
+             192.168.1.2 nas.lan\n"
+        );
+        assert_eq!(
+            export(ExportFormat::Dnsmasq(DnsmasqMode::Sink)),
+            "address=/ads.example.com/0.0.0.0\naddress=/track.example.com/0.0.0.0\n\
+             address=/ads.example.com/::\naddress=/nas.lan/192.168.1.2\n"
+        );
+        assert_eq!(
+            export(ExportFormat::Dnsmasq(DnsmasqMode::Nxdomain)),
+            "server=/ads.example.com/\nserver=/track.example.com/\n\
+             address=/nas.lan/192.168.1.2\n"
+        );
+        assert_eq!(
+            export(ExportFormat::Domains),
+            "ads.example.com\ntrack.example.com\n"
+        );
+        assert_eq!(
+            export(ExportFormat::Adblock),
+            "||ads.example.com^\n||track.example.com^\n"
+        );
+        assert!("pihole".parse::<ExportFormat>().is_err());
+    }
+}
@@ -2,285 +2,1195 @@
 //!
 //! 提供从 URL 获取 hosts 内容的功能。
 
+use crate::cache::load_cached_sources;
+use crate::circuit::CircuitBreakers;
+use crate::config::{Config, DnsResolverConfig, HostsSource, LineRewrite, TlsVersion};
+use crate::git::GitSource;
+use crate::log_limit::{log_failure, log_recovery};
+use crate::hosts::count_entries;
+use crate::preprocess::{json_domains_to_hosts, preprocess_content};
+use crate::public_suffix::{filter_public_suffix, load_public_suffix_list};
+use crate::update::Cancelled;
 use anyhow::{Context, Result};
-use reqwest::blocking::Client;
-use std::time::Duration;
+use chrono::{DateTime, Local, Utc};
+use reqwest::{Certificate, tls};
+use reqwest::blocking::{Client, ClientBuilder, Response};
+use reqwest::header::{ACCEPT_RANGES, CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::io::Read;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+// 校验函数已移至 `validation` 模块，在此重新导出以保持原有路径可用
+pub use crate::validation::{
+    LineError, is_valid_domain, is_valid_ip, validate_hosts_content, validate_hosts_content_collect,
+};
 
 /// HTTP 客户端超时配置
-const DEFAULT_TIMEOUT_SECS: u64 = 30;
+pub const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+/// 单个数据源的获取结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceResult {
+    /// 数据源 URL
+    pub url: String,
+    /// 经过预处理和验证的 hosts 内容
+    pub content: String,
+    /// 原始响应内容的字节数
+    pub bytes: usize,
+    /// 获取时间
+    pub fetched_at: DateTime<Utc>,
+    /// 是否来自本地缓存
+    pub from_cache: bool,
+    /// HTTP 状态码
+    pub status: u16,
+    /// 响应 `Cache-Control` 中的 `max-age`（秒），未提供或禁止缓存时为 `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_secs: Option<u64>,
+}
 
-/// 从 URL 获取 hosts 内容
+impl SourceResult {
+    /// 统计该数据源的有效条目数量
+    pub fn entry_count(&self) -> usize {
+        count_entries(&self.content)
+    }
+}
+
+#[cfg(test)]
+impl SourceResult {
+    /// 测试用的获取结果：刚刚从网络获取，HTTP 状态码为 200
+    pub(crate) fn for_test(url: &str, content: &str) -> Self {
+        SourceResult {
+            url: url.to_string(),
+            content: content.to_string(),
+            bytes: content.len(),
+            fetched_at: Utc::now(),
+            from_cache: false,
+            status: 200,
+            max_age_secs: None,
+        }
+    }
+}
+
+impl From<SourceResult> for (String, String) {
+    fn from(result: SourceResult) -> Self {
+        (result.url, result.content)
+    }
+}
+
+/// 一次更新中所有重试共用的时间预算，对应 `max_total_retry_secs`
 ///
-/// 返回纯文本格式的 hosts 内容，可直接追加到系统 hosts 文件。
-pub fn fetch_hosts_content(url: &str) -> Result<String> {
-    let client = Client::builder()
-        .timeout(Duration::from_secs(DEFAULT_TIMEOUT_SECS))
-        .build()
-        .context("创建 HTTP 客户端失败")?;
+/// 改用后续镜像与断点续传都计为重试。预算用尽后不再发起新的重试，
+/// 重试中的请求超时也不超过剩余预算，因此重试的总耗时不会超出预算。
+#[derive(Debug, Default)]
+pub struct RetryBudget {
+    /// 重试总耗时上限，`None` 表示不限制
+    limit: Option<Duration>,
+    /// 已结束的重试累计耗时
+    spent: Cell<Duration>,
+    /// 进行中的重试的开始时间
+    current: Cell<Option<Instant>>,
+}
 
-    let response = client
-        .get(url)
-        .send()
-        .with_context(|| format!("请求 URL 失败: {}", url))?;
+impl RetryBudget {
+    /// 创建重试预算，`limit` 为 `None` 时不限制
+    pub fn new(limit: Option<Duration>) -> Self {
+        RetryBudget {
+            limit,
+            ..Default::default()
+        }
+    }
 
-    if !response.status().is_success() {
-        return Err(anyhow::anyhow!(
-            "请求失败，HTTP 状态码: {}",
-            response.status()
-        ));
+    /// 按配置的 `max_total_retry_secs` 创建重试预算
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(config.max_total_retry_secs.map(Duration::from_secs))
     }
 
-    let content = response
-        .text()
-        .with_context(|| format!("读取响应内容失败: {}", url))?;
+    /// 重试累计耗时，包括进行中的重试
+    pub fn spent(&self) -> Duration {
+        let current = self.current.get().map_or(Duration::ZERO, |start| start.elapsed());
+        self.spent.get().saturating_add(current)
+    }
 
-    // 验证内容格式
-    validate_hosts_content(&content, url)?;
+    /// 预算是否已用尽
+    fn is_exhausted(&self) -> bool {
+        self.limit.is_some_and(|limit| self.spent() >= limit)
+    }
 
-    Ok(content)
+    /// 请求的超时：重试中不超过剩余预算，否则不变
+    fn timeout(&self, timeout: Duration) -> Duration {
+        match self.limit {
+            Some(limit) if self.current.get().is_some() => {
+                timeout.min(limit.saturating_sub(self.spent()))
+            }
+            _ => timeout,
+        }
+    }
+
+    /// 开始一次重试，返回的计时器释放时累计耗时
+    ///
+    /// 已在重试中时（改用镜像后又断点续传）耗时已由外层计时，不重复累计。
+    fn start(&self) -> RetryTimer<'_> {
+        let owner = self.current.get().is_none();
+        if owner {
+            self.current.set(Some(Instant::now()));
+        }
+        RetryTimer { budget: self, owner }
+    }
 }
 
-/// 验证 hosts 内容格式
-fn validate_hosts_content(content: &str, url: &str) -> Result<()> {
-    if content.trim().is_empty() {
-        return Err(anyhow::anyhow!("URL 返回内容为空: {}", url));
+/// 一次重试的计时器，释放时将耗时计入 [`RetryBudget`]
+struct RetryTimer<'a> {
+    budget: &'a RetryBudget,
+    owner: bool,
+}
+
+impl Drop for RetryTimer<'_> {
+    fn drop(&mut self) {
+        if self.owner
+            && let Some(start) = self.budget.current.take()
+        {
+            let spent = self.budget.spent.get().saturating_add(start.elapsed());
+            self.budget.spent.set(spent);
+        }
     }
+}
+
+/// 获取数据源的 hosts 内容
+///
+/// 按顺序尝试数据源的各个镜像，返回首个成功的镜像经过预处理的纯文本格式 hosts 内容，
+/// 可直接追加到系统 hosts 文件。
+pub fn fetch_hosts_content(source: &HostsSource, config: &Config) -> Result<String> {
+    let budget = RetryBudget::from_config(config);
+    fetch_source_with_mirrors(source, config, &budget).map(|result| result.content)
+}
+
+/// 按顺序尝试数据源的各个镜像，返回首个成功获取并通过验证的结果
+///
+/// 结果的 `url` 始终为数据源标识（首个 URL），以便缓存与状态记录不随所用镜像变化。
+/// 所有镜像均失败时返回最后一个镜像的错误。改用后续镜像计入重试预算，预算用尽后不再尝试其余镜像。
+pub fn fetch_source_with_mirrors(
+    source: &HostsSource,
+    config: &Config,
+    budget: &RetryBudget,
+) -> Result<SourceResult> {
+    let urls = source.urls();
+    let rewrite = source.line_rewrite()?;
+    let mut last_error = None;
 
-    // 检查是否包含非法字符（控制字符等）
-    for (i, c) in content.chars().enumerate() {
-        if c.is_control() && c != '\n' && c != '\r' && c != '\t' {
-            return Err(anyhow::anyhow!(
-                "URL 返回内容包含非法控制字符 (位置 {}): {}",
-                i,
-                url
-            ));
+    for (i, url) in urls.iter().enumerate() {
+        if i > 0 && budget.is_exhausted() {
+            tracing::warn!(
+                "重试总耗时已达 max_total_retry_secs，不再尝试其余镜像: {}",
+                source.url()
+            );
+            let error = last_error.unwrap_or_else(|| anyhow::anyhow!("数据源没有可用的 URL"));
+            return Err(error.context("重试总耗时已达 max_total_retry_secs，未尝试其余镜像"));
         }
+        let _timer = (i > 0).then(|| budget.start());
+        let sha256 = source.sha256();
+        match fetch_source(url, source.json_pointer(), rewrite.as_ref(), sha256, config, budget) {
+            Ok(mut result) => {
+                if i > 0 {
+                    tracing::info!("数据源 {} 使用镜像: {}", source.url(), url);
+                }
+                result.url = source.url().to_string();
+                if let Some(max_entries) = source.max_entries() {
+                    let (content, dropped) = truncate_entries(&result.content, max_entries);
+                    if dropped > 0 {
+                        tracing::info!(
+                            "数据源超过 max_entries，仅保留前 {} 条，截断 {} 条: {}",
+                            max_entries,
+                            dropped,
+                            source.url()
+                        );
+                        result.content = content;
+                    }
+                }
+                return Ok(result);
+            }
+            Err(e) => {
+                if i + 1 < urls.len() {
+                    tracing::warn!("镜像获取失败，尝试下一个: {}, 错误: {}", url, e);
+                }
+                last_error = Some(e);
+            }
+        }
+    }
+
+    let error = last_error.unwrap_or_else(|| anyhow::anyhow!("数据源没有可用的 URL"));
+    if urls.len() > 1 {
+        return Err(error.context(format!("数据源的 {} 个镜像均获取失败", urls.len())));
     }
+    Err(error)
+}
 
-    // 逐行检查 hosts 格式
-    for (line_num, line) in content.lines().enumerate() {
-        let line = line.trim();
+/// 只保留内容中的前 `max_entries` 条条目，返回截断后的内容与丢弃的条目数量
+///
+/// 保留的条目之间的注释与空行原样保留，最后一条保留条目之后的内容全部丢弃。
+fn truncate_entries(content: &str, max_entries: usize) -> (String, usize) {
+    let mut result = String::with_capacity(content.len());
+    let mut kept = 0;
+    let mut dropped = 0;
 
-        // 跳过空行和注释行
-        if line.is_empty() || line.starts_with('#') {
+    for line in content.lines() {
+        let trimmed = line.trim();
+        let is_entry = !trimmed.is_empty() && !trimmed.starts_with('#');
+        if kept == max_entries {
+            dropped += usize::from(is_entry);
             continue;
         }
+        kept += usize::from(is_entry);
+        result.push_str(line);
+        result.push('\n');
+    }
 
-        // 检查是否为有效的 hosts 格式: IP + 域名
-        if let Err(e) = validate_hosts_line(line, line_num + 1, url) {
-            return Err(e);
+    (result, dropped)
+}
+
+/// 获取数据源的当前内容并计算 SHA-256，用于固定数据源内容
+///
+/// 按顺序尝试各镜像，返回首个获取成功的内容的哈希；不校验已固定的值。
+pub fn fetch_source_sha256(source: &HostsSource, config: &Config) -> Result<String> {
+    let mut last_error = None;
+    for url in source.urls() {
+        match fetch_raw(url, config) {
+            Ok((_, content)) => return Ok(sha256_hex(&content)),
+            Err(e) => last_error = Some(e),
         }
     }
 
-    Ok(())
+    Err(last_error.unwrap_or_else(|| anyhow::anyhow!("数据源没有可用的 URL")))
 }
 
-/// 验证单行 hosts 配置格式
-fn validate_hosts_line(line: &str, line_num: usize, url: &str) -> Result<()> {
-    let parts: Vec<&str> = line.split_whitespace().collect();
+/// 计算内容的 SHA-256，以小写十六进制表示
+pub fn sha256_hex(content: &str) -> String {
+    ring::digest::digest(&ring::digest::SHA256, content.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
 
-    if parts.len() < 2 {
+/// 校验下载内容与固定的 SHA-256 是否一致
+fn verify_sha256(content: &str, expected: &str, url: &str) -> Result<()> {
+    let actual = sha256_hex(content);
+    if !actual.eq_ignore_ascii_case(expected) {
         return Err(anyhow::anyhow!(
-            "第 {} 行格式无效，缺少 IP 或域名: {} (来源: {})",
-            line_num,
-            line,
-            url
+            "数据源内容的 SHA-256 与固定值不一致: {}，期望 {}，实际 {}",
+            url,
+            expected,
+            actual
         ));
     }
+    Ok(())
+}
 
-    let ip = parts[0];
+/// 每个请求的总超时
+fn request_timeout(config: &Config) -> Duration {
+    let timeout = config
+        .fetch_timeout_secs
+        .or(config.read_timeout_secs)
+        .unwrap_or(DEFAULT_TIMEOUT_SECS);
+    Duration::from_secs(timeout)
+}
 
-    // 验证 IP 地址格式
-    if !is_valid_ip(ip) {
-        return Err(anyhow::anyhow!(
-            "第 {} 行 IP 地址格式无效: {} (来源: {})",
-            line_num,
-            ip,
-            url
-        ));
+/// 创建 HTTP 客户端
+///
+/// 根据配置添加自定义 CA 证书，或在 `tls_insecure` 时关闭证书校验，并限制最低 TLS 版本、
+/// 使用自定义 DNS 解析器；
+/// 每个请求的总超时依次取 `fetch_timeout_secs`、`read_timeout_secs`，默认为 `DEFAULT_TIMEOUT_SECS`；
+/// 配置了 `connect_timeout_secs` 时另外限制建立连接的时间。
+pub fn build_client(config: &Config) -> Result<Client> {
+    let mut builder = Client::builder().timeout(request_timeout(config));
+
+    if let Some(secs) = config.connect_timeout_secs {
+        builder = builder.connect_timeout(Duration::from_secs(secs));
     }
 
-    // 验证每个域名格式
-    for domain in &parts[1..] {
-        if !is_valid_domain(domain) {
-            return Err(anyhow::anyhow!(
-                "第 {} 行域名格式无效: {} (来源: {})",
-                line_num,
-                domain,
-                url
-            ));
+    if let Some(path) = &config.tls_ca_cert {
+        for cert in load_ca_certificates(path)? {
+            builder = builder.add_root_certificate(cert);
         }
     }
 
-    Ok(())
+    if config.tls_insecure {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+
+    if let Some(version) = config.min_tls_version {
+        builder = builder.min_tls_version(match version {
+            TlsVersion::Tls12 => tls::Version::TLS_1_2,
+            TlsVersion::Tls13 => tls::Version::TLS_1_3,
+        });
+    }
+
+    if let Some(resolver) = &config.dns_resolver {
+        builder = with_dns_resolver(builder, resolver)?;
+    }
+
+    builder.build().context("创建 HTTP 客户端失败")
+}
+
+/// 通过配置的 DNS 服务器解析数据源主机名
+#[cfg(feature = "doh")]
+fn with_dns_resolver(
+    builder: ClientBuilder,
+    resolver: &DnsResolverConfig,
+) -> Result<ClientBuilder> {
+    let resolver = crate::dns::HickoryResolver::new(resolver)?;
+    Ok(builder.dns_resolver(std::sync::Arc::new(resolver)))
+}
+
+/// 未启用 `doh` 特性时无法使用自定义 DNS 解析器
+#[cfg(not(feature = "doh"))]
+fn with_dns_resolver(
+    _builder: ClientBuilder,
+    _resolver: &DnsResolverConfig,
+) -> Result<ClientBuilder> {
+    Err(anyhow::anyhow!("使用自定义 DNS 解析器需要启用 doh 特性"))
 }
 
-/// 验证域名格式
-fn is_valid_domain(domain: &str) -> bool {
-    // 域名不能为空
-    if domain.is_empty() {
-        return false;
+/// 读取 PEM 格式的 CA 证书文件（可包含多个证书）
+pub fn load_ca_certificates(path: &str) -> Result<Vec<Certificate>> {
+    let pem = std::fs::read(path).with_context(|| format!("读取 CA 证书失败: {}", path))?;
+    let certs =
+        Certificate::from_pem_bundle(&pem).with_context(|| format!("解析 CA 证书失败: {}", path))?;
+
+    if certs.is_empty() {
+        return Err(anyhow::anyhow!("CA 证书文件中没有证书: {}", path));
     }
 
-    // 域名长度限制（总长度 253 字符以内）
-    if domain.len() > 253 {
-        return false;
+    Ok(certs)
+}
+
+/// 获取数据源的原始内容（不做预处理与验证），返回 HTTP 状态码与内容
+///
+/// `git+` 开头的数据源从 Git 仓库中读取，见 [`crate::git`]。
+pub fn fetch_raw(url: &str, config: &Config) -> Result<(u16, String)> {
+    let budget = RetryBudget::from_config(config);
+    fetch_raw_response(url, config, &budget).map(|(status, content, _)| (status, content))
+}
+
+/// 获取数据源的原始内容，同时返回响应 `Cache-Control` 中的 `max-age`（秒）
+fn fetch_raw_response(
+    url: &str,
+    config: &Config,
+    budget: &RetryBudget,
+) -> Result<(u16, String, Option<u64>)> {
+    if GitSource::is_git_url(url) {
+        let timeout = budget.timeout(request_timeout(config));
+        return fetch_git_raw(url, config, timeout)
+            .map(|(status, content)| (status, content, None));
     }
 
-    // 每段标签长度限制（1-63 字符）
-    let labels: Vec<&str> = domain.split('.').collect();
-    for label in &labels {
-        let label_len = label.len();
-        if label_len == 0 || label_len > 63 {
-            return false;
+    let client = build_client(config)?;
+    let timeout = request_timeout(config);
+
+    let response = client
+        .get(url)
+        .timeout(budget.timeout(timeout))
+        .send()
+        .with_context(|| format!("请求 URL 失败: {}", url))?;
+
+    if !response.status().is_success() {
+        return Err(anyhow::anyhow!(
+            "请求失败，HTTP 状态码: {}",
+            response.status()
+        ));
+    }
+
+    let status = response.status().as_u16();
+    let max_age = response
+        .headers()
+        .get(reqwest::header::CACHE_CONTROL)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_max_age);
+    let body = read_body_resumable(&client, url, response, config, budget)?;
+    let content = String::from_utf8_lossy(&body).into_owned();
+
+    Ok((status, content, max_age))
+}
+
+/// 响应头的字符串值
+fn header_str(response: &Response, name: reqwest::header::HeaderName) -> Option<&str> {
+    response.headers().get(name).and_then(|value| value.to_str().ok())
+}
+
+/// 读取响应内容，下载中断时从断点续传
+///
+/// 服务器声明 `Accept-Ranges: bytes` 时，连接中断后以 `Range: bytes=<已接收字节数>-` 重新请求，
+/// 并以强 `ETag`（或 `Last-Modified`）作为 `If-Range`，内容已变化时服务器返回完整内容。
+/// 服务器不支持续传（返回 200）或返回的范围与断点不符时，丢弃已接收的内容重新下载完整内容。
+/// 拼接完成后按 `Content-Length` 检查总长度。续传与重新下载计入重试预算，预算用尽后不再续传。
+fn read_body_resumable(
+    client: &Client,
+    url: &str,
+    mut response: Response,
+    config: &Config,
+    budget: &RetryBudget,
+) -> Result<Vec<u8>> {
+    let max_attempts = config.max_resume_attempts;
+    let timeout = request_timeout(config);
+    let mut body = Vec::new();
+    let mut attempts = 0;
+    let mut _timer = None;
+    let mut resumable = false;
+    let mut validator = None;
+    let mut expected_len = None;
+    let mut partial = false;
+
+    loop {
+        if !partial {
+            resumable = header_str(&response, ACCEPT_RANGES)
+                .is_some_and(|value| value.split(',').any(|unit| unit.trim() == "bytes"));
+            validator = header_str(&response, ETAG)
+                .filter(|etag| !etag.starts_with("W/"))
+                .or_else(|| header_str(&response, LAST_MODIFIED))
+                .map(str::to_string);
+            expected_len = response.content_length();
         }
 
-        // 标签必须以字母或数字开头和结尾
-        let bytes = label.as_bytes();
-        let first_char = bytes[0] as char;
-        let last_char = bytes[bytes.len() - 1] as char;
+        let error = match response.read_to_end(&mut body) {
+            Ok(_) => break,
+            Err(e) => e,
+        };
+        if !resumable || body.is_empty() || attempts >= max_attempts {
+            return Err(anyhow::Error::new(error))
+                .with_context(|| format!("读取响应内容失败: {}", url));
+        }
+        if budget.is_exhausted() {
+            tracing::warn!("重试总耗时已达 max_total_retry_secs，不再续传: {}", url);
+            return Err(anyhow::Error::new(error))
+                .with_context(|| format!("读取响应内容失败: {}", url));
+        }
 
-        if !first_char.is_alphanumeric() || !last_char.is_alphanumeric() {
-            return false;
+        _timer.get_or_insert_with(|| budget.start());
+        attempts += 1;
+        tracing::warn!(
+            "响应内容下载中断（已接收 {} 字节），从断点续传（第 {} 次）: {}, 错误: {}",
+            body.len(),
+            attempts,
+            url,
+            error
+        );
+        let mut request = client
+            .get(url)
+            .timeout(budget.timeout(timeout))
+            .header(RANGE, format!("bytes={}-", body.len()));
+        if let Some(validator) = &validator {
+            request = request.header(IF_RANGE, validator.as_str());
         }
+        response = request
+            .send()
+            .with_context(|| format!("续传请求失败: {}", url))?;
 
-        // 标签只能包含字母、数字和连字符
-        for &byte in bytes {
-            let c = byte as char;
-            if !c.is_alphanumeric() && c != '-' {
-                return false;
-            }
+        let resumed_at = format!("bytes {}-", body.len());
+        partial = response.status() == reqwest::StatusCode::PARTIAL_CONTENT
+            && header_str(&response, CONTENT_RANGE)
+                .is_some_and(|range| range.starts_with(&resumed_at));
+        if partial {
+            continue;
+        }
+        if response.status() == reqwest::StatusCode::PARTIAL_CONTENT {
+            response = client
+                .get(url)
+                .timeout(budget.timeout(timeout))
+                .send()
+                .with_context(|| format!("请求 URL 失败: {}", url))?;
+        }
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!("续传请求失败，HTTP 状态码: {}", response.status()));
         }
+        tracing::warn!("服务器未按断点返回内容，重新下载完整内容: {}", url);
+        body.clear();
     }
 
-    true
+    if let Some(expected) = expected_len
+        && body.len() as u64 != expected
+    {
+        return Err(anyhow::anyhow!(
+            "下载内容不完整: 期望 {} 字节，实际 {} 字节: {}",
+            expected,
+            body.len(),
+            url
+        ));
+    }
+
+    Ok(body)
 }
 
-/// 验证 IP 地址格式（支持 IPv4 和 IPv6）
-fn is_valid_ip(ip: &str) -> bool {
-    // IPv4 检查
-    if ip.parse::<std::net::Ipv4Addr>().is_ok() {
-        return true;
+/// 解析 `Cache-Control` 中的 `max-age`（秒）
+///
+/// 带有 `no-store` 或 `no-cache`（每次使用前都须向服务器确认）时视为不可缓存，`max-age=0` 同样返回 `None`。
+/// `s-maxage` 只适用于共享缓存，不予采用。
+pub fn parse_max_age(cache_control: &str) -> Option<u64> {
+    let mut max_age = None;
+    for directive in cache_control.split(',') {
+        let (name, value) = match directive.split_once('=') {
+            Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+            None => (directive.trim(), None),
+        };
+        if name.eq_ignore_ascii_case("no-store") || name.eq_ignore_ascii_case("no-cache") {
+            return None;
+        }
+        if name.eq_ignore_ascii_case("max-age") {
+            max_age = value.and_then(|value| value.parse().ok());
+        }
     }
+    max_age.filter(|&secs| secs > 0)
+}
 
-    // IPv6 检查（方括号格式）
-    if ip.starts_with('[') && ip.ends_with(']') {
-        let ipv6 = &ip[1..ip.len() - 1];
-        return ipv6.parse::<std::net::Ipv6Addr>().is_ok();
-    }
+/// 获取 Git 数据源中的文件，成功时视为状态码 200，与 HTTP 请求一样受总超时限制
+#[cfg(feature = "git")]
+fn fetch_git_raw(url: &str, config: &Config, timeout: Duration) -> Result<(u16, String)> {
+    Ok((200, crate::git::fetch_git(url, config, timeout)?))
+}
+
+/// 未启用 `git` 特性时无法获取 Git 数据源
+#[cfg(not(feature = "git"))]
+fn fetch_git_raw(url: &str, _config: &Config, _timeout: Duration) -> Result<(u16, String)> {
+    Err(anyhow::anyhow!("获取 Git 数据源需要启用 git 特性: {}", url))
+}
 
-    // 纯 IPv6 检查
-    if ip.parse::<std::net::Ipv6Addr>().is_ok() {
-        return true;
+/// 从 URL 获取单个数据源，返回包含元数据的获取结果
+///
+/// 固定了 SHA-256 时先校验下载内容；`json_pointer` 不为 `None` 时将 JSON 数据源中该位置的域名数组
+/// 转换为 hosts 条目；配置了逐行替换规则时，先替换再预处理与验证。断点续传计入重试预算 `budget`。
+pub fn fetch_source(
+    url: &str,
+    json_pointer: Option<&str>,
+    rewrite: Option<&LineRewrite>,
+    sha256: Option<&str>,
+    config: &Config,
+    budget: &RetryBudget,
+) -> Result<SourceResult> {
+    let (status, content, max_age_secs) = fetch_raw_response(url, config, budget)?;
+    let bytes = content.len();
+    if let Some(expected) = sha256 {
+        verify_sha256(&content, expected, url)?;
     }
+    let content = match json_pointer {
+        Some(pointer) => json_domains_to_hosts(&content, pointer, url)?,
+        None => content,
+    };
+
+    // 预处理后验证内容格式
+    let content = match rewrite {
+        Some(rewrite) => preprocess_content(&rewrite.apply(&content), config),
+        None => preprocess_content(&content, config),
+    };
+    validate_hosts_content(&content, url)?;
+
+    Ok(SourceResult {
+        url: url.to_string(),
+        content,
+        bytes,
+        fetched_at: Utc::now(),
+        from_cache: false,
+        status,
+        max_age_secs,
+    })
+}
 
-    false
+/// 数据源获取进度
+#[derive(Debug, Clone)]
+pub struct FetchProgress {
+    /// 已完成（成功或失败）的数据源数量
+    pub completed: usize,
+    /// 数据源总数
+    pub total: usize,
+    /// 刚完成的数据源 URL
+    pub url: String,
+    /// 该数据源是否获取成功
+    pub success: bool,
+}
+
+/// 获取失败的数据源
+///
+/// 作为错误上下文附加在 `fetch_all_hosts` 返回的错误上，调用方可通过
+/// `downcast_ref::<FailedSource>()` 得知是哪个数据源失败。
+#[derive(Debug, Clone)]
+pub struct FailedSource {
+    /// 数据源 URL
+    pub url: String,
+}
+
+impl std::fmt::Display for FailedSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "获取数据源失败: {}", self.url)
+    }
 }
 
 /// 批量获取多个数据源的 hosts 内容
 ///
-/// 返回 (URL, 内容) 元组的向量。
-pub fn fetch_all_hosts(sources: &[String]) -> Result<Vec<(String, String)>> {
+/// 返回每个数据源的获取结果。仅包含注释的数据源视为有效但没有条目，会记录警告。
+pub fn fetch_all_hosts(config: &Config) -> Result<Vec<SourceResult>> {
+    fetch_all_hosts_with_progress(config, |_| {})
+}
+
+/// 批量获取多个数据源的 hosts 内容，并在每个数据源完成时回调进度
+///
+/// 回调在数据源获取成功或失败后各调用一次，可用于渲染进度条等界面。
+pub fn fetch_all_hosts_with_progress(
+    config: &Config,
+    on_progress: impl Fn(FetchProgress),
+) -> Result<Vec<SourceResult>> {
+    fetch_all_hosts_cancellable(config, &CancellationToken::new(), on_progress)
+}
+
+/// 批量获取多个数据源的 hosts 内容，`cancel` 被取消后不再获取剩余的数据源
+///
+/// 与 [`fetch_sources_cancellable`] 相同，但只返回成功获取的数据源。
+pub fn fetch_all_hosts_cancellable(
+    config: &Config,
+    cancel: &CancellationToken,
+    on_progress: impl Fn(FetchProgress),
+) -> Result<Vec<SourceResult>> {
+    fetch_sources_cancellable(config, cancel, on_progress).map(|fetched| fetched.sources)
+}
+
+/// 批量获取的结果
+#[derive(Debug, Default)]
+pub struct FetchedSources {
+    /// 成功获取（或复用缓存）的数据源
+    pub sources: Vec<SourceResult>,
+    /// `fail_fast = false` 时被跳过的失败数据源及其错误信息
+    pub failed: Vec<(FailedSource, String)>,
+}
+
+/// 批量获取多个数据源的 hosts 内容，并返回被跳过的失败数据源
+///
+/// 数据源（及其镜像）按配置顺序逐个获取，同一时刻最多只有一个请求，
+/// 多个数据源位于同一主机时也不会并发访问该主机。
+/// URL 匹配 `disabled_source_patterns` 或带有 `disabled_tags` 中标签的数据源被跳过，不出现在结果中。
+/// 启用 `require_public_suffix` 时，先加载公共后缀列表，再从每个数据源中丢弃没有已知公共后缀的域名。
+///
+/// `fail_fast` 时任一数据源失败即返回错误；否则跳过失败的数据源继续获取，
+/// 只有全部数据源都失败时才返回错误。
+///
+/// 配置了 `circuit_breaker_threshold` 时，处于熔断冷却期的数据源不发起请求：缓存中有该数据源时复用缓存内容，
+/// 否则与失败的数据源一样被跳过（即使 `fail_fast` 也不终止本次更新）。
+///
+/// 取消时返回 [`Cancelled`] 错误；正在进行的请求不会被中断，由调用方决定是否等待。
+pub fn fetch_sources_cancellable(
+    config: &Config,
+    cancel: &CancellationToken,
+    on_progress: impl Fn(FetchProgress),
+) -> Result<FetchedSources> {
     let mut results = Vec::new();
+    let mut failed = Vec::new();
+    let total = config.hosts_sources.len();
+    let disabled = config.disabled_sources()?;
+    let suffix_list = if config.require_public_suffix {
+        Some(load_public_suffix_list(config)?)
+    } else {
+        None
+    };
+    let mut breakers = config.circuit_breaker_threshold.map(|_| {
+        CircuitBreakers::load(&config.cache_dir).unwrap_or_else(|e| {
+            tracing::warn!("读取熔断状态失败，按全部数据源正常处理: {:?}", e);
+            CircuitBreakers::default()
+        })
+    });
+    let cached = if config.honor_cache_control
+        || breakers.is_some()
+        || config
+            .hosts_sources
+            .iter()
+            .any(|source| matches!(source.min_refresh_hours(), Ok(Some(_))))
+    {
+        load_cached_sources(&config.cache_dir).unwrap_or_else(|e| {
+            tracing::warn!("读取本地缓存失败，按没有缓存处理: {:?}", e);
+            Vec::new()
+        })
+    } else {
+        Vec::new()
+    };
+    let budget = RetryBudget::from_config(config);
+
+    for (i, hosts_source) in config.hosts_sources.iter().enumerate() {
+        if cancel.is_cancelled() {
+            save_breakers(config, &breakers);
+            return Err(Cancelled.into());
+        }
 
-    for url in sources {
-        match fetch_hosts_content(url) {
-            Ok(content) => {
-                results.push((url.clone(), content));
-                tracing::info!("成功获取 hosts 内容: {}", url);
+        let url = hosts_source.url();
+        let disabled_tag = config.disabled_tag(hosts_source);
+        if disabled.is_match(url) || disabled_tag.is_some() {
+            match disabled_tag {
+                Some(tag) => tracing::info!("数据源的标签 {} 已停用，已跳过: {}", tag, url),
+                None => tracing::info!("数据源匹配 disabled_source_patterns，已跳过: {}", url),
+            }
+            on_progress(FetchProgress {
+                completed: i + 1,
+                total,
+                url: url.to_string(),
+                success: true,
+            });
+            continue;
+        }
+
+        let reused = match recent_cached_source(hosts_source, &cached)? {
+            Some(source) => {
+                tracing::info!(
+                    "数据源未到 min_refresh_interval，复用缓存内容: {} (获取于 {})",
+                    url,
+                    source.fetched_at
+                );
+                Some(source)
+            }
+            None if config.honor_cache_control => {
+                fresh_cached_source(hosts_source, &cached).inspect(|source| {
+                    tracing::info!(
+                        "数据源的 Cache-Control max-age 未过期，复用缓存内容: {} (获取于 {}，max-age {} 秒)",
+                        url,
+                        source.fetched_at,
+                        source.max_age_secs.unwrap_or_default()
+                    );
+                })
+            }
+            None => None,
+        };
+        if let Some(source) = reused {
+            on_progress(FetchProgress {
+                completed: i + 1,
+                total,
+                url: url.to_string(),
+                success: true,
+            });
+            results.push(source);
+            continue;
+        }
+
+        if let Some(until) = breakers
+            .as_mut()
+            .and_then(|breakers| breakers.open_until(url, Utc::now()))
+        {
+            let until = until.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S");
+            let source = cached.iter().find(|result| result.url == url).cloned();
+            on_progress(FetchProgress {
+                completed: i + 1,
+                total,
+                url: url.to_string(),
+                success: source.is_some(),
+            });
+            match source {
+                Some(source) => {
+                    tracing::info!("数据源处于熔断冷却期（至 {}），复用缓存内容: {}", until, url);
+                    results.push(source);
+                }
+                None => {
+                    tracing::warn!("数据源处于熔断冷却期（至 {}），已跳过: {}", until, url);
+                    let source = FailedSource {
+                        url: url.to_string(),
+                    };
+                    failed.push((source, format!("熔断冷却中，至 {} 前不再获取", until)));
+                }
+            }
+            continue;
+        }
+
+        let result = fetch_source_with_mirrors(hosts_source, config, &budget);
+        on_progress(FetchProgress {
+            completed: i + 1,
+            total,
+            url: url.to_string(),
+            success: result.is_ok(),
+        });
+
+        match result {
+            Ok(mut source) => {
+                log_recovery(url);
+                if let Some(breakers) = &mut breakers {
+                    breakers.record_success(url);
+                }
+                if let Some(list) = &suffix_list {
+                    let (content, removed) = filter_public_suffix(&source.content, list);
+                    if removed > 0 {
+                        tracing::info!("丢弃 {} 个没有已知公共后缀的域名: {}", removed, url);
+                    }
+                    source.content = content;
+                }
+                let entries = source.entry_count();
+                if entries == 0 {
+                    tracing::warn!("数据源仅包含注释，无有效条目: {}", url);
+                } else {
+                    tracing::info!("成功获取 hosts 内容: {} ({} 条)", url, entries);
+                }
+                results.push(source);
             }
             Err(e) => {
-                tracing::error!("获取 hosts 内容失败: {}, 错误: {}", url, e);
-                return Err(e);
+                log_failure(
+                    url,
+                    &format!("获取 hosts 内容失败: {}", e),
+                    config.log_dedup_window(),
+                );
+                if let (Some(breakers), Some(threshold)) =
+                    (&mut breakers, config.circuit_breaker_threshold)
+                {
+                    let cooldown = config.circuit_breaker_cooldown();
+                    breakers.record_failure(url, threshold, cooldown, Utc::now());
+                }
+                let source = FailedSource {
+                    url: url.to_string(),
+                };
+                if config.fail_fast {
+                    save_breakers(config, &breakers);
+                    log_retry_time(&budget);
+                    return Err(e.context(source));
+                }
+                tracing::warn!("跳过获取失败的数据源，继续获取其余数据源: {}", url);
+                failed.push((source, format!("{:#}", e)));
             }
         }
     }
 
-    Ok(results)
+    save_breakers(config, &breakers);
+    log_retry_time(&budget);
+
+    // 全部数据源都失败时与 fail_fast 一样返回错误，以便调用方回退到本地缓存
+    if results.is_empty()
+        && let Some((source, error)) = failed.pop()
+    {
+        return Err(anyhow::anyhow!("所有数据源均获取失败，最后的错误: {}", error).context(source));
+    }
+
+    Ok(FetchedSources {
+        sources: results,
+        failed,
+    })
+}
+
+/// 保存熔断状态，保存失败仅输出警告
+fn save_breakers(config: &Config, breakers: &Option<CircuitBreakers>) {
+    if let Some(breakers) = breakers
+        && let Err(e) = breakers.save(&config.cache_dir)
+    {
+        tracing::warn!("保存熔断状态失败: {:?}", e);
+    }
+}
+
+/// 输出本次获取中重试的总耗时，没有重试时不输出
+fn log_retry_time(budget: &RetryBudget) {
+    let spent = budget.spent();
+    if !spent.is_zero() {
+        tracing::info!("本次获取的重试共耗时 {:.1} 秒", spent.as_secs_f64());
+    }
+}
+
+/// 查找响应的 `Cache-Control: max-age` 尚未过期的缓存结果
+fn fresh_cached_source(source: &HostsSource, cached: &[SourceResult]) -> Option<SourceResult> {
+    let now = Utc::now();
+    cached
+        .iter()
+        .find(|result| {
+            result.url == source.url()
+                && result.max_age_secs.is_some_and(|secs| {
+                    // max-age 来自服务器，超出时间范围时视为一直有效而不是溢出
+                    i64::try_from(secs)
+                        .ok()
+                        .and_then(chrono::TimeDelta::try_seconds)
+                        .and_then(|max_age| result.fetched_at.checked_add_signed(max_age))
+                        .is_none_or(|expires| expires > now)
+                })
+        })
+        .cloned()
+}
+
+/// 查找数据源在 `min_refresh_interval` 内获取过的缓存结果
+fn recent_cached_source(
+    source: &HostsSource,
+    cached: &[SourceResult],
+) -> Result<Option<SourceResult>> {
+    let Some(hours) = source.min_refresh_hours()? else {
+        return Ok(None);
+    };
+
+    let interval = i64::try_from(hours)
+        .ok()
+        .and_then(chrono::TimeDelta::try_hours)
+        .ok_or_else(|| anyhow::anyhow!("min_refresh_interval 超出范围: {} 小时", hours))?;
+    let refresh_after = Utc::now() - interval;
+    Ok(cached
+        .iter()
+        .find(|result| result.url == source.url() && result.fetched_at > refresh_after)
+        .cloned())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::SourceSpec;
 
     #[test]
-    fn test_validate_hosts_content_valid() {
-        let content = r#"
-# 注释行
-127.0.0.1 localhost
-192.168.1.100 example.com
-"#;
+    fn test_fetch_all_skips_disabled_sources() {
+        // 被跳过的数据源不会发起请求，端口 1 无法连接也不影响结果
+        let mut config = Config::from_sources(vec!["http://127.0.0.1:1/hosts".to_string()]);
+        config.disabled_source_patterns = vec![r"^http://127\.0\.0\.1:1/".to_string()];
+        let results = fetch_all_hosts_cancellable(&config, &CancellationToken::new(), |_| {});
+        assert!(results.unwrap().is_empty());
 
-        assert!(validate_hosts_content(content, "https://example.com").is_ok());
-    }
+        config.disabled_source_patterns = vec!["(".to_string()];
+        assert!(crate::config::validate_config(&config).is_err());
 
-    #[test]
-    fn test_validate_hosts_content_empty() {
-        let content = "";
-        assert!(validate_hosts_content(content, "https://example.com").is_err());
+        // 带有已停用标签的数据源同样被跳过；命令行启用标签后恢复获取
+        let mut config = Config::from_sources(Vec::new());
+        config.hosts_sources = vec![HostsSource::Detailed(SourceSpec {
+            urls: vec!["http://127.0.0.1:1/hosts".to_string()],
+            tags: vec!["social".to_string(), "ads".to_string()],
+            ..Default::default()
+        })];
+        config.apply_tag_overrides(&[], &["ads".to_string()]);
+        assert_eq!(config.disabled_tag(&config.hosts_sources[0]), Some("ads"));
+        let results = fetch_all_hosts_cancellable(&config, &CancellationToken::new(), |_| {});
+        assert!(results.unwrap().is_empty());
+
+        config.apply_tag_overrides(&["ads".to_string()], &[]);
+        assert!(config.disabled_tags.is_empty());
     }
 
     #[test]
-    fn test_validate_hosts_content_with_control_chars() {
-        let content = "127.0.0.1 localhost\x00";
-        assert!(validate_hosts_content(content, "https://example.com").is_err());
+    fn test_recent_cached_source() {
+        let source = HostsSource::Detailed(SourceSpec {
+            urls: vec!["https://a.example/hosts".to_string()],
+            min_refresh_interval: Some("1d".to_string()),
+            ..Default::default()
+        });
+        let cached = |url, hours_ago| SourceResult {
+            fetched_at: Utc::now() - chrono::Duration::hours(hours_ago),
+            from_cache: true,
+            ..SourceResult::for_test(url, "0.0.0.0 a.com\n")
+        };
+
+        let recent = vec![
+            cached("https://b.example/hosts", 1),
+            cached("https://a.example/hosts", 2),
+        ];
+        assert_eq!(
+            recent_cached_source(&source, &recent).unwrap(),
+            Some(recent[1].clone())
+        );
+
+        let stale = vec![cached("https://a.example/hosts", 25)];
+        assert_eq!(recent_cached_source(&source, &stale).unwrap(), None);
+
+        let plain = HostsSource::Url("https://a.example/hosts".to_string());
+        assert_eq!(recent_cached_source(&plain, &recent).unwrap(), None);
+
+        // 过长的间隔在验证时报错，不会在计算时间时溢出
+        let huge = HostsSource::Detailed(SourceSpec {
+            urls: vec!["https://a.example/hosts".to_string()],
+            min_refresh_interval: Some(format!("{}d", u64::MAX / 24)),
+            ..Default::default()
+        });
+        assert!(recent_cached_source(&huge, &recent).is_err());
+        let config = Config {
+            hosts_sources: vec![huge],
+            ..Config::from_sources(Vec::new())
+        };
+        assert!(crate::config::validate_config(&config).is_err());
     }
 
     #[test]
-    fn test_validate_hosts_line_valid_ipv4() {
-        assert!(is_valid_ip("127.0.0.1"));
-        assert!(is_valid_ip("192.168.1.100"));
-        assert!(is_valid_ip("0.0.0.0"));
+    fn test_cache_control_max_age() {
+        assert_eq!(parse_max_age("public, max-age=3600"), Some(3600));
+        assert_eq!(parse_max_age("Max-Age=\"60\", must-revalidate"), Some(60));
+        assert_eq!(parse_max_age("max-age=0"), None);
+        assert_eq!(parse_max_age("no-cache, max-age=3600"), None);
+        assert_eq!(parse_max_age("no-store"), None);
+        assert_eq!(parse_max_age("s-maxage=600"), None);
+
+        let source = HostsSource::Url("https://a.example/hosts".to_string());
+        let cached = |minutes_ago, max_age_secs| SourceResult {
+            fetched_at: Utc::now() - chrono::Duration::minutes(minutes_ago),
+            from_cache: true,
+            max_age_secs,
+            ..SourceResult::for_test("https://a.example/hosts", "0.0.0.0 a.com\n")
+        };
+        assert!(fresh_cached_source(&source, &[cached(30, Some(3600))]).is_some());
+        assert!(fresh_cached_source(&source, &[cached(90, Some(3600))]).is_none());
+        assert!(fresh_cached_source(&source, &[cached(1, None)]).is_none());
+        assert!(fresh_cached_source(&source, &[cached(1, Some(u64::MAX))]).is_some());
     }
 
-    #[test]
-    fn test_validate_hosts_line_valid_ipv6() {
-        assert!(is_valid_ip("::1"));
-        assert!(is_valid_ip("2001:0db8:85a3:0000:0000:8a2e:0370:7334"));
-        assert!(is_valid_ip("[::1]"));
+    /// 依次以预设的原始响应应答每个连接，返回收到的请求
+    fn serve_responses(responses: Vec<Vec<u8>>) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        use std::io::Write;
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hosts", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = [0; 4096];
+                let len = stream.read(&mut request).unwrap();
+                requests.push(String::from_utf8_lossy(&request[..len]).to_lowercase());
+                stream.write_all(&response).unwrap();
+            }
+            requests
+        });
+        (url, handle)
     }
 
     #[test]
-    fn test_validate_hosts_line_invalid_ip() {
-        assert!(!is_valid_ip("invalid"));
-        assert!(!is_valid_ip("256.1.1.1"));
-        assert!(!is_valid_ip("abc.def.ghi.jkl"));
+    fn test_resume_interrupted_download() {
+        let full = "0.0.0.0 a.com\n0.0.0.0 b.com\n";
+        let head = "HTTP/1.1 200 OK\r\ncontent-length: 28\r\naccept-ranges: bytes\r\n\
+                    etag: \"v1\"\r\nconnection: close\r\n\r\n";
+        // 首次响应只发送前 14 字节后断开，续传请求返回剩余部分
+        let (url, server) = serve_responses(vec![
+            format!("{}{}", head, &full[..14]).into_bytes(),
+            format!(
+                "HTTP/1.1 206 Partial Content\r\ncontent-length: 14\r\n\
+                 content-range: bytes 14-27/28\r\nconnection: close\r\n\r\n{}",
+                &full[14..]
+            )
+            .into_bytes(),
+        ]);
+        let config = Config::from_sources(vec![url.clone()]);
+        assert_eq!(fetch_raw(&url, &config).unwrap().1, full);
+        let requests = server.join().unwrap();
+        assert!(requests[1].contains("range: bytes=14-"));
+        assert!(requests[1].contains("if-range: \"v1\""));
+
+        // 服务器忽略 Range 返回完整内容时重新下载
+        let (url, server) = serve_responses(vec![
+            format!("{}{}", head, &full[..14]).into_bytes(),
+            format!("{}{}", head, full).into_bytes(),
+        ]);
+        assert_eq!(fetch_raw(&url, &config).unwrap().1, full);
+        server.join().unwrap();
+
+        // 不允许续传时直接失败
+        let (url, server) = serve_responses(vec![format!("{}{}", head, &full[..14]).into_bytes()]);
+        let mut config = Config::from_sources(vec![url.clone()]);
+        config.max_resume_attempts = 0;
+        assert!(fetch_raw(&url, &config).is_err());
+        server.join().unwrap();
     }
 
     #[test]
-    fn test_validate_hosts_content_invalid_line() {
-        let content = "127.0.0.1\ninvalid_line_without_ip\n192.168.1.100 example.com";
-        assert!(validate_hosts_content(content, "https://example.com").is_err());
+    fn test_retry_budget() {
+        use std::io::Write;
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hosts", listener.local_addr().unwrap());
+        let server = listener.try_clone().unwrap();
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = server.accept().unwrap();
+            let mut request = [0; 4096];
+            let _ = stream.read(&mut request).unwrap();
+            let response = "HTTP/1.1 200 OK\r\ncontent-length: 28\r\naccept-ranges: bytes\r\n\
+                            connection: close\r\n\r\n0.0.0.0 a.com\n";
+            stream.write_all(response.as_bytes()).unwrap();
+        });
+
+        // 预算为 0 时下载中断后不再续传
+        let mut config = Config::from_sources(vec![url.clone()]);
+        config.max_total_retry_secs = Some(0);
+        assert!(fetch_raw(&url, &config).is_err());
+        handle.join().unwrap();
+        listener.set_nonblocking(true).unwrap();
+        assert!(listener.accept().is_err());
+
+        // 首个镜像失败后不再尝试其余镜像
+        let source = HostsSource::Detailed(SourceSpec {
+            urls: vec!["http://127.0.0.1:1/hosts".to_string(), url],
+            ..Default::default()
+        });
+        let budget = RetryBudget::from_config(&config);
+        assert!(fetch_source_with_mirrors(&source, &config, &budget).is_err());
+        assert!(listener.accept().is_err());
+
+        // 重试中的请求超时不超过剩余预算，嵌套的重试不重复计时
+        let budget = RetryBudget::new(Some(Duration::from_secs(1)));
+        let timeout = Duration::from_secs(30);
+        assert_eq!(budget.timeout(timeout), timeout);
+        {
+            let _timer = budget.start();
+            let nested = budget.start();
+            assert!(budget.timeout(timeout) <= Duration::from_secs(1));
+            std::thread::sleep(Duration::from_millis(20));
+            drop(nested);
+            assert!(budget.current.get().is_some());
+            assert_eq!(budget.spent.get(), Duration::ZERO);
+        }
+        assert!(budget.spent.get() >= Duration::from_millis(20));
+        assert!(budget.current.get().is_none());
     }
 
     #[test]
-    fn test_validate_hosts_content_missing_domain() {
-        let content = "127.0.0.1";
-        assert!(validate_hosts_content(content, "https://example.com").is_err());
+    fn test_fetch_sources_without_fail_fast() {
+        let body = "0.0.0.0 a.com\n";
+        let (url, server) = serve_responses(vec![
+            format!(
+                "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .into_bytes(),
+        ]);
+        let unreachable = "http://127.0.0.1:1/hosts".to_string();
+        let mut config = Config::from_sources(vec![unreachable.clone(), url.clone()]);
+        config.fail_fast = false;
+
+        let cancel = CancellationToken::new();
+        let fetched = fetch_sources_cancellable(&config, &cancel, |_| {}).unwrap();
+        server.join().unwrap();
+        assert_eq!(fetched.sources.len(), 1);
+        assert_eq!(fetched.sources[0].url, url);
+        assert_eq!(fetched.failed.len(), 1);
+        assert_eq!(fetched.failed[0].0.url, unreachable);
+
+        // 全部失败时仍返回错误
+        let mut config = Config::from_sources(vec![unreachable.clone()]);
+        config.fail_fast = false;
+        let error = fetch_sources_cancellable(&config, &cancel, |_| {});
+        assert!(error.unwrap_err().downcast_ref::<FailedSource>().is_some());
     }
 
     #[test]
-    fn test_is_valid_domain_valid() {
-        assert!(is_valid_domain("example.com"));
-        assert!(is_valid_domain("sub.example.com"));
-        assert!(is_valid_domain("localhost"));
-        assert!(is_valid_domain("my-server-123.com"));
-        assert!(is_valid_domain("a1b2c3.com"));
+    fn test_fetch_ignores_corrupt_cache() {
+        let body = "0.0.0.0 a.com\n";
+        let (url, server) = serve_responses(vec![
+            format!(
+                "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .into_bytes(),
+        ]);
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("hosts_cache.json.gz"), "not gzip").unwrap();
+        let mut config = Config::from_sources(vec![url.clone()]);
+        config.cache_dir = Some(dir.path().to_string_lossy().to_string());
+        assert!(config.honor_cache_control);
+
+        // 损坏的缓存按没有缓存处理，照常获取
+        let fetched = fetch_sources_cancellable(&config, &CancellationToken::new(), |_| {});
+        server.join().unwrap();
+        assert_eq!(fetched.unwrap().sources[0].content, body);
     }
 
     #[test]
-    fn test_is_valid_domain_invalid() {
-        assert!(!is_valid_domain(""));
-        assert!(!is_valid_domain("-invalid.com"));
-        assert!(!is_valid_domain("invalid-.com"));
-        assert!(!is_valid_domain("invalid..com"));
-        assert!(!is_valid_domain("invalid_domain.com"));
-        assert!(!is_valid_domain("exam ple.com"));
+    fn test_truncate_entries() {
+        let content = "# 头部\n0.0.0.0 a.com\n\n0.0.0.0 b.com\n# 尾部\n0.0.0.0 c.com\n";
+        assert_eq!(
+            truncate_entries(content, 2),
+            ("# 头部\n0.0.0.0 a.com\n\n0.0.0.0 b.com\n".to_string(), 1)
+        );
+        assert_eq!(truncate_entries(content, 3), (content.to_string(), 0));
     }
 
     #[test]
-    fn test_validate_hosts_content_invalid_domain() {
-        let content = "127.0.0.1 -invalid.com";
-        assert!(validate_hosts_content(content, "https://example.com").is_err());
+    fn test_verify_sha256() {
+        let expected = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        assert_eq!(sha256_hex("abc"), expected);
+        assert!(verify_sha256("abc", expected, "https://a.example/hosts").is_ok());
+        assert!(verify_sha256("abc", &expected.to_uppercase(), "https://a.example/hosts").is_ok());
+        assert!(verify_sha256("abd", expected, "https://a.example/hosts").is_err());
     }
 }
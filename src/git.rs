@@ -0,0 +1,324 @@
+//! Git 数据源模块
+//!
+//! 支持 `git+<仓库地址>[@<分支或标签>]#<文件路径>` 形式的数据源：获取时在缓存目录中维护仓库的浅克隆，
+//! 每次更新拉取最新提交后读取指定文件。实际获取需启用 `git` 特性，并依赖系统中的 `git` 命令。
+
+use anyhow::Result;
+
+/// Git 数据源 URL 的前缀
+pub const GIT_PREFIX: &str = "git+";
+
+/// 解析后的 Git 数据源
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitSource {
+    /// 仓库地址（去掉 `git+` 前缀与分支）
+    pub repo: String,
+    /// 分支或标签，未指定时使用远程默认分支
+    pub reference: Option<String>,
+    /// 仓库内的文件路径
+    pub path: String,
+}
+
+impl GitSource {
+    /// 是否为 Git 数据源 URL
+    pub fn is_git_url(url: &str) -> bool {
+        url.starts_with(GIT_PREFIX)
+    }
+
+    /// 解析 Git 数据源 URL
+    ///
+    /// 仓库地址支持 `https`、`http`、`ssh` 与 `file` 协议；分支写在仓库地址最后一段的 `@` 之后，
+    /// 文件路径写在 `#` 之后，不能为绝对路径或包含 `..`。
+    pub fn parse(url: &str) -> Result<Self> {
+        let rest = url
+            .strip_prefix(GIT_PREFIX)
+            .ok_or_else(|| anyhow::anyhow!("Git 数据源必须以 {} 开头: {}", GIT_PREFIX, url))?;
+        let (repo, path) = rest
+            .split_once('#')
+            .ok_or_else(|| anyhow::anyhow!("Git 数据源缺少 #<文件路径>: {}", url))?;
+
+        if !["https://", "http://", "ssh://", "file://"]
+            .iter()
+            .any(|scheme| repo.starts_with(scheme))
+        {
+            return Err(anyhow::anyhow!(
+                "Git 数据源仅支持 https、http、ssh、file 协议: {}",
+                url
+            ));
+        }
+        // 仓库地址与分支都会作为 git 命令的参数
+        if repo.chars().any(|c| c.is_whitespace() || c.is_control()) {
+            return Err(anyhow::anyhow!("Git 数据源地址不能包含空白或控制字符: {}", url));
+        }
+        if path.is_empty() || path.starts_with('/') || path.split('/').any(|part| part == "..") {
+            return Err(anyhow::anyhow!("Git 数据源的文件路径无效: {}", url));
+        }
+
+        // 分支只可能出现在最后一段，之前的 `@` 属于用户名
+        let last_segment = repo.rfind('/').map_or(0, |pos| pos + 1);
+        let (repo, reference) = match repo[last_segment..].rfind('@') {
+            Some(pos) => {
+                let at = last_segment + pos;
+                (&repo[..at], Some(repo[at + 1..].to_string()))
+            }
+            None => (repo, None),
+        };
+        if reference.as_deref() == Some("") {
+            return Err(anyhow::anyhow!("Git 数据源的分支不能为空: {}", url));
+        }
+        // 分支不能被 git fetch 解析为选项
+        if reference.as_deref().is_some_and(|reference| reference.starts_with('-')) {
+            return Err(anyhow::anyhow!("Git 数据源的分支无效: {}", url));
+        }
+
+        Ok(GitSource {
+            repo: repo.to_string(),
+            reference,
+            path: path.to_string(),
+        })
+    }
+
+    /// 缓存目录中该仓库（及分支）使用的子目录名
+    ///
+    /// 取仓库地址与分支的 SHA-256，不同仓库不会共用同一个目录。
+    #[cfg(feature = "git")]
+    fn cache_name(&self) -> String {
+        let key = match &self.reference {
+            Some(reference) => format!("{}@{}", self.repo, reference),
+            None => self.repo.clone(),
+        };
+        crate::fetcher::sha256_hex(&key)
+    }
+}
+
+/// 拉取 Git 数据源并读取其中的文件
+///
+/// 所有 git 命令的总耗时不超过 `timeout`，超时后终止 git 并返回错误。
+#[cfg(feature = "git")]
+pub fn fetch_git(
+    url: &str,
+    config: &crate::config::Config,
+    timeout: std::time::Duration,
+) -> Result<String> {
+    use anyhow::Context;
+
+    let deadline = std::time::Instant::now().checked_add(timeout);
+    let source = GitSource::parse(url)?;
+    let dir = crate::state::get_cache_dir(&config.cache_dir)
+        .join("git")
+        .join(source.cache_name());
+    let reference = source.reference.as_deref().unwrap_or("HEAD");
+    let fetch = ["fetch", "--depth", "1", "--end-of-options", "origin", reference];
+
+    if dir.join(".git").exists() {
+        run_git(config, &dir, &fetch, deadline)?;
+        run_git(config, &dir, &["reset", "--hard", "FETCH_HEAD"], deadline)?;
+    } else {
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("创建 Git 缓存目录失败: {:?}", dir))?;
+        let cloned = run_git(config, &dir, &["init", "--quiet"], deadline)
+            .and_then(|_| {
+                run_git(config, &dir, &["remote", "add", "origin", &source.repo], deadline)
+            })
+            .and_then(|_| run_git(config, &dir, &fetch, deadline))
+            .and_then(|_| run_git(config, &dir, &["reset", "--hard", "FETCH_HEAD"], deadline));
+        // 首次克隆失败时删除不完整的仓库，否则之后每次都会按已有仓库处理并失败
+        if let Err(e) = cloned {
+            let _ = std::fs::remove_dir_all(&dir);
+            return Err(e);
+        }
+    }
+
+    // 仓库中的符号链接可能指向仓库之外，解析后须仍位于仓库目录内
+    let root = dir
+        .canonicalize()
+        .with_context(|| format!("读取 Git 缓存目录失败: {:?}", dir))?;
+    let file = root
+        .join(&source.path)
+        .canonicalize()
+        .with_context(|| format!("读取 Git 数据源文件失败: {}", url))?;
+    if !file.starts_with(&root) {
+        return Err(anyhow::anyhow!("Git 数据源文件指向仓库之外: {}", url));
+    }
+    std::fs::read_to_string(&file).with_context(|| format!("读取 Git 数据源文件失败: {}", url))
+}
+
+/// 在仓库目录中执行 git 命令，沿用配置中的 TLS 设置
+///
+/// git 以非交互方式运行：不读取标准输入，需要凭据或确认 SSH 主机密钥时直接失败而不是等待输入，
+/// 以免定时更新一直阻塞。超过 `deadline` 时终止 git。
+#[cfg(feature = "git")]
+fn run_git(
+    config: &crate::config::Config,
+    dir: &std::path::Path,
+    args: &[&str],
+    deadline: Option<std::time::Instant>,
+) -> Result<()> {
+    use anyhow::Context;
+    use std::io::Read;
+    use std::process::Stdio;
+
+    let mut command = std::process::Command::new("git");
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env("GIT_SSH_COMMAND", "ssh -o BatchMode=yes");
+    command.arg("-C").arg(dir);
+    if config.tls_insecure {
+        command.args(["-c", "http.sslVerify=false"]);
+    }
+    if let Some(path) = &config.tls_ca_cert {
+        command.arg("-c").arg(format!("http.sslCAInfo={}", path));
+    }
+    // curl 将 tlsv1.x 视为最低版本
+    if let Some(version) = config.min_tls_version {
+        command.arg("-c").arg(format!("http.sslVersion=tlsv{}", version.as_str()));
+    }
+
+    let mut child = command
+        .args(args)
+        .spawn()
+        .context("执行 git 命令失败，请确认已安装 git")?;
+    // 另起线程读取错误输出，避免输出较多时 git 因管道写满而阻塞
+    let stderr = child.stderr.take();
+    let reader = std::thread::spawn(move || {
+        let mut output = Vec::new();
+        if let Some(mut stderr) = stderr {
+            let _ = stderr.read_to_end(&mut output);
+        }
+        output
+    });
+
+    let status = loop {
+        if let Some(status) = child.try_wait().context("等待 git 命令失败")? {
+            break status;
+        }
+        if deadline.is_some_and(|deadline| std::time::Instant::now() >= deadline) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(anyhow::anyhow!("git {} 超时", args.join(" ")));
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    };
+    if !status.success() {
+        let stderr = reader.join().unwrap_or_default();
+        return Err(anyhow::anyhow!(
+            "git {} 失败: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&stderr).trim()
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_git_source() {
+        let source = GitSource::parse("git+https://example.com/org/lists.git@v1.0#hosts/ads").unwrap();
+        assert_eq!(source.repo, "https://example.com/org/lists.git");
+        assert_eq!(source.reference.as_deref(), Some("v1.0"));
+        assert_eq!(source.path, "hosts/ads");
+
+        let source = GitSource::parse("git+ssh://git@example.com/org/lists.git#hosts").unwrap();
+        assert_eq!(source.repo, "ssh://git@example.com/org/lists.git");
+        assert_eq!(source.reference, None);
+
+        for url in [
+            "git+https://example.com/org/lists.git",
+            "git+https://example.com/org/lists.git#../etc/hosts",
+            "git+https://example.com/org/lists.git@#hosts",
+            "git+file:///repo@--upload-pack=touch /tmp/PWNED;git-upload-pack#hosts",
+            "git+https://example.com/org/lists.git@-b#hosts",
+            "git+https://example.com/org/lists.git@main\tx#hosts",
+            "git+file:///repo@--upload-pack=touch#hosts",
+            "git+ftp://example.com/org/lists.git#hosts",
+        ] {
+            assert!(GitSource::parse(url).is_err(), "{}", url);
+        }
+    }
+
+    #[cfg(feature = "git")]
+    #[test]
+    fn test_fetch_git() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        std::fs::create_dir_all(repo.join("lists")).unwrap();
+        std::fs::write(repo.join("lists/hosts"), "0.0.0.0 a.com\n").unwrap();
+        let git_in = |repo: &std::path::Path, args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .arg("-C")
+                .arg(repo)
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {:?}", args);
+        };
+        let init = |repo: &std::path::Path| {
+            git_in(repo, &["init", "--quiet"]);
+            git_in(repo, &["add", "."]);
+            git_in(repo, &["commit", "--quiet", "-m", "init"]);
+        };
+        let git = |args: &[&str]| git_in(&repo, args);
+        init(&repo);
+
+        let mut config = crate::config::Config::from_sources(Vec::new());
+        config.cache_dir = Some(dir.path().join("cache").to_string_lossy().to_string());
+        let url = format!("git+file://{}#lists/hosts", repo.display());
+        let timeout = std::time::Duration::from_secs(30);
+        assert_eq!(fetch_git(&url, &config, timeout).unwrap(), "0.0.0.0 a.com\n");
+
+        // 再次获取时拉取新的提交
+        std::fs::write(repo.join("lists/hosts"), "0.0.0.0 b.com\n").unwrap();
+        git(&["commit", "--quiet", "-am", "update"]);
+        assert_eq!(fetch_git(&url, &config, timeout).unwrap(), "0.0.0.0 b.com\n");
+
+        // 指向仓库之外的符号链接不会被读取
+        #[cfg(unix)]
+        {
+            let outside = dir.path().join("outside");
+            std::fs::write(&outside, "secret\n").unwrap();
+            std::os::unix::fs::symlink(&outside, repo.join("lists/escape")).unwrap();
+            git(&["add", "."]);
+            git(&["commit", "--quiet", "-m", "escape"]);
+            let escape = format!("git+file://{}#lists/escape", repo.display());
+            assert!(fetch_git(&escape, &config, timeout).is_err());
+        }
+
+        // 地址仅分隔符不同的仓库使用各自的缓存目录
+        let colliding = [("a/b", "0.0.0.0 ab.com\n"), ("a_b", "0.0.0.0 a-b.com\n")];
+        for (name, content) in colliding {
+            let other = dir.path().join(name);
+            std::fs::create_dir_all(&other).unwrap();
+            std::fs::write(other.join("hosts"), content).unwrap();
+            init(&other);
+        }
+        for (name, content) in colliding {
+            let url = format!("git+file://{}#hosts", dir.path().join(name).display());
+            assert_eq!(fetch_git(&url, &config, timeout).unwrap(), content);
+        }
+
+        // 首次克隆失败时不留下不完整的仓库
+        let missing = format!("git+file://{}#hosts", dir.path().join("missing").display());
+        assert!(fetch_git(&missing, &config, timeout).is_err());
+        let name = GitSource::parse(&missing).unwrap().cache_name();
+        assert!(!dir.path().join("cache/git").join(name).exists());
+
+        // 超时后终止 git，不会一直等待
+        let started = std::time::Instant::now();
+        let deadline = started.checked_add(std::time::Duration::from_millis(200));
+        let upload_pack = "--upload-pack=sleep 5; git-upload-pack";
+        let repo_arg = repo.to_string_lossy();
+        let args = ["fetch", upload_pack, &repo_arg];
+        let error = run_git(&config, &repo, &args, deadline).unwrap_err();
+        assert!(error.to_string().contains("超时"), "{}", error);
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    }
+}
@@ -2,15 +2,48 @@
 //!
 //! 提供 hosts 文件的读取、写入、备份和管理功能。
 
+use crate::allowlist::{apply_allowlist, load_allowlist};
+use crate::config::{BlockPosition, Config, Locale, SectionSpacing, TimestampTz};
+use crate::conflict::{dedup_sources, resolve_conflicts};
+use crate::fetcher::SourceResult;
+use crate::i18n::{backups_table_header, locale};
+use crate::paths::default_backup_dir;
+use crate::validation::{
+    LineError, is_valid_ip, validate_hosts_content, validate_hosts_content_collect,
+};
 use anyhow::{Context, Result};
+use chrono::NaiveDateTime;
+use comfy_table::{Table, presets::UTF8_FULL};
+use std::collections::HashMap;
 use std::fs::{self, File};
-use std::io::Write;
-use std::path::PathBuf;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
 
 /// hosts 文件标记常量
 pub const START_MARKER: &str = "# >>> hosts_updater_rs START >>>";
 pub const END_MARKER: &str = "# <<< hosts_updater_rs END <<<";
 
+/// 自动管理区域中数据源标注的前缀
+const SOURCE_LABEL: &str = "# Source: ";
+
+/// 紧凑来源标注的图例前缀（`compact_attribution`），其后为 `s1=url1, s2=url2`
+const SOURCES_LEGEND_LABEL: &str = "# Sources: ";
+
+/// Windows 错误码：文件被其他进程以不允许共享的方式打开
+const ERROR_SHARING_VIOLATION: i32 = 32;
+
+/// Windows 错误码：文件的某一区域被其他进程锁定
+const ERROR_LOCK_VIOLATION: i32 = 33;
+
+/// hosts 文件被占用时的重试间隔（毫秒），依次加倍，合计约 3 秒
+const LOCK_RETRY_DELAYS_MS: [u64; 5] = [100, 200, 400, 800, 1600];
+
+/// 头部中最后更新时间的格式
+pub const LAST_UPDATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// 超过该大小（字节）的 hosts 文件在写入时按行流式处理
+const STREAMING_THRESHOLD: u64 = 8 * 1024 * 1024;
+
 /// 获取系统 hosts 文件路径
 #[cfg(target_os = "windows")]
 pub fn get_hosts_path() -> PathBuf {
@@ -24,21 +57,13 @@ pub fn get_hosts_path() -> PathBuf {
 }
 
 /// 备份 hosts 文件
-pub fn backup_hosts(backup_path: &Option<String>) -> Result<String> {
+///
+/// 备份文件名中的时间按 `timestamp_tz` 指定的时区生成，格式固定，以便按文件名排序与解析。
+pub fn backup_hosts(backup_path: &Option<String>, timestamp_tz: TimestampTz) -> Result<String> {
     let hosts_path = get_hosts_path();
-    let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S").to_string();
+    let timestamp = timestamp_tz.now().format("%Y%m%d_%H%M%S").to_string();
 
-    let backup_file_path = match backup_path {
-        Some(path) => PathBuf::from(path),
-        None => {
-            let mut path = PathBuf::from("./backup");
-            if !path.exists() {
-                fs::create_dir_all(&path)?;
-            }
-            path.push(format!("hosts.backup.{}", timestamp));
-            path
-        }
-    };
+    let backup_file_path = resolve_backup_path(backup_path, &hosts_path, &timestamp)?;
 
     if hosts_path.exists() {
         fs::copy(&hosts_path, &backup_file_path)
@@ -48,6 +73,198 @@ pub fn backup_hosts(backup_path: &Option<String>) -> Result<String> {
     Ok(backup_file_path.to_string_lossy().to_string())
 }
 
+/// 解析并校验备份文件路径
+///
+/// - 未配置时使用默认备份目录（见 [`default_backup_dir`]）下的时间戳文件名
+/// - 配置为目录（已存在的目录或以路径分隔符结尾）时，在其中生成时间戳文件名
+/// - 备份目标不能是 hosts 文件本身，也不能是 hosts 文件所在目录
+/// - 备份目标为已存在的文件时，仅当其内容看起来是 hosts 备份时才允许覆盖
+fn resolve_backup_path(
+    backup_path: &Option<String>,
+    hosts_path: &Path,
+    timestamp: &str,
+) -> Result<PathBuf> {
+    let file_name = format!("hosts.backup.{}", timestamp);
+
+    let path = match backup_path {
+        Some(path) => PathBuf::from(path),
+        None => default_backup_dir().join(&file_name),
+    };
+    let is_dir_path = path.is_dir()
+        || backup_path
+            .as_deref()
+            .is_some_and(|p| p.ends_with('/') || p.ends_with('\\'));
+
+    let path = if is_dir_path {
+        if let Some(hosts_dir) = hosts_path.parent()
+            && same_path(&path, hosts_dir)
+        {
+            return Err(anyhow::anyhow!(
+                "备份目录不能是 hosts 文件所在目录: {:?}",
+                path
+            ));
+        }
+        path.join(&file_name)
+    } else {
+        path
+    };
+
+    if same_path(&path, hosts_path) {
+        return Err(anyhow::anyhow!(
+            "备份路径不能是 hosts 文件本身: {:?}",
+            path
+        ));
+    }
+
+    if path.is_file() {
+        let content = fs::read(&path).with_context(|| format!("读取备份目标失败: {:?}", path))?;
+        if !looks_like_hosts_content(&String::from_utf8_lossy(&content)) {
+            return Err(anyhow::anyhow!(
+                "备份目标已存在且不是 hosts 备份文件，拒绝覆盖: {:?}（请将 backup_path 设置为目录或新文件）",
+                path
+            ));
+        }
+    }
+
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+        && !parent.exists()
+    {
+        fs::create_dir_all(parent).with_context(|| format!("创建备份目录失败: {:?}", parent))?;
+    }
+
+    Ok(path)
+}
+
+/// 列出备份目录中带时间戳的备份文件，按时间从旧到新排序
+///
+/// `backup_path` 配置为单个文件时每次备份都会覆盖该文件，不存在可列出的备份，返回空列表。
+pub fn list_backups(backup_path: &Option<String>) -> Result<Vec<PathBuf>> {
+    let dir = match backup_path {
+        None => default_backup_dir(),
+        Some(path) if Path::new(path).is_dir() || path.ends_with('/') || path.ends_with('\\') => {
+            PathBuf::from(path)
+        }
+        Some(_) => return Ok(Vec::new()),
+    };
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(&dir).with_context(|| format!("读取备份目录失败: {:?}", dir))? {
+        let path = entry.with_context(|| format!("读取备份目录失败: {:?}", dir))?.path();
+        let is_backup = path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with("hosts.backup."));
+        if is_backup && path.is_file() {
+            backups.push(path);
+        }
+    }
+    // 文件名中的时间戳格式为 %Y%m%d_%H%M%S，按名称排序即按时间排序
+    backups.sort();
+
+    Ok(backups)
+}
+
+/// 备份文件信息
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackupInfo {
+    /// 备份文件路径
+    pub path: PathBuf,
+    /// 从文件名解析的备份时间
+    pub timestamp: Option<NaiveDateTime>,
+    /// 文件大小（字节）
+    pub size: u64,
+    /// 是否为 gzip 压缩文件
+    pub gzip: bool,
+}
+
+/// 读取备份文件的信息，时间从 `hosts.backup.%Y%m%d_%H%M%S[.gz]` 形式的文件名中解析
+pub fn backup_info(path: &Path) -> Result<BackupInfo> {
+    let size = fs::metadata(path)
+        .with_context(|| format!("读取备份文件信息失败: {:?}", path))?
+        .len();
+
+    let mut magic = [0u8; 2];
+    let gzip = File::open(path)
+        .and_then(|mut file| io::Read::read_exact(&mut file, &mut magic))
+        .is_ok()
+        && magic == [0x1f, 0x8b];
+
+    let timestamp = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .and_then(|name| {
+            let stamp = name.strip_prefix("hosts.backup.")?;
+            let stamp = stamp.strip_suffix(".gz").unwrap_or(stamp);
+            NaiveDateTime::parse_from_str(stamp, "%Y%m%d_%H%M%S").ok()
+        });
+
+    Ok(BackupInfo {
+        path: path.to_path_buf(),
+        timestamp,
+        size,
+        gzip,
+    })
+}
+
+/// 将备份信息渲染为对齐的文本表格
+pub fn render_backups_table(backups: &[BackupInfo]) -> String {
+    let en = locale() == Locale::En;
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    table.set_header(backups_table_header(locale()));
+
+    for backup in backups {
+        table.add_row(vec![
+            backup.path.to_string_lossy().to_string(),
+            backup.timestamp.map_or_else(
+                || "-".to_string(),
+                |time| time.format(LAST_UPDATE_FORMAT).to_string(),
+            ),
+            backup.size.to_string(),
+            match (backup.gzip, en) {
+                (true, true) => "yes",
+                (false, true) => "no",
+                (true, false) => "是",
+                (false, false) => "否",
+            }
+            .to_string(),
+        ]);
+    }
+
+    table.to_string()
+}
+
+/// 判断两个路径是否指向同一位置（尽量解析为规范路径后比较）
+fn same_path(a: &Path, b: &Path) -> bool {
+    canonicalize_lenient(a) == canonicalize_lenient(b)
+}
+
+/// 解析规范路径，文件不存在时解析其父目录后拼接文件名
+fn canonicalize_lenient(path: &Path) -> PathBuf {
+    if let Ok(path) = fs::canonicalize(path) {
+        return path;
+    }
+
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => canonicalize_lenient(parent).join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// 判断内容是否看起来是 hosts 文件
+///
+/// 所有非空、非注释行均以合法 IP 开头时视为 hosts 内容（空内容同样视为 hosts 内容）。
+fn looks_like_hosts_content(content: &str) -> bool {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .all(|line| line.split_whitespace().next().is_some_and(is_valid_ip))
+}
+
 /// 读取 hosts 文件内容
 pub fn read_hosts_content() -> Result<String> {
     let hosts_path = get_hosts_path();
@@ -60,6 +277,142 @@ pub fn read_hosts_content() -> Result<String> {
         .with_context(|| format!("读取 hosts 文件失败: {:?}", hosts_path))
 }
 
+/// 自动管理区域所在的文件：配置了 `output_file` 时为该文件，否则为系统 hosts 文件
+pub fn managed_hosts_path(config: &Config) -> PathBuf {
+    match &config.output_file {
+        Some(path) => PathBuf::from(path),
+        None => get_hosts_path(),
+    }
+}
+
+/// 读取自动管理区域所在文件的内容，文件尚未生成时返回空字符串
+pub fn read_managed_content(config: &Config) -> Result<String> {
+    if config.output_file.is_none() {
+        return read_hosts_content();
+    }
+
+    let path = managed_hosts_path(config);
+    if !path.exists() {
+        return Ok(String::new());
+    }
+    fs::read_to_string(&path).with_context(|| format!("读取 hosts 文件失败: {:?}", path))
+}
+
+/// 写入后检查时抽查解析的条目数量
+const POST_CHECK_SAMPLES: usize = 3;
+
+/// 写入后检查系统 hosts 文件，用于事务更新（`transactional`）
+///
+/// 重新读取并解析自动管理区域，条目数量须与写入的 `entries` 一致；
+/// 再通过系统解析器抽查前几个域名，全部未能解析到 hosts 中的地址时视为检查失败。
+pub fn post_check_hosts(entries: &str, config: &Config) -> Result<()> {
+    let section = parse_managed_section(&read_hosts_content()?, &config.timestamp_format);
+    let expected = count_entries(entries);
+    if section.entry_count() != expected {
+        return Err(anyhow::anyhow!(
+            "自动管理区域条目数量与写入的不一致: 期望 {} 条，实际 {} 条",
+            expected,
+            section.entry_count()
+        ));
+    }
+
+    let samples = sample_entries(&section, POST_CHECK_SAMPLES);
+    if !samples.is_empty() && !samples.iter().any(|(ip, domain)| resolves_to(domain, *ip)) {
+        let domains: Vec<&str> = samples.iter().map(|(_, domain)| *domain).collect();
+        return Err(anyhow::anyhow!(
+            "抽查的域名均未能通过系统解析器解析到 hosts 中的地址: {}",
+            domains.join(", ")
+        ));
+    }
+
+    Ok(())
+}
+
+/// 取自动管理区域中前 `count` 条条目的 IP 与第一个域名
+fn sample_entries(section: &ManagedSection, count: usize) -> Vec<(std::net::IpAddr, &str)> {
+    section
+        .sources
+        .iter()
+        .flat_map(|source| &source.entries)
+        .filter_map(|entry| {
+            let mut parts = entry.split_whitespace();
+            let ip = parts.next()?.trim_matches(['[', ']']).parse().ok()?;
+            Some((ip, parts.next()?))
+        })
+        .take(count)
+        .collect()
+}
+
+/// 域名能否通过系统解析器解析到指定 IP
+fn resolves_to(domain: &str, ip: std::net::IpAddr) -> bool {
+    use std::net::ToSocketAddrs;
+
+    (domain, 0)
+        .to_socket_addrs()
+        .is_ok_and(|mut addrs| addrs.any(|addr| addr.ip() == ip))
+}
+
+/// 用写入前的内容恢复系统 hosts 文件
+pub fn restore_hosts(original: &[u8]) -> Result<()> {
+    let hosts_path = get_hosts_path();
+    retry_while_locked(&hosts_path, || fs::write(&hosts_path, original))
+        .with_context(|| format!("恢复 hosts 文件失败: {:?}", hosts_path))
+}
+
+/// 是否为 Windows 上文件被其他进程占用导致的错误
+///
+/// 其他平台上相同的错误码含义不同（如 Linux 的 32 为 `EPIPE`），始终返回 `false`。
+fn is_sharing_violation(error: &io::Error) -> bool {
+    cfg!(windows)
+        && matches!(
+            error.raw_os_error(),
+            Some(ERROR_SHARING_VIOLATION | ERROR_LOCK_VIOLATION)
+        )
+}
+
+/// 执行 hosts 文件操作，文件被其他进程占用时按 `LOCK_RETRY_DELAYS_MS` 退避重试
+///
+/// Windows 上杀毒软件或 DNS Client 服务读取 hosts 文件时可能短暂持有独占句柄，
+/// 打开文件会间歇性地失败；重试耗尽后返回说明占用原因的错误。
+fn retry_while_locked<T>(path: &Path, mut operation: impl FnMut() -> io::Result<T>) -> Result<T> {
+    let mut delays = LOCK_RETRY_DELAYS_MS.iter();
+    loop {
+        match operation() {
+            Err(e) if is_sharing_violation(&e) => {
+                let Some(&delay) = delays.next() else {
+                    return Err(anyhow::Error::new(e).context(format!(
+                        "hosts 文件被其他进程占用: {:?}（可能是杀毒软件或 DNS Client 服务），请稍后重试；\
+                         持续出现时可将 hosts 文件加入杀毒软件的排除列表，或参阅 README 中关于 DNS Client 服务的说明",
+                        path
+                    )));
+                };
+                tracing::warn!("hosts 文件被其他进程占用，{} 毫秒后重试: {:?}", delay, path);
+                std::thread::sleep(std::time::Duration::from_millis(delay));
+            }
+            result => return Ok(result?),
+        }
+    }
+}
+
+/// 检查 hosts 内容中的格式问题，返回所有出错的行
+///
+/// 手动编辑的 hosts 文件常带有行尾注释，验证前先去除；错误中保留该行的原始内容。
+pub fn lint_hosts_content(content: &str) -> Vec<LineError> {
+    let entries: String = content
+        .lines()
+        .map(|line| format!("{}\n", line.split('#').next().unwrap_or_default()))
+        .collect();
+    let lines: Vec<&str> = content.lines().collect();
+
+    validate_hosts_content_collect(&entries)
+        .into_iter()
+        .map(|error| LineError {
+            content: lines[error.line_num - 1].to_string(),
+            ..error
+        })
+        .collect()
+}
+
 /// 检查是否以管理员权限运行
 pub fn check_admin_permission() -> bool {
     #[cfg(target_os = "windows")]
@@ -76,21 +429,21 @@ pub fn check_admin_permission() -> bool {
     #[cfg(target_os = "linux")]
     {
         std::fs::metadata("/etc/hosts")
-            .and_then(|m| Ok(m.permissions().readonly()))
+            .map(|m| m.permissions().readonly())
             .is_err()
     }
 
     #[cfg(target_os = "macos")]
     {
         std::fs::metadata("/etc/hosts")
-            .and_then(|m| Ok(m.permissions().readonly()))
+            .map(|m| m.permissions().readonly())
             .is_err()
     }
 
     #[cfg(target_os = "freebsd")]
     {
         std::fs::metadata("/etc/hosts")
-            .and_then(|m| Ok(m.permissions().readonly()))
+            .map(|m| m.permissions().readonly())
             .is_err()
     }
 
@@ -122,89 +475,2032 @@ pub fn check_admin_permission() -> bool {
 ///
 /// # <<< hosts_updater_rs END <<<
 /// ```
-pub fn write_hosts(sources: &[(String, String)], last_update: &str) -> Result<()> {
+///
+/// 返回写入的数据源条目区块（不含标记与头部注释）。
+/// 超过 `STREAMING_THRESHOLD` 的 hosts 文件按行流式处理，内存占用与文件大小无关。
+pub fn write_hosts(sources: &[SourceResult], last_update: &str, config: &Config) -> Result<String> {
     let hosts_path = get_hosts_path();
 
-    // 读取现有内容
-    let existing_content = read_hosts_content()?;
+    // 检查符号链接
+    check_symlink(&hosts_path, config.follow_symlinks)?;
 
-    // 移除旧的自动管理区域
-    let cleaned_content = remove_auto_managed_section(&existing_content);
+    // 检查 hosts 所在目录是否位于只读文件系统
+    check_read_only_dir(&hosts_path)?;
 
-    // 构建新的自动管理区域
-    let auto_section = build_auto_section(sources, last_update);
+    write_hosts_to(&hosts_path, sources, last_update, config)
+}
 
-    // 组合内容
-    let new_content = if cleaned_content.trim().is_empty() {
-        auto_section
-    } else {
-        format!("{}\n\n{}", cleaned_content.trim_end(), auto_section)
-    };
+/// 以系统 hosts 文件的内容为基础生成更新后的 hosts 文件，写入 `target`
+///
+/// `target` 不是系统 hosts 文件时不会修改系统 hosts 文件，可用于生成供审阅或比较的文件。
+/// 写入后回读 `target`，自动管理区域与预期内容不一致时返回错误。
+/// 返回值与 [`write_hosts`] 相同。
+pub fn write_hosts_to(
+    target: &Path,
+    sources: &[SourceResult],
+    last_update: &str,
+    config: &Config,
+) -> Result<String> {
+    let hosts_path = get_hosts_path();
+
+    if config.append_only {
+        let file = File::open(&hosts_path)
+            .with_context(|| format!("读取 hosts 文件失败: {:?}", hosts_path))?;
+        check_markers(BufReader::new(file))?;
+    }
+
+    // 配置了锚点时需要先定位锚点所在行，始终在内存中组合
+    let size = fs::metadata(&hosts_path).map(|m| m.len()).unwrap_or(0);
+    if size > STREAMING_THRESHOLD && BlockAnchor::from_config(config).is_none() {
+        return write_hosts_streaming(&hosts_path, target, sources, last_update, config);
+    }
+
+    // 生成并自检新内容
+    let (auto_section, entries) = render_auto_section(sources, last_update, config)?;
+    let new_content = combine_hosts(
+        &read_hosts_content()?,
+        auto_section.clone(),
+        config.block_position,
+        BlockAnchor::from_config(config),
+    );
 
     // 写入文件
-    let mut file = File::create(&hosts_path)
-        .with_context(|| format!("创建 hosts 文件失败: {:?}", hosts_path))?;
+    let mut file = retry_while_locked(target, || File::create(target))
+        .with_context(|| format!("创建 hosts 文件失败: {:?}", target))?;
 
     file.write_all(new_content.as_bytes())
-        .with_context(|| format!("写入 hosts 文件失败: {:?}", hosts_path))?;
+        .with_context(|| format!("写入 hosts 文件失败: {:?}", target))?;
+    drop(file);
+
+    verify_written(target, &auto_section)?;
+
+    Ok(entries)
+}
+
+/// 将自动管理区域单独写入 `output_file` 指定的 include 文件
+///
+/// 该文件只包含自动管理区域，整体覆盖写入，不读取也不修改系统 hosts 文件；
+/// 保留首尾标记与头部，`status`、过期检查与变更日志照常从该文件读取。
+/// 返回值与 [`write_hosts`] 相同。
+pub fn write_include_file(
+    target: &Path,
+    sources: &[SourceResult],
+    last_update: &str,
+    config: &Config,
+) -> Result<String> {
+    let (auto_section, entries) = render_auto_section(sources, last_update, config)?;
+
+    if let Some(dir) = target.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).with_context(|| format!("创建目录失败: {:?}", dir))?;
+    }
+    let mut file = retry_while_locked(target, || File::create(target))
+        .with_context(|| format!("创建 include 文件失败: {:?}", target))?;
+    file.write_all(auto_section.as_bytes())
+        .with_context(|| format!("写入 include 文件失败: {:?}", target))?;
+    drop(file);
+
+    verify_written(target, &auto_section)?;
+
+    Ok(entries)
+}
+
+/// 以流式方式写入 hosts 文件
+///
+/// 先将移除旧区域后的用户内容与新区域写入目标文件同目录下的临时文件，
+/// 再复制到目标文件（目标为原文件时保留其 inode 与权限），最后删除临时文件。
+fn write_hosts_streaming(
+    hosts_path: &Path,
+    target: &Path,
+    sources: &[SourceResult],
+    last_update: &str,
+    config: &Config,
+) -> Result<String> {
+    let (auto_section, entries) = render_auto_section(sources, last_update, config)?;
+
+    // 第一遍只查找开始标记之上的注释，使其与新区域保持相邻
+    let note = find_marker_note(
+        BufReader::new(
+            File::open(hosts_path)
+                .with_context(|| format!("读取 hosts 文件失败: {:?}", hosts_path))?,
+        )
+        .lines()
+        .map_while(Result::ok),
+    );
+    let skip = note.as_ref().map_or(0..0, MarkerNote::range);
+    let section = match &note {
+        Some(note) => note.attach(auto_section.clone()),
+        None => auto_section.clone(),
+    };
+
+    let file_name = target
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "hosts".to_string());
+    let temp_path = target.with_file_name(format!("{}.hosts_updater.tmp", file_name));
+
+    let result = (|| -> Result<()> {
+        let reader = BufReader::new(
+            File::open(hosts_path)
+                .with_context(|| format!("读取 hosts 文件失败: {:?}", hosts_path))?,
+        );
+        let mut writer = BufWriter::new(
+            File::create(&temp_path)
+                .with_context(|| format!("创建临时文件失败: {:?}", temp_path))?,
+        );
+
+        match config.block_position {
+            BlockPosition::Top => {
+                writer.write_all(section.as_bytes())?;
+                if remove_auto_managed_section_streaming(reader, &mut writer, Some(b"\n"), skip)? {
+                    writer.write_all(b"\n")?;
+                }
+            }
+            BlockPosition::Bottom => {
+                if remove_auto_managed_section_streaming(reader, &mut writer, None, skip)? {
+                    writer.write_all(b"\n\n")?;
+                }
+                writer.write_all(section.as_bytes())?;
+            }
+        }
+        writer
+            .flush()
+            .with_context(|| format!("写入临时文件失败: {:?}", temp_path))?;
+        drop(writer);
+
+        let mut temp = File::open(&temp_path)
+            .with_context(|| format!("读取临时文件失败: {:?}", temp_path))?;
+        let mut file = retry_while_locked(target, || File::create(target))
+            .with_context(|| format!("创建 hosts 文件失败: {:?}", target))?;
+        io::copy(&mut temp, &mut file)
+            .with_context(|| format!("写入 hosts 文件失败: {:?}", target))?;
+        Ok(())
+    })();
+
+    let _ = fs::remove_file(&temp_path);
+    result?;
+
+    verify_written(target, &auto_section)?;
+
+    Ok(entries)
+}
+
+/// 重新读取写入的文件，确认其中自动管理区域的 SHA-256 与预期一致
+///
+/// 用于发现写入不完整或文件系统异常导致的内容损坏，不一致时返回错误而不是静默继续。
+fn verify_written(target: &Path, auto_section: &str) -> Result<()> {
+    let expected = auto_section_digest(auto_section.as_bytes())?;
+    let file = File::open(target).with_context(|| format!("回读 hosts 文件失败: {:?}", target))?;
+    let actual = auto_section_digest(BufReader::new(file))
+        .with_context(|| format!("回读 hosts 文件失败: {:?}", target))?;
+
+    if actual != expected {
+        return Err(anyhow::anyhow!(
+            "写入后校验失败，hosts 文件中的自动管理区域与预期内容不一致: {:?}",
+            target
+        ));
+    }
 
     Ok(())
 }
 
-/// 移除自动管理区域
-fn remove_auto_managed_section(content: &str) -> String {
-    let mut result = String::new();
-    let mut in_auto_section = false;
-    let mut found_start = false;
+/// 计算自动管理区域（不含首尾标记）逐行内容的 SHA-256，没有完整的区域时返回 `None`
+fn auto_section_digest(reader: impl BufRead) -> Result<Option<Vec<u8>>> {
+    let mut context: Option<ring::digest::Context> = None;
 
-    for line in content.lines() {
-        if line.trim() == START_MARKER {
-            in_auto_section = true;
-            found_start = true;
-            continue;
+    for line in reader.lines() {
+        let line = line?;
+        let trimmed = line.trim();
+        if is_start_marker(trimmed) {
+            context = Some(ring::digest::Context::new(&ring::digest::SHA256));
+        } else if is_end_marker(trimmed) {
+            return Ok(context.map(|context| context.finish().as_ref().to_vec()));
+        } else if let Some(context) = &mut context {
+            context.update(line.as_bytes());
+            context.update(b"\n");
         }
+    }
 
-        if line.trim() == END_MARKER {
-            in_auto_section = false;
-            continue;
+    Ok(None)
+}
+
+/// 生成更新后的完整 hosts 内容（不写入文件）
+///
+/// 新的自动管理区域会先经过自检，自检失败时返回错误，避免写入格式错误的内容。
+/// 配置了 `output_file` 时只返回自动管理区域，即 include 文件的完整内容。
+pub fn render_hosts(sources: &[SourceResult], last_update: &str, config: &Config) -> Result<String> {
+    let (auto_section, _) = render_auto_section(sources, last_update, config)?;
+    if config.output_file.is_some() {
+        return Ok(auto_section);
+    }
+    let existing_content = read_hosts_content()?;
+    if config.append_only {
+        check_markers(existing_content.as_bytes())?;
+    }
+    Ok(combine_hosts(
+        &existing_content,
+        auto_section,
+        config.block_position,
+        BlockAnchor::from_config(config),
+    ))
+}
+
+/// 生成并自检新的自动管理区域，返回完整区域与其中的数据源条目区块
+fn render_auto_section(
+    sources: &[SourceResult],
+    last_update: &str,
+    config: &Config,
+) -> Result<(String, String)> {
+    // 处理不同数据源之间的域名 IP 冲突，再移除重复条目
+    let sources = dedup_sources(&resolve_conflicts(sources, config.conflict_policy)?);
+    let sources = filter_allowlist(sources, config)?;
+
+    // 构建新的自动管理区域
+    let entries = build_source_entries(&sources, config);
+    let header = if config.minimize {
+        String::new()
+    } else {
+        let source_count = sources.iter().filter(|s| s.entry_count() > 0).count();
+        render_header(config, last_update, source_count)
+    };
+    let footer = render_footer(config);
+    let auto_section = build_auto_section(&header, &entries, &footer, config.section_spacing);
+
+    // 自检：重新解析自动管理区域中的每一行
+    verify_auto_section(&auto_section).context("自动管理区域自检失败，已取消写入")?;
+
+    Ok((auto_section, entries))
+}
+
+/// 配置了 `allowlist_file` 时，从条目中移除白名单中的域名
+pub(crate) fn filter_allowlist(
+    sources: Vec<SourceResult>,
+    config: &Config,
+) -> Result<Vec<SourceResult>> {
+    let Some(path) = &config.allowlist_file else {
+        return Ok(sources);
+    };
+
+    let patterns = load_allowlist(path)?;
+    let (sources, removed) = apply_allowlist(&sources, &patterns);
+    tracing::info!("白名单移除 {} 个域名（{} 条规则）: {}", removed, patterns.len(), path);
+    Ok(sources)
+}
+
+/// 管理区域相对其他工具标记的锚点
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BlockAnchor<'a> {
+    /// 放在锚点行之后
+    After(&'a str),
+    /// 放在锚点行之前
+    Before(&'a str),
+}
+
+impl<'a> BlockAnchor<'a> {
+    /// 读取 `anchor_after` / `anchor_before` 配置
+    fn from_config(config: &'a Config) -> Option<Self> {
+        config
+            .anchor_after
+            .as_deref()
+            .map(BlockAnchor::After)
+            .or(config.anchor_before.as_deref().map(BlockAnchor::Before))
+    }
+
+    /// 在内容中查找第一个与锚点相同（忽略首尾空白）的行，返回管理区域应插入的行号
+    fn insert_at(&self, lines: &[&str]) -> Option<usize> {
+        let (anchor, offset) = match self {
+            BlockAnchor::After(anchor) => (anchor, 1),
+            BlockAnchor::Before(anchor) => (anchor, 0),
+        };
+        lines
+            .iter()
+            .position(|line| line.trim() == anchor.trim())
+            .map(|i| i + offset)
+    }
+}
+
+/// 移除现有内容中的旧自动管理区域，并按 `anchor` 或 `position` 与新区域组合
+///
+/// 用户内容去除末尾空白（包括缺失或多余的换行）后，与新区域之间恰好保留一个空行；
+/// 区域置顶时同样去除用户内容开头的空行。紧挨在开始标记之上的用户注释随新区域一起放置，
+/// 原样保留且与开始标记之间不插入空行。配置了锚点但内容中没有锚点行时按 `position` 放置。
+fn combine_hosts(
+    existing_content: &str,
+    auto_section: String,
+    position: BlockPosition,
+    anchor: Option<BlockAnchor<'_>>,
+) -> String {
+    let (existing_content, auto_section) = match find_marker_note(existing_content.lines()) {
+        Some(note) => {
+            let without_note = existing_content
+                .lines()
+                .enumerate()
+                .filter(|(i, _)| !note.range().contains(i))
+                .map(|(_, line)| format!("{}\n", line))
+                .collect();
+            (without_note, note.attach(auto_section))
         }
+        None => (existing_content.to_string(), auto_section),
+    };
+    let cleaned_content = remove_auto_managed_section(&existing_content);
 
-        if !in_auto_section {
-            result.push_str(line);
-            result.push('\n');
+    if cleaned_content.trim().is_empty() {
+        return auto_section;
+    }
+
+    if let Some(anchor) = anchor {
+        let lines: Vec<&str> = cleaned_content.lines().collect();
+        match anchor.insert_at(&lines) {
+            Some(at) => {
+                let head = lines[..at].join("\n");
+                let tail = lines[at..].join("\n");
+                return place_section(&head, auto_section, &tail);
+            }
+            None => tracing::warn!(
+                "hosts 文件中未找到锚点 {:?}，按 block_position 放置管理区域",
+                anchor
+            ),
         }
     }
 
-    // 如果没有找到标记，返回原内容
-    if !found_start {
-        content.to_string()
-    } else {
-        result.trim_end().to_string()
+    match position {
+        BlockPosition::Top => place_section("", auto_section, &cleaned_content),
+        BlockPosition::Bottom => place_section(&cleaned_content, auto_section, ""),
     }
 }
 
-/// 构建自动管理区域
-fn build_auto_section(sources: &[(String, String)], last_update: &str) -> String {
-    let mut section = String::new();
+/// 将新区域放在 `head` 与 `tail` 之间，与两侧的非空内容各保留一个空行
+fn place_section(head: &str, auto_section: String, tail: &str) -> String {
+    let head = head.trim_end();
+    let tail = trim_leading_blank_lines(tail.trim_end());
 
-    section.push_str(START_MARKER);
-    section.push('\n');
-    section.push_str("# 此区域由 hosts_updater_rs 自动管理，请勿手动修改");
-    section.push('\n');
-    section.push_str("# 最后更新: ");
-    section.push_str(last_update);
-    section.push_str("\n\n");
+    let mut content = String::new();
+    if !head.is_empty() {
+        content.push_str(head);
+        content.push_str("\n\n");
+    }
+    content.push_str(&auto_section);
+    if !tail.is_empty() {
+        content.push('\n');
+        content.push_str(tail);
+        content.push('\n');
+    }
+    content
+}
 
-    for (url, content) in sources {
-        section.push_str("# Source: ");
-        section.push_str(url);
-        section.push('\n');
-        section.push_str(content.trim());
-        section.push_str("\n\n");
+/// 紧挨在第一个开始标记之上的连续注释行（如“以下内容由 hosts_updater 管理”）
+///
+/// 这类注释属于用户对区域的标注，重新写入区域时需要与开始标记保持相邻。
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct MarkerNote {
+    /// 第一行注释的行号（从 0 开始）
+    start: usize,
+    /// 注释行（去除行尾空白）
+    lines: Vec<String>,
+}
+
+impl MarkerNote {
+    /// 注释所在的行号范围
+    fn range(&self) -> std::ops::Range<usize> {
+        self.start..self.start + self.lines.len()
     }
 
-    section.push_str(END_MARKER);
-    section.push('\n');
+    /// 将注释放在新区域之前
+    fn attach(&self, auto_section: String) -> String {
+        format!("{}\n{}", self.lines.join("\n"), auto_section)
+    }
+}
 
-    section
+/// 查找紧挨在第一个开始标记之上的注释，注释与标记之间有空行或其他内容时不视为标注
+fn find_marker_note<S: AsRef<str>>(lines: impl Iterator<Item = S>) -> Option<MarkerNote> {
+    let mut note = MarkerNote { start: 0, lines: Vec::new() };
+
+    for (i, line) in lines.enumerate() {
+        let line = line.as_ref();
+        let trimmed = line.trim();
+        if trimmed.ends_with(START_MARKER) {
+            return (trimmed == START_MARKER && !note.lines.is_empty()).then_some(note);
+        }
+        if trimmed.starts_with('#') && trimmed != END_MARKER {
+            if note.lines.is_empty() {
+                note.start = i;
+            }
+            note.lines.push(line.trim_end().to_string());
+        } else {
+            note.lines.clear();
+        }
+    }
+
+    None
+}
+
+/// 去除开头仅含空白的行，保留第一行非空内容的缩进
+fn trim_leading_blank_lines(content: &str) -> &str {
+    let Some(first) = content.find(|c: char| !c.is_whitespace()) else {
+        return "";
+    };
+    let start = content[..first].rfind('\n').map_or(0, |i| i + 1);
+    &content[start..]
+}
+
+/// 自检自动管理区域
+///
+/// 去掉首尾标记后用 `validate_hosts_content` 重新验证区域内容，
+/// 用于发现生成过程（预处理、规范化等）中产生的格式错误行。
+fn verify_auto_section(section: &str) -> Result<()> {
+    let body: String = section
+        .lines()
+        .filter(|line| {
+            let line = line.trim();
+            line != START_MARKER && line != END_MARKER
+        })
+        .map(|line| format!("{}\n", line))
+        .collect();
+
+    validate_hosts_content(&body, "自动管理区域")
+}
+
+/// 在 hosts 文件所在目录创建并删除探测文件，目录位于只读文件系统时返回明确的错误
+///
+/// 仅识别只读文件系统；权限不足等其他错误交由后续写入报告。
+fn check_read_only_dir(hosts_path: &Path) -> Result<()> {
+    let Some(dir) = hosts_path.parent() else {
+        return Ok(());
+    };
+    let probe = dir.join(".hosts_updater.probe");
+
+    match fs::OpenOptions::new().write(true).create_new(true).open(&probe) {
+        Ok(_) => {
+            let _ = fs::remove_file(&probe);
+            Ok(())
+        }
+        Err(e) => read_only_error(dir, &e).map_or(Ok(()), Err),
+    }
+}
+
+/// 目录位于只读文件系统时生成带处理建议的错误
+fn read_only_error(dir: &Path, error: &io::Error) -> Option<anyhow::Error> {
+    (error.kind() == io::ErrorKind::ReadOnlyFilesystem).then(|| {
+        anyhow::anyhow!(
+            "hosts 目录为只读，无法写入: {:?}。请确认该文件系统是否以只读方式挂载（如不可变系统），\
+             按系统的方式重新挂载为可写或修改 hosts 文件；也可以使用 `run --output <路径>` \
+             将更新后的 hosts 文件写入其他位置",
+            dir
+        )
+    })
+}
+
+/// 检查 hosts 文件是否为符号链接
+///
+/// 默认拒绝通过符号链接写入，避免写入到意料之外的目标文件；
+/// 设置 `follow_symlinks` 后仅记录链接目标并允许写入。
+fn check_symlink(path: &Path, follow_symlinks: bool) -> Result<()> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return Ok(()),
+    };
+
+    if !metadata.file_type().is_symlink() {
+        return Ok(());
+    }
+
+    let target = fs::canonicalize(path)
+        .or_else(|_| fs::read_link(path))
+        .with_context(|| format!("解析符号链接失败: {:?}", path))?;
+    tracing::warn!("hosts 文件为符号链接: {:?} -> {:?}", path, target);
+
+    if !follow_symlinks {
+        return Err(anyhow::anyhow!(
+            "hosts 文件为符号链接，拒绝写入: {:?} -> {:?}（如确认安全可设置 follow_symlinks: true）",
+            path,
+            target
+        ));
+    }
+
+    Ok(())
+}
+
+/// 统计 hosts 内容中的条目数量（忽略空行和注释行）
+pub fn count_entries(content: &str) -> usize {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .count()
+}
+
+/// 读取系统 hosts 文件中自动管理区域的最后更新时间
+///
+/// 时间戳按 `format`（即 `timestamp_format`）解析，为写入时所用时区的时间。
+/// 文件中没有自动管理区域或头部中没有时间戳时返回 `None`。
+pub fn read_hosts_last_update(format: &str) -> Result<Option<NaiveDateTime>> {
+    read_last_update(&get_hosts_path(), format)
+}
+
+/// 读取指定 hosts 文件（如 `--output` 生成的快照）中自动管理区域的最后更新时间
+pub fn read_last_update(path: &Path, format: &str) -> Result<Option<NaiveDateTime>> {
+    let file = File::open(path)
+        .with_context(|| format!("读取 hosts 文件失败: {}", path.display()))?;
+    Ok(parse_last_update(BufReader::new(file), format))
+}
+
+/// 从自动管理区域的头部注释中解析最后更新时间
+///
+/// 不依赖头部模板的具体文本，取头部注释中第一个符合 `format` 的时间戳；
+/// 遇到第一条条目即停止，无需读取整个区域。
+fn parse_last_update(reader: impl BufRead, format: &str) -> Option<NaiveDateTime> {
+    let mut in_auto_section = false;
+
+    for line in reader.lines() {
+        let line = line.ok()?;
+        let trimmed = line.trim();
+
+        if is_start_marker(trimmed) {
+            in_auto_section = true;
+            continue;
+        }
+        if !in_auto_section || trimmed.is_empty() {
+            continue;
+        }
+        if is_end_marker(trimmed) || !trimmed.starts_with('#') {
+            return None;
+        }
+        if let Some(time) = find_timestamp(trimmed, format) {
+            return Some(time);
+        }
+    }
+
+    None
+}
+
+/// 在文本中查找第一个符合 `format` 的时间戳
+///
+/// 格式不包含完整的日期与时间（如仅有日期）时无法解析，返回 `None`。
+fn find_timestamp(text: &str, format: &str) -> Option<NaiveDateTime> {
+    text.char_indices()
+        .find_map(|(i, _)| NaiveDateTime::parse_and_remainder(&text[i..], format).ok())
+        .map(|(time, _)| time)
+}
+
+/// 其他工具的区域标记
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ForeignMarker {
+    Start,
+    End,
+}
+
+/// 判断注释行是否像其他工具的区域标记
+///
+/// 去掉开头的 `#`、`-`、`=` 与空白后，首个单词为 `BEGIN` 或 `START`、或以 `>>>` 开头视为开始标记，
+/// 首个单词为 `END`、或以 `<<<` 开头视为结束标记（不区分大小写）。
+/// 只比较完整的单词，`# Startup servers`、`# Beginning of ...` 等普通注释不计入。本工具自身的标记不计入。
+fn foreign_marker(line: &str) -> Option<ForeignMarker> {
+    if !line.starts_with('#') || line == START_MARKER || line == END_MARKER {
+        return None;
+    }
+
+    let text = line
+        .trim_start_matches(|c: char| c == '#' || c == '-' || c == '=' || c.is_whitespace());
+    let word = text
+        .split(|c: char| !c.is_alphanumeric())
+        .next()
+        .unwrap_or_default();
+    if word.eq_ignore_ascii_case("BEGIN")
+        || word.eq_ignore_ascii_case("START")
+        || text.starts_with(">>>")
+    {
+        Some(ForeignMarker::Start)
+    } else if word.eq_ignore_ascii_case("END") || text.starts_with("<<<") {
+        Some(ForeignMarker::End)
+    } else {
+        None
+    }
+}
+
+/// 找出其他工具的区域，返回开始与结束行号
+///
+/// 开始标记之后出现结束标记才构成一个区域；没有对应结束标记的开始标记（如 `# Start of LAN devices`
+/// 这类普通注释）不计入。
+fn foreign_regions(lines: &[String]) -> Vec<(usize, usize)> {
+    let mut regions = Vec::new();
+    let mut start = None;
+
+    for (index, line) in lines.iter().enumerate() {
+        match foreign_marker(line.trim()) {
+            Some(ForeignMarker::Start) if start.is_none() => start = Some(index + 1),
+            Some(ForeignMarker::End) => {
+                if let Some(start) = start.take() {
+                    regions.push((start, index + 1));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    regions
+}
+
+/// 检查 hosts 文件中的标记，供 `append_only` 模式在写入前调用
+///
+/// 以下情况返回错误，不写入文件：
+/// - 本工具的标记重复、缺少配对或顺序颠倒
+/// - 本工具的区域位于其他工具的区域内（开始标记出现时其他工具的区域尚未结束）
+/// - 其他工具的区域在本工具的区域内开始、但直到本工具的结束标记仍未结束（区域交叉）
+fn check_markers(reader: impl BufRead) -> Result<()> {
+    const GUIDANCE: &str = "append_only 模式下为避免破坏其他工具管理的内容已拒绝写入，请手动整理 hosts 文件中的标记后重试";
+
+    let lines = reader
+        .lines()
+        .collect::<std::io::Result<Vec<_>>>()
+        .context("读取 hosts 文件失败")?;
+    let regions = foreign_regions(&lines);
+    // 包含指定行的其他工具区域的开始行号
+    let enclosing = |line_num: usize| {
+        regions
+            .iter()
+            .find(|(start, end)| *start < line_num && line_num < *end)
+            .map(|(start, _)| *start)
+    };
+
+    // 本工具区域的开始行号，以及是否已出现过完整的区域
+    let mut own_start: Option<usize> = None;
+    let mut own_seen = false;
+
+    for (index, line) in lines.iter().enumerate() {
+        let line_num = index + 1;
+        let trimmed = line.trim();
+
+        if is_start_marker(trimmed) {
+            if own_start.is_some() || own_seen {
+                return Err(anyhow::anyhow!(
+                    "第 {} 行出现重复的开始标记；{}",
+                    line_num,
+                    GUIDANCE
+                ));
+            }
+            if let Some(start) = enclosing(line_num) {
+                return Err(anyhow::anyhow!(
+                    "第 {} 行的开始标记位于其他工具的区域内（第 {} 行开始）；{}",
+                    line_num,
+                    start,
+                    GUIDANCE
+                ));
+            }
+            own_start = Some(line_num);
+            continue;
+        }
+
+        if is_end_marker(trimmed) {
+            if own_start.is_none() {
+                return Err(anyhow::anyhow!(
+                    "第 {} 行的结束标记没有对应的开始标记；{}",
+                    line_num,
+                    GUIDANCE
+                ));
+            }
+            if let Some(start) = enclosing(line_num) {
+                return Err(anyhow::anyhow!(
+                    "其他工具的区域（第 {} 行开始）与本工具的区域交叉；{}",
+                    start,
+                    GUIDANCE
+                ));
+            }
+            own_start = None;
+            own_seen = true;
+        }
+    }
+
+    if let Some(start) = own_start {
+        return Err(anyhow::anyhow!(
+            "第 {} 行的开始标记没有对应的结束标记；{}",
+            start,
+            GUIDANCE
+        ));
+    }
+
+    Ok(())
+}
+
+/// 去除首尾空白后的行是否为开始标记（标记前可能连着缺少换行的用户内容）
+fn is_start_marker(trimmed: &str) -> bool {
+    trimmed.ends_with(START_MARKER)
+}
+
+/// 去除首尾空白后的行是否为结束标记（标记后可能连着缺少换行的用户内容）
+fn is_end_marker(trimmed: &str) -> bool {
+    trimmed.starts_with(END_MARKER)
+}
+
+/// 移除自动管理区域时的标记状态
+///
+/// 按嵌套深度跟踪标记：区域内再次出现的开始标记视为嵌套，需遇到相同数量的结束标记才离开区域；
+/// 区域外多余的结束标记直接忽略。同时统计找到的区域数量，便于记录异常的重复区域。
+#[derive(Debug, Default)]
+struct MarkerState {
+    /// 当前嵌套深度，0 表示位于区域之外
+    depth: usize,
+    /// 找到的最外层区域数量
+    blocks: usize,
+    /// 嵌套在其他区域内的开始标记数量
+    nested: usize,
+    /// 刚离开区域、尚未遇到非空用户内容，期间的空行需要合并
+    seam: bool,
+}
+
+impl MarkerState {
+    /// 返回行中属于用户内容的部分，并根据标记更新嵌套深度
+    ///
+    /// 文件缺少换行时，标记可能与用户内容连在同一行：开始标记之前、结束标记之后的内容
+    /// 仍属于用户内容，单独成行保留。
+    fn user_content<'a>(&mut self, line: &'a str) -> Option<&'a str> {
+        let trimmed = line.trim();
+
+        if let Some(before) = trimmed.strip_suffix(START_MARKER) {
+            let outside = self.depth == 0;
+            self.depth += 1;
+            if outside {
+                self.blocks += 1;
+            } else {
+                self.nested += 1;
+            }
+            let before = before.trim_end();
+            return (outside && !before.is_empty()).then_some(before);
+        }
+        if let Some(after) = trimmed.strip_prefix(END_MARKER) {
+            if self.depth > 0 {
+                self.depth -= 1;
+                if self.depth > 0 {
+                    return None;
+                }
+                self.seam = true;
+            }
+            let after = after.trim_start();
+            self.seam &= after.is_empty();
+            return (!after.is_empty()).then_some(after);
+        }
+
+        if self.depth > 0 {
+            return None;
+        }
+        if !trimmed.is_empty() {
+            self.seam = false;
+        }
+        Some(line)
+    }
+
+    /// 找到多个或嵌套的区域时记录警告
+    fn log_removed(&self) {
+        if self.blocks > 1 || self.nested > 0 {
+            tracing::warn!(
+                "hosts 文件中发现 {} 个自动管理区域（其中嵌套的开始标记 {} 个），已全部移除",
+                self.blocks,
+                self.nested
+            );
+        } else if self.blocks == 1 {
+            tracing::debug!("已移除 1 个自动管理区域");
+        }
+    }
+}
+
+/// 提取自动管理区域内的内容（不含首尾标记），没有自动管理区域时返回空字符串
+///
+/// 嵌套的标记按深度匹配，内层标记本身不计入内容。
+pub fn extract_auto_section(content: &str) -> String {
+    let mut result = String::new();
+    let mut depth = 0usize;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if is_start_marker(trimmed) {
+            depth += 1;
+        } else if is_end_marker(trimmed) {
+            depth = depth.saturating_sub(1);
+        } else if depth > 0 {
+            result.push_str(line);
+            result.push('\n');
+        }
+    }
+
+    result
+}
+
+/// 解析后的自动管理区域
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ManagedSection {
+    /// 头部注释中的最后更新时间
+    pub last_update: Option<NaiveDateTime>,
+    /// 按出现顺序排列的数据源及其条目
+    pub sources: Vec<ManagedSource>,
+}
+
+/// 自动管理区域中的单个数据源
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ManagedSource {
+    /// `# Source:` 标注的 URL，`minimize` 写入的区域没有标注时为 `None`
+    pub url: Option<String>,
+    /// 条目行（去除行内注释与首尾空白）
+    pub entries: Vec<String>,
+}
+
+impl ManagedSection {
+    /// 区域中所有条目的数量
+    pub fn entry_count(&self) -> usize {
+        self.sources.iter().map(|source| source.entries.len()).sum()
+    }
+
+    /// 区域中所有条目的域名（按出现顺序，可能重复）
+    pub fn domains(&self) -> impl Iterator<Item = &str> {
+        self.sources
+            .iter()
+            .flat_map(|source| &source.entries)
+            .flat_map(|entry| entry.split_whitespace().skip(1))
+    }
+
+    /// 查找指定域名（不区分大小写）的所有条目，按出现顺序返回
+    pub fn lookup(&self, domain: &str) -> Vec<DomainMatch<'_>> {
+        self.sources
+            .iter()
+            .flat_map(|source| source.entries.iter().map(move |entry| (source, entry)))
+            .filter_map(|(source, entry)| {
+                let mut parts = entry.split_whitespace();
+                let ip = parts.next()?;
+                parts.any(|d| d.eq_ignore_ascii_case(domain)).then_some(DomainMatch {
+                    ip,
+                    source: source.url.as_deref(),
+                })
+            })
+            .collect()
+    }
+}
+
+impl ManagedSource {
+    /// 由数据源内容构造，条目的提取方式与解析自动管理区域时一致
+    pub fn from_content(url: &str, content: &str) -> Self {
+        let entries = content
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default().trim())
+            .filter(|entry| !entry.is_empty())
+            .map(str::to_string)
+            .collect();
+        ManagedSource { url: Some(url.to_string()), entries }
+    }
+}
+
+/// 域名查找结果中的单个条目
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DomainMatch<'a> {
+    /// 条目中的 IP
+    pub ip: &'a str,
+    /// 条目所属数据源的 URL
+    pub source: Option<&'a str>,
+}
+
+/// 解析 hosts 内容中的自动管理区域
+///
+/// 最后更新时间取第一条 `# Source:` 标注或条目之前的头部注释中第一个符合 `format` 的时间戳；
+/// 每条 `# Source:` 标注开始一个新的数据源，标注之前的条目归入 URL 为 `None` 的数据源。
+/// `compact_attribution` 写入的区域按 `# Sources:` 图例将条目的行尾标签还原为数据源 URL。
+/// 没有自动管理区域时返回空的结果。
+pub fn parse_managed_section(content: &str, format: &str) -> ManagedSection {
+    let mut section = ManagedSection::default();
+    let mut in_header = true;
+    let mut legend: HashMap<&str, &str> = HashMap::new();
+    let auto_section = extract_auto_section(content);
+
+    for line in auto_section.lines() {
+        let trimmed = line.trim();
+        if let Some(items) = trimmed.strip_prefix(SOURCES_LEGEND_LABEL) {
+            legend = items.split(", ").filter_map(|item| item.split_once('=')).collect();
+            continue;
+        }
+        if let Some(url) = trimmed.strip_prefix(SOURCE_LABEL) {
+            in_header = false;
+            section.sources.push(ManagedSource {
+                url: Some(url.trim().to_string()),
+                entries: Vec::new(),
+            });
+            continue;
+        }
+
+        let entry = trimmed.split('#').next().unwrap_or_default().trim();
+        if entry.is_empty() {
+            if in_header && section.last_update.is_none() {
+                section.last_update = find_timestamp(trimmed, format);
+            }
+            continue;
+        }
+
+        in_header = false;
+        let tagged = trimmed
+            .split_once('#')
+            .and_then(|(_, tag)| legend.get(tag.trim()))
+            .map(|url| url.to_string());
+        if let Some(url) = tagged {
+            if section.sources.last().is_none_or(|source| source.url.as_ref() != Some(&url)) {
+                section.sources.push(ManagedSource { url: Some(url), entries: Vec::new() });
+            }
+        } else if section.sources.is_empty() {
+            section.sources.push(ManagedSource::default());
+        }
+        if let Some(source) = section.sources.last_mut() {
+            source.entries.push(entry.to_string());
+        }
+    }
+
+    section
+}
+
+/// 移除自动管理区域
+///
+/// 多个或嵌套的区域全部移除；区域移除处前后的空行合并为一个。
+/// 没有开始标记时原样返回；结果缓冲区按输入长度预先分配，百万行的文件也不会反复扩容。
+pub fn remove_auto_managed_section(content: &str) -> String {
+    // 没有开始标记时不会移除任何内容，跳过逐行处理
+    if !content.contains(START_MARKER) {
+        return content.to_string();
+    }
+
+    let mut result = String::with_capacity(content.len());
+    let mut state = MarkerState::default();
+    let mut last_blank = false;
+
+    for line in content.lines() {
+        let Some(line) = state.user_content(line) else {
+            continue;
+        };
+
+        let blank = line.trim().is_empty();
+        if blank && state.seam && (result.is_empty() || last_blank) {
+            continue;
+        }
+        last_blank = blank;
+        result.push_str(line);
+        result.push('\n');
+    }
+
+    // 如果没有找到标记，返回原内容
+    if state.blocks == 0 {
+        content.to_string()
+    } else {
+        state.log_removed();
+        result.truncate(result.trim_end().len());
+        result
+    }
+}
+
+/// 流式移除自动管理区域
+///
+/// 逐行读取并写出区域外的内容，输出与 `remove_auto_managed_section` 去除末尾空白后一致。
+/// 末尾的空白在遇到下一行非空内容前暂存，因此内存占用与文件大小无关。
+/// 指定 `leading` 时，开头的空行替换为 `leading`（仅在存在非空内容时写出）。
+/// 行号位于 `skip` 中的行（开始标记之上的注释，由调用方随新区域写出）直接跳过。
+/// 返回是否写出了非空内容。
+fn remove_auto_managed_section_streaming(
+    reader: impl BufRead,
+    writer: &mut impl Write,
+    leading: Option<&[u8]>,
+    skip: std::ops::Range<usize>,
+) -> Result<bool> {
+    let mut state = MarkerState::default();
+    let mut has_content = false;
+    // 尚未写出的空白（上一行末尾空白及其后的空行）
+    let mut pending = String::new();
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line.context("读取 hosts 文件失败")?;
+        if skip.contains(&i) {
+            continue;
+        }
+        let Some(line) = state.user_content(&line) else {
+            continue;
+        };
+
+        if line.trim().is_empty() {
+            // 与 remove_auto_managed_section 一致：区域移除处只保留一个空行
+            let pending_blank = pending.matches('\n').count() > usize::from(has_content);
+            if state.seam && (!has_content || pending_blank) {
+                continue;
+            }
+            pending.push_str(line);
+            pending.push('\n');
+            continue;
+        }
+
+        let content = line.trim_end();
+        match leading {
+            Some(leading) if !has_content => writer.write_all(leading)?,
+            _ => writer.write_all(pending.as_bytes())?,
+        }
+        writer.write_all(content.as_bytes())?;
+        pending.clear();
+        pending.push_str(&line[content.len()..]);
+        pending.push('\n');
+        has_content = true;
+    }
+
+    state.log_removed();
+    Ok(has_content)
+}
+
+/// 中文默认头部模板
+const DEFAULT_HEADER_ZH: &str = "# 此区域由 hosts_updater_rs 自动管理，请勿手动修改\n# 最后更新: {last_update}";
+
+/// 英文默认头部模板
+const DEFAULT_HEADER_EN: &str =
+    "# This section is managed by hosts_updater_rs, do not edit manually\n# Last updated: {last_update}";
+
+/// 渲染自动管理区域的头部
+///
+/// 未配置 `header_template` 时按 `locale` 选择默认模板。
+fn render_header(config: &Config, last_update: &str, source_count: usize) -> String {
+    let template = config
+        .header_template
+        .as_deref()
+        .unwrap_or(match config.locale {
+            Locale::Zh => DEFAULT_HEADER_ZH,
+            Locale::En => DEFAULT_HEADER_EN,
+        });
+
+    template
+        .replace("{last_update}", last_update)
+        .replace("{source_count}", &source_count.to_string())
+        .replace("{tool_version}", env!("CARGO_PKG_VERSION"))
+}
+
+/// 渲染结束标记前的来源注脚：程序版本、主机名与配置文件路径
+///
+/// 未启用 `provenance_footer` 或启用了 `minimize` 时返回空字符串。
+fn render_footer(config: &Config) -> String {
+    if !config.provenance_footer || config.minimize {
+        return String::new();
+    }
+
+    let host = gethostname::gethostname().to_string_lossy().to_string();
+    let config_path = config.config_path.as_deref().unwrap_or("-");
+    match config.locale {
+        Locale::Zh => format!(
+            "# 生成者: hosts_updater_rs {}，主机: {}，配置文件: {}",
+            env!("CARGO_PKG_VERSION"),
+            host,
+            config_path
+        ),
+        Locale::En => format!(
+            "# Generated by hosts_updater_rs {} on {} using {}",
+            env!("CARGO_PKG_VERSION"),
+            host,
+            config_path
+        ),
+    }
+}
+
+/// 构建自动管理区域，头部为空时省略头部及其后的空行，注脚不为空时置于结束标记之前
+fn build_auto_section(
+    header: &str,
+    entries: &str,
+    footer: &str,
+    spacing: SectionSpacing,
+) -> String {
+    let mut section = String::new();
+
+    section.push_str(START_MARKER);
+    section.push('\n');
+    if !header.trim().is_empty() {
+        section.push_str(header.trim_end());
+        section.push('\n');
+        if spacing == SectionSpacing::Normal {
+            section.push('\n');
+        }
+    }
+    section.push_str(entries);
+    if !footer.trim().is_empty() {
+        section.push_str(footer.trim_end());
+        section.push('\n');
+    }
+    section.push_str(END_MARKER);
+    section.push('\n');
+
+    section
+}
+
+/// 构建自动管理区域中各数据源的条目区块
+///
+/// 没有有效条目（仅包含注释）的数据源不会生成区块。
+/// `minimize` 时只输出条目行，去除行内注释，不生成 `# Source:` 标注与空行；
+/// 同时启用 `compact_attribution` 时，开头输出一行 `# Sources:` 图例，每个条目行尾添加所属数据源的标签。
+fn build_source_entries(sources: &[SourceResult], config: &Config) -> String {
+    let mut entries = String::new();
+    let sources: Vec<&SourceResult> =
+        sources.iter().filter(|source| source.entry_count() > 0).collect();
+
+    let compact = config.minimize && config.compact_attribution;
+    if compact && !sources.is_empty() {
+        let legend: Vec<String> = sources
+            .iter()
+            .enumerate()
+            .map(|(i, source)| format!("s{}={}", i + 1, source.url))
+            .collect();
+        entries.push_str(SOURCES_LEGEND_LABEL);
+        entries.push_str(&legend.join(", "));
+        entries.push('\n');
+    }
+
+    for (i, source) in sources.into_iter().enumerate() {
+        if config.minimize {
+            let tag = format!(" #s{}", i + 1);
+            for line in source.content.lines() {
+                let entry = line.split('#').next().unwrap_or_default().trim();
+                if entry.is_empty() {
+                    continue;
+                }
+                let start = entries.len();
+                if config.normalize_whitespace {
+                    push_entry_line(&mut entries, &normalize_line(entry), config);
+                } else {
+                    push_entry_line(&mut entries, entry, config);
+                }
+                // 拆分出的每一行都带上标签
+                if compact {
+                    let tagged: String =
+                        entries[start..].lines().map(|line| format!("{}{}\n", line, tag)).collect();
+                    entries.truncate(start);
+                    entries.push_str(&tagged);
+                }
+            }
+            continue;
+        }
+
+        entries.push_str(SOURCE_LABEL);
+        entries.push_str(&source.url);
+        entries.push('\n');
+        let content = source.content.trim();
+        if config.normalize_whitespace {
+            for line in content.lines() {
+                push_entry_line(&mut entries, &normalize_line(line), config);
+            }
+        } else if config.max_domains_per_line.is_some() || config.max_line_length.is_some() {
+            for line in content.lines() {
+                push_entry_line(&mut entries, line, config);
+            }
+        } else {
+            entries.push_str(content);
+            entries.push('\n');
+        }
+        if config.section_spacing == SectionSpacing::Normal {
+            entries.push('\n');
+        }
+    }
+
+    entries
+}
+
+/// 追加一行内容，条目行超出 `max_domains_per_line` 或 `max_line_length` 时拆分为多行
+///
+/// 拆分出的各行使用相同的 IP，域名保持原有顺序；行尾注释单独成行放在最前。
+/// 单个域名本身已超出长度限制时无法再拆分，仍单独成行。
+fn push_entry_line(entries: &mut String, line: &str, config: &Config) {
+    let max_domains = config.max_domains_per_line.unwrap_or(usize::MAX);
+    let max_length = config.max_line_length.unwrap_or(usize::MAX);
+
+    let (entry, comment) = match line.find('#') {
+        Some(pos) => (&line[..pos], Some(line[pos..].trim())),
+        None => (line, None),
+    };
+    let mut parts = entry.split_whitespace();
+    let ip = parts.next();
+    let domains: Vec<&str> = parts.collect();
+
+    let Some(ip) = ip.filter(|_| domains.len() > max_domains || line.len() > max_length) else {
+        entries.push_str(line);
+        entries.push('\n');
+        return;
+    };
+
+    if let Some(comment) = comment {
+        entries.push_str(comment);
+        entries.push('\n');
+    }
+    let mut current = ip.to_string();
+    let mut count = 0;
+    for domain in domains {
+        if count > 0 && (count == max_domains || current.len() + 1 + domain.len() > max_length) {
+            entries.push_str(&current);
+            entries.push('\n');
+            current = ip.to_string();
+            count = 0;
+        }
+        current.push(' ');
+        current.push_str(domain);
+        count += 1;
+    }
+    entries.push_str(&current);
+    entries.push('\n');
+}
+
+/// 规范化单行空白
+///
+/// 条目行的 IP 与域名之间统一使用单个空格分隔，所有行去除首尾空白。
+fn normalize_line(line: &str) -> String {
+    let trimmed = line.trim();
+    if trimmed.is_empty() || trimmed.starts_with('#') {
+        return trimmed.to_string();
+    }
+
+    trimmed.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render_section(sources: &[SourceResult], config: &Config) -> String {
+        let header = render_header(config, "2024-01-15 10:30:00", sources.len());
+        build_auto_section(
+            &header,
+            &build_source_entries(sources, config),
+            &render_footer(config),
+            config.section_spacing,
+        )
+    }
+
+    #[test]
+    fn test_post_check_samples() {
+        let section = parse_managed_section(
+            &format!(
+                "{}\n# Source: https://a.example\n127.0.0.1 localhost a.com\n\
+                 [::1] ip6-localhost\nbad.ip b.com\n0.0.0.0 c.com\n{}\n",
+                START_MARKER, END_MARKER
+            ),
+            LAST_UPDATE_FORMAT,
+        );
+        let samples = sample_entries(&section, 2);
+        assert_eq!(
+            samples,
+            [
+                ("127.0.0.1".parse().unwrap(), "localhost"),
+                ("::1".parse().unwrap(), "ip6-localhost"),
+            ]
+        );
+        assert!(resolves_to("localhost", "127.0.0.1".parse().unwrap()));
+        assert!(!resolves_to("localhost", "10.9.8.7".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_managed_section_lookup() {
+        let section = ManagedSection {
+            last_update: None,
+            sources: vec![
+                ManagedSource::from_content(
+                    "https://a.example",
+                    "# comment\n0.0.0.0 ads.example.com tracker.example.com\n",
+                ),
+                ManagedSource::from_content("https://b.example", "127.0.0.1 Ads.Example.com\n"),
+            ],
+        };
+
+        assert_eq!(
+            section.lookup("ads.example.com"),
+            [
+                DomainMatch { ip: "0.0.0.0", source: Some("https://a.example") },
+                DomainMatch { ip: "127.0.0.1", source: Some("https://b.example") },
+            ]
+        );
+        assert!(section.lookup("example.com").is_empty());
+    }
+
+    #[test]
+    fn test_lint_hosts_content() {
+        let content = "# 手动编辑\n127.0.0.1 localhost # 本机\n999.0.0.1 bad.ip\n\n\
+                       10.0.0.1\n::1 ip6-localhost\n";
+        let errors = lint_hosts_content(content);
+        let lines: Vec<usize> = errors.iter().map(|e| e.line_num).collect();
+        assert_eq!(lines, [3, 5]);
+        assert_eq!(errors[0].content, "999.0.0.1 bad.ip");
+        assert!(lint_hosts_content("127.0.0.1 localhost\n").is_empty());
+    }
+
+    #[test]
+    fn test_count_entries() {
+        assert_eq!(count_entries("# 注释\n\n127.0.0.1 a.com\n  0.0.0.0 b.com c.com\n"), 2);
+        assert_eq!(count_entries("# 仅注释\n"), 0);
+    }
+
+    #[test]
+    fn test_build_auto_section_skips_comment_only_source() {
+        let sources = vec![
+            SourceResult::for_test("https://a.example", "# 暂无条目\n"),
+            SourceResult::for_test("https://b.example", "127.0.0.1 b.com\n"),
+        ];
+        let config = Config::for_test(r#"{ "hosts_sources": [] }"#);
+        let section = render_section(&sources, &config);
+
+        assert!(!section.contains("https://a.example"));
+        assert!(section.contains("# Source: https://b.example\n127.0.0.1 b.com\n"));
+    }
+
+    #[test]
+    fn test_build_auto_section_spacing() {
+        let sources = vec![
+            SourceResult::for_test("https://a.example", "127.0.0.1 a.com\n"),
+            SourceResult::for_test("https://b.example", "127.0.0.1 b.com\n"),
+        ];
+
+        let config = Config::for_test(r##"{ "hosts_sources": [], "header_template": "# 头部" }"##);
+        assert_eq!(
+            render_section(&sources, &config),
+            format!(
+                "{}\n# 头部\n\n# Source: https://a.example\n127.0.0.1 a.com\n\n\
+                 # Source: https://b.example\n127.0.0.1 b.com\n\n{}\n",
+                START_MARKER, END_MARKER
+            )
+        );
+
+        let config = Config::for_test(
+            r##"{ "hosts_sources": [], "header_template": "# 头部", "section_spacing": "compact" }"##,
+        );
+        assert_eq!(
+            render_section(&sources, &config),
+            format!(
+                "{}\n# 头部\n# Source: https://a.example\n127.0.0.1 a.com\n\
+                 # Source: https://b.example\n127.0.0.1 b.com\n{}\n",
+                START_MARKER, END_MARKER
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_managed_section() {
+        let sources = vec![
+            SourceResult::for_test("https://a.example", "# 注释\n127.0.0.1 a.com b.com # 行尾\n"),
+            SourceResult::for_test("https://b.example", "0.0.0.0 c.com\n"),
+        ];
+        let config = Config::for_test(r#"{ "hosts_sources": [], "provenance_footer": true }"#);
+        let content = format!(
+            "127.0.0.1 localhost\n{}",
+            build_auto_section(
+                &render_header(&config, "2024-01-15 10:30:00", sources.len()),
+                &build_source_entries(&sources, &config),
+                &render_footer(&config),
+                config.section_spacing,
+            )
+        );
+
+        let section = parse_managed_section(&content, LAST_UPDATE_FORMAT);
+        assert_eq!(
+            section.last_update,
+            NaiveDateTime::parse_from_str("2024-01-15 10:30:00", LAST_UPDATE_FORMAT).ok()
+        );
+        assert_eq!(
+            section.sources,
+            vec![
+                ManagedSource {
+                    url: Some("https://a.example".to_string()),
+                    entries: vec!["127.0.0.1 a.com b.com".to_string()],
+                },
+                ManagedSource {
+                    url: Some("https://b.example".to_string()),
+                    entries: vec!["0.0.0.0 c.com".to_string()],
+                },
+            ]
+        );
+        assert_eq!(section.entry_count(), 2);
+        assert_eq!(section.domains().collect::<Vec<_>>(), ["a.com", "b.com", "c.com"]);
+
+        // minimize 写入的区域没有头部与数据源标注
+        let config = Config::for_test(r#"{ "hosts_sources": [], "minimize": true }"#);
+        let minimized = build_auto_section(
+            "",
+            &build_source_entries(&sources, &config),
+            "",
+            config.section_spacing,
+        );
+        let section = parse_managed_section(&minimized, LAST_UPDATE_FORMAT);
+        assert_eq!(section.last_update, None);
+        assert_eq!(section.sources.len(), 1);
+        assert_eq!(section.sources[0].url, None);
+        assert_eq!(section.entry_count(), 2);
+
+        assert_eq!(
+            parse_managed_section("127.0.0.1 localhost\n", LAST_UPDATE_FORMAT),
+            ManagedSection::default()
+        );
+    }
+
+    #[test]
+    fn test_compact_attribution() {
+        let sources = vec![
+            SourceResult::for_test("https://a.example", "# 注释\n127.0.0.1 a.com b.com c.com # 行尾\n"),
+            SourceResult::for_test("https://b.example", "# 暂无条目\n"),
+            SourceResult::for_test("https://c.example", "0.0.0.0 d.com\n"),
+        ];
+        let config = Config::for_test(
+            r#"{ "hosts_sources": [], "minimize": true, "compact_attribution": true,
+                 "max_domains_per_line": 2 }"#,
+        );
+        let entries = build_source_entries(&sources, &config);
+        assert_eq!(
+            entries,
+            "# Sources: s1=https://a.example, s2=https://c.example\n\
+             127.0.0.1 a.com b.com #s1\n127.0.0.1 c.com #s1\n0.0.0.0 d.com #s2\n"
+        );
+
+        // 解析时按图例还原数据源，查找结果可以得知条目来源
+        let section = build_auto_section("", &entries, "", config.section_spacing);
+        let section = parse_managed_section(&section, LAST_UPDATE_FORMAT);
+        assert_eq!(section.sources.len(), 2);
+        assert_eq!(section.sources[0].entries.len(), 2);
+        assert_eq!(section.lookup("d.com")[0].source, Some("https://c.example"));
+
+        assert!(crate::config::validate_config(&Config::for_test(
+            r#"{ "hosts_sources": ["https://a.example"], "compact_attribution": true }"#
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn test_retry_while_locked() {
+        let path = Path::new("hosts");
+        let mut attempts = 0;
+        let result = retry_while_locked(path, || {
+            attempts += 1;
+            if attempts < 3 {
+                Err(io::Error::from_raw_os_error(ERROR_SHARING_VIOLATION))
+            } else {
+                Ok(attempts)
+            }
+        });
+
+        // 只有 Windows 上的共享冲突会重试，其他平台直接返回错误
+        if cfg!(windows) {
+            assert_eq!(result.unwrap(), 3);
+        } else {
+            assert!(result.is_err());
+            assert_eq!(attempts, 1);
+        }
+    }
+
+    #[test]
+    fn test_provenance_footer() {
+        let sources = vec![SourceResult::for_test("https://a.example", "127.0.0.1 a.com\n")];
+        let mut config = Config::for_test(
+            r#"{ "hosts_sources": [], "provenance_footer": true, "locale": "en" }"#,
+        );
+        config.config_path = Some("/etc/hosts_updater/config.toml".to_string());
+
+        let section = render_section(&sources, &config);
+        let footer_line = section.lines().rev().nth(1).unwrap();
+        assert!(footer_line.starts_with("# Generated by hosts_updater_rs "));
+        assert!(footer_line.ends_with(" using /etc/hosts_updater/config.toml"));
+        assert_eq!(count_entries(&section), 1);
+
+        let content = format!("127.0.0.1 localhost\n\n{}", section);
+        assert_eq!(remove_auto_managed_section(&content), "127.0.0.1 localhost");
+
+        config.minimize = true;
+        assert!(!render_section(&sources, &config).contains("Generated by"));
+    }
+
+    #[test]
+    fn test_remove_auto_managed_section_streaming() {
+        let section = format!("{}\n# 最后更新: x\n0.0.0.0 a.com\n{}\n", START_MARKER, END_MARKER);
+        let cases = [
+            String::new(),
+            "\n  \n".to_string(),
+            "127.0.0.1 localhost\n".to_string(),
+            format!("\n127.0.0.1 localhost  \n\n{}\n::1 localhost\n\n \n", section),
+            format!("127.0.0.1 localhost\n\n{}", section),
+            format!("127.0.0.1 localhost{}", section),
+            format!("{}::1 localhost", section.trim_end()),
+        ];
+
+        for content in cases {
+            let mut output = Vec::new();
+            let has_content =
+                remove_auto_managed_section_streaming(content.as_bytes(), &mut output, None, 0..0)
+                    .unwrap();
+            let expected = remove_auto_managed_section(&content);
+
+            assert_eq!(String::from_utf8(output).unwrap(), expected.trim_end());
+            assert_eq!(has_content, !expected.trim().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_remove_duplicate_and_nested_sections() {
+        let section = format!("{}\n0.0.0.0 a.com\n{}\n", START_MARKER, END_MARKER);
+
+        // 两个区域：全部移除，移除处的空行合并为一个
+        let two_blocks = format!(
+            "127.0.0.1 localhost\n\n{}\n\n::1 localhost\n\n{}\n\n# 用户注释\n",
+            section, section
+        );
+        // 嵌套区域：内层结束标记之后、外层结束标记之前的内容同样属于区域
+        let nested = format!(
+            "127.0.0.1 localhost\n\n{}\n0.0.0.0 b.com\n{}0.0.0.0 c.com\n{}\n\n::1 localhost\n\n\
+             # 用户注释\n",
+            START_MARKER, section, END_MARKER
+        );
+        let expected = "127.0.0.1 localhost\n\n::1 localhost\n\n# 用户注释";
+
+        for content in [two_blocks, nested] {
+            assert_eq!(remove_auto_managed_section(&content), expected, "{:?}", content);
+
+            let mut output = Vec::new();
+            remove_auto_managed_section_streaming(content.as_bytes(), &mut output, None, 0..0).unwrap();
+            assert_eq!(String::from_utf8(output).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_combine_hosts_trailing_newline() {
+        let section = format!("{}\n0.0.0.0 a.com\n{}\n", START_MARKER, END_MARKER);
+        let expected = format!("127.0.0.1 localhost\n\n{}", section);
+        let cases = [
+            "127.0.0.1 localhost".to_string(),
+            "127.0.0.1 localhost\n".to_string(),
+            "127.0.0.1 localhost \t\n\n\n".to_string(),
+            format!("127.0.0.1 localhost\n\n{}", section.trim_end()),
+            format!("127.0.0.1 localhost  \n{}", section),
+            format!("127.0.0.1 localhost{}", section),
+        ];
+
+        for content in cases {
+            assert_eq!(
+                combine_hosts(&content, section.clone(), BlockPosition::Bottom, None),
+                expected,
+                "{:?}",
+                content
+            );
+        }
+
+        // 结束标记后缺少换行、直接连着用户内容
+        let content = format!("127.0.0.1 localhost\n{}::1 localhost", section.trim_end());
+        assert_eq!(
+            combine_hosts(&content, section.clone(), BlockPosition::Bottom, None),
+            format!("127.0.0.1 localhost\n::1 localhost\n\n{}", section)
+        );
+        assert_eq!(combine_hosts("\n \n", section.clone(), BlockPosition::Bottom, None), section);
+    }
+
+    #[test]
+    fn test_combine_hosts_top() {
+        let section = format!("{}\n0.0.0.0 a.com\n{}\n", START_MARKER, END_MARKER);
+        let expected = format!("{}\n127.0.0.1 localhost\n", section);
+        let cases = [
+            "\n \n127.0.0.1 localhost\n\n".to_string(),
+            format!("127.0.0.1 localhost\n\n{}", section),
+            expected.clone(),
+        ];
+
+        for content in cases {
+            let combined = combine_hosts(&content, section.clone(), BlockPosition::Top, None);
+            assert_eq!(combined, expected, "{:?}", content);
+
+            // 流式写入的结果与一次性组合一致
+            let mut output = section.clone().into_bytes();
+            if remove_auto_managed_section_streaming(content.as_bytes(), &mut output, Some(b"\n"), 0..0)
+                .unwrap()
+            {
+                output.push(b'\n');
+            }
+            assert_eq!(String::from_utf8(output).unwrap(), expected);
+        }
+        assert_eq!(combine_hosts("\n", section.clone(), BlockPosition::Top, None), section);
+    }
+
+    #[test]
+    fn test_combine_hosts_anchor() {
+        let section = format!("{}\n0.0.0.0 a.com\n{}\n", START_MARKER, END_MARKER);
+        let content = "127.0.0.1 localhost\n# BEGIN other\n0.0.0.0 b.com\n# END other\n\
+                       192.168.1.2 nas.lan\n";
+
+        let after = combine_hosts(
+            content,
+            section.clone(),
+            BlockPosition::Bottom,
+            Some(BlockAnchor::After("# END other")),
+        );
+        let expected = format!(
+            "127.0.0.1 localhost\n# BEGIN other\n0.0.0.0 b.com\n# END other\n\n{}\
+             \n192.168.1.2 nas.lan\n",
+            section
+        );
+        assert_eq!(after, expected);
+        // 重复写入时位置不变
+        let again = combine_hosts(
+            &after,
+            section.clone(),
+            BlockPosition::Bottom,
+            Some(BlockAnchor::After("# END other")),
+        );
+        assert_eq!(again, expected);
+
+        let before = combine_hosts(
+            content,
+            section.clone(),
+            BlockPosition::Bottom,
+            Some(BlockAnchor::Before("  # BEGIN other ")),
+        );
+        assert_eq!(
+            before,
+            format!(
+                "127.0.0.1 localhost\n\n{}\n# BEGIN other\n0.0.0.0 b.com\n# END other\n\
+                 192.168.1.2 nas.lan\n",
+                section
+            )
+        );
+
+        // 找不到锚点时按 block_position 放置
+        assert_eq!(
+            combine_hosts(
+                content,
+                section.clone(),
+                BlockPosition::Top,
+                Some(BlockAnchor::After("# END missing")),
+            ),
+            combine_hosts(content, section.clone(), BlockPosition::Top, None)
+        );
+    }
+
+    #[test]
+    fn test_combine_hosts_keeps_marker_note() {
+        let section = format!("{}\n0.0.0.0 a.com\n{}\n", START_MARKER, END_MARKER);
+        let note = "# below managed by hosts_updater - do not edit\n#   (ops team)";
+        let bottom = format!("127.0.0.1 localhost\n\n{}\n{}", note, section);
+        let top = format!("{}\n{}\n127.0.0.1 localhost\n", note, section);
+
+        let cases = [(BlockPosition::Bottom, &bottom), (BlockPosition::Top, &top)];
+        for (position, expected) in cases {
+            // 注释紧挨开始标记时原样保留，重复写入的结果不变
+            for content in [bottom.clone(), top.clone()] {
+                let combined = combine_hosts(&content, section.clone(), position, None);
+                assert_eq!(&combined, expected, "{:?}", content);
+                assert_eq!(&combine_hosts(&combined, section.clone(), position, None), expected);
+            }
+        }
+
+        // 流式写入同样保留注释
+        let found = find_marker_note(bottom.lines()).unwrap();
+        assert_eq!(found.range(), 2..4);
+        let mut output = Vec::new();
+        remove_auto_managed_section_streaming(bottom.as_bytes(), &mut output, None, found.range())
+            .unwrap();
+        output.extend_from_slice(b"\n\n");
+        output.extend_from_slice(found.attach(section.clone()).as_bytes());
+        assert_eq!(String::from_utf8(output).unwrap(), bottom);
+
+        // 与开始标记之间有空行的注释不随区域移动
+        let separated = format!("# 用户注释\n\n{}\n127.0.0.1 localhost\n", section);
+        assert!(find_marker_note(separated.lines()).is_none());
+    }
+
+    #[test]
+    fn test_write_hosts_to_leaves_system_hosts() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("hosts");
+        let config = Config::for_test(r#"{ "hosts_sources": [] }"#);
+        let before = read_hosts_content().unwrap();
+
+        let sources = [SourceResult::for_test("https://a.example/hosts", "0.0.0.0 a.com\n")];
+        let entries = write_hosts_to(&output, &sources, "2024-01-15 10:30:00", &config).unwrap();
+
+        let written = fs::read_to_string(&output).unwrap();
+        assert!(written.contains(START_MARKER));
+        assert!(written.ends_with(&format!("{}{}\n", entries, END_MARKER)));
+        assert_eq!(read_hosts_content().unwrap(), before);
+    }
+
+    #[test]
+    fn test_write_include_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("hosts.d").join("updater");
+        let mut config = Config::for_test(r#"{ "hosts_sources": ["https://a.example/hosts"] }"#);
+        config.output_file = Some(output.to_string_lossy().to_string());
+        let before = read_hosts_content().unwrap();
+        assert_eq!(read_managed_content(&config).unwrap(), "");
+
+        // include 文件只包含自动管理区域，不合并系统 hosts 文件中的内容
+        let sources = [SourceResult::for_test("https://a.example/hosts", "0.0.0.0 a.com\n")];
+        let entries =
+            write_include_file(&output, &sources, "2024-01-15 10:30:00", &config).unwrap();
+        let written = read_managed_content(&config).unwrap();
+        assert!(written.starts_with(START_MARKER));
+        assert!(written.ends_with(&format!("{}{}\n", entries, END_MARKER)));
+        assert_eq!(render_hosts(&sources, "2024-01-15 10:30:00", &config).unwrap(), written);
+        assert_eq!(read_hosts_content().unwrap(), before);
+
+        let section = parse_managed_section(&written, "%Y-%m-%d %H:%M:%S");
+        assert_eq!(section.entry_count(), 1);
+
+        assert!(crate::config::validate_config(&config).is_ok());
+        let hosts = get_hosts_path().to_string_lossy().to_string();
+        config.output_file = Some(hosts);
+        assert!(crate::config::validate_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_backup_info() {
+        let dir = tempfile::tempdir().unwrap();
+        let plain = dir.path().join("hosts.backup.20240115_103000");
+        fs::write(&plain, "127.0.0.1 localhost\n").unwrap();
+        let compressed = dir.path().join("hosts.backup.20240116_103000.gz");
+        fs::write(&compressed, [0x1f, 0x8b, 0x08, 0x00]).unwrap();
+
+        let info = backup_info(&plain).unwrap();
+        assert_eq!(info.timestamp.unwrap().to_string(), "2024-01-15 10:30:00");
+        assert_eq!(info.size, 20);
+        assert!(!info.gzip);
+
+        let info = backup_info(&compressed).unwrap();
+        assert_eq!(info.timestamp.unwrap().to_string(), "2024-01-16 10:30:00");
+        assert!(info.gzip);
+    }
+
+    #[test]
+    fn test_read_only_error() {
+        let dir = Path::new("/etc");
+        let error = read_only_error(dir, &io::Error::from(io::ErrorKind::ReadOnlyFilesystem));
+        let message = error.unwrap().to_string();
+        assert!(message.starts_with("hosts 目录为只读，无法写入"));
+        assert!(message.contains("--output"));
+
+        let denied = io::Error::from(io::ErrorKind::PermissionDenied);
+        assert!(read_only_error(dir, &denied).is_none());
+    }
+
+    #[test]
+    fn test_verify_written() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hosts");
+        let section = format!("{}\n0.0.0.0 a.com\n{}\n", START_MARKER, END_MARKER);
+
+        fs::write(&path, format!("127.0.0.1 localhost\n\n{}", section)).unwrap();
+        assert!(verify_written(&path, &section).is_ok());
+
+        // 区域内容被截断或缺少结束标记时校验失败
+        fs::write(&path, format!("{}\n0.0.0.0 a.c", START_MARKER)).unwrap();
+        assert!(verify_written(&path, &section).is_err());
+        fs::write(&path, format!("{}\n0.0.0.0 b.com\n{}\n", START_MARKER, END_MARKER)).unwrap();
+        assert!(verify_written(&path, &section).is_err());
+    }
+
+    #[test]
+    fn test_render_header() {
+        let config = Config::for_test(r#"{ "hosts_sources": [] }"#);
+        assert_eq!(
+            render_header(&config, "2024-01-15 10:30:00", 2),
+            "# 此区域由 hosts_updater_rs 自动管理，请勿手动修改\n# 最后更新: 2024-01-15 10:30:00"
+        );
+
+        let config = Config::for_test(r#"{ "hosts_sources": [], "locale": "en" }"#);
+        assert!(render_header(&config, "x", 2).contains("# Last updated: x"));
+
+        let config = Config::for_test(
+            r##"{ "hosts_sources": [], "header_template": "# ACME {tool_version}\n# {source_count} 个数据源 @ {last_update}" }"##,
+        );
+        assert_eq!(
+            render_header(&config, "x", 2),
+            format!("# ACME {}\n# 2 个数据源 @ x", env!("CARGO_PKG_VERSION"))
+        );
+    }
+
+    #[test]
+    fn test_parse_last_update() {
+        let expected =
+            NaiveDateTime::parse_from_str("2024-01-15 10:30:00", LAST_UPDATE_FORMAT).unwrap();
+
+        for locale in ["zh", "en"] {
+            let config = Config::for_test(&format!(
+                r#"{{ "hosts_sources": [], "locale": "{}" }}"#,
+                locale
+            ));
+            let header = render_header(&config, "2024-01-15 10:30:00", 1);
+            let content = format!(
+                "127.0.0.1 localhost\n{}",
+                build_auto_section(&header, "0.0.0.0 a.com\n", "", SectionSpacing::Normal)
+            );
+            assert_eq!(parse_last_update(content.as_bytes(), LAST_UPDATE_FORMAT), Some(expected));
+        }
+
+        let custom = build_auto_section(
+            "# Last updated: 2024-01-15T10:30:00+0000",
+            "0.0.0.0 a.com\n",
+            "",
+            SectionSpacing::Normal,
+        );
+        assert_eq!(parse_last_update(custom.as_bytes(), "%Y-%m-%dT%H:%M:%S%z"), Some(expected));
+
+        let without_time = build_auto_section(
+            "# 自定义头部",
+            "0.0.0.0 a.com\n# 2024-01-15 10:30:00\n",
+            "",
+            SectionSpacing::Normal,
+        );
+        assert_eq!(parse_last_update(without_time.as_bytes(), LAST_UPDATE_FORMAT), None);
+        assert_eq!(
+            parse_last_update("127.0.0.1 localhost\n".as_bytes(), LAST_UPDATE_FORMAT),
+            None
+        );
+    }
+
+    #[test]
+    fn test_check_markers() {
+        let own = format!("{}\n0.0.0.0 a.com\n{}\n", START_MARKER, END_MARKER);
+        let check = |content: String| check_markers(content.as_bytes());
+
+        // 与其他工具的区域前后并列
+        assert!(check(format!("# BEGIN other\n1.1.1.1 b.com\n# END other\n{}", own)).is_ok());
+        assert!(check("127.0.0.1 localhost\n".to_string()).is_ok());
+
+        // 嵌套在其他工具的区域内
+        assert!(check(format!("# BEGIN other\n{}# END other\n", own)).is_err());
+        // 交叉
+        assert!(
+            check(format!(
+                "{}\n# >>> other >>>\n{}\n# <<< other <<<\n",
+                START_MARKER, END_MARKER
+            ))
+            .is_err()
+        );
+        // 重复或缺少配对的标记
+        assert!(check(format!("{}{}", own, own)).is_err());
+        assert!(check(format!("{}\n0.0.0.0 a.com\n", START_MARKER)).is_err());
+        assert!(check(format!("{}\n", END_MARKER)).is_err());
+
+        // 像标记的普通注释：没有对应的结束标记，或不是完整的单词
+        assert!(check(format!("# Start of LAN devices\n192.168.1.2 nas\n{}", own)).is_ok());
+        assert!(check(format!("# Startup servers\n{}# Ending\n", own)).is_ok());
+        assert!(check(format!("# Beginning of office printers\n{}# END\n", own)).is_ok());
+    }
+
+    #[test]
+    fn test_normalize_line() {
+        assert_eq!(normalize_line("0.0.0.0\ta.com   b.com  "), "0.0.0.0 a.com b.com");
+        assert_eq!(normalize_line("  # 注释  "), "# 注释");
+        assert_eq!(normalize_line("   "), "");
+    }
+
+    #[test]
+    fn test_entry_line_limits() {
+        let content = "0.0.0.0 a.example.com b.example.com c.example.com d.example.com\n\
+                       0.0.0.0 e.com\n\
+                       127.0.0.1 very-long-subdomain-name.example.org\n";
+        let sources = [SourceResult::for_test("https://a.example/hosts", content)];
+        let domains = [
+            "a.example.com",
+            "b.example.com",
+            "c.example.com",
+            "d.example.com",
+            "e.com",
+            "very-long-subdomain-name.example.org",
+        ];
+
+        let limits = [(Some(2), None), (None, Some(40)), (Some(3), Some(32))];
+        for (max_domains, max_length) in limits {
+            let mut config = Config::for_test(r#"{ "hosts_sources": [] }"#);
+            config.max_domains_per_line = max_domains;
+            config.max_line_length = max_length;
+            let (section, entries) =
+                render_auto_section(&sources, "2024-01-15 10:30:00", &config).unwrap();
+
+            let entry_lines: Vec<&str> = entries
+                .lines()
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .collect();
+            for line in &entry_lines {
+                let domains = line.split_whitespace().count() - 1;
+                // 单个域名本身超出长度限制的行除外
+                assert!(
+                    domains <= max_domains.unwrap_or(usize::MAX)
+                        && (domains == 1 || line.len() <= max_length.unwrap_or(usize::MAX)),
+                    "{:?}",
+                    line
+                );
+            }
+
+            // 拆分不丢失域名，也不改变顺序
+            let section = parse_managed_section(&section, LAST_UPDATE_FORMAT);
+            assert_eq!(section.domains().collect::<Vec<_>>(), domains);
+        }
+
+        // 行尾注释单独成行放在拆分出的各行之前
+        let mut entries = String::new();
+        let mut config = Config::for_test(r#"{ "hosts_sources": [] }"#);
+        config.max_domains_per_line = Some(1);
+        push_entry_line(&mut entries, "0.0.0.0 a.com b.com # 广告", &config);
+        assert_eq!(entries, "# 广告\n0.0.0.0 a.com\n0.0.0.0 b.com\n");
+    }
+
+    #[test]
+    fn test_build_auto_section_minimize() {
+        let config = Config::for_test(r#"{ "hosts_sources": [], "minimize": true }"#);
+        let sources = [
+            SourceResult::for_test(
+                "https://a.example/hosts",
+                "# 注释\n\n0.0.0.0 a.com # 广告\n0.0.0.0  b.com\n",
+            ),
+            SourceResult::for_test("https://b.example/hosts", "# 仅注释\n"),
+            SourceResult::for_test("https://c.example/hosts", "0.0.0.0 c.com"),
+        ];
+
+        let (section, entries) =
+            render_auto_section(&sources, "2024-01-15 10:30:00", &config).unwrap();
+        assert_eq!(entries, "0.0.0.0 a.com\n0.0.0.0 b.com\n0.0.0.0 c.com\n");
+        assert_eq!(section, format!("{}\n{}{}\n", START_MARKER, entries, END_MARKER));
+    }
+
+    #[test]
+    fn test_build_auto_section_normalize_whitespace() {
+        let sources = vec![SourceResult::for_test(
+            "https://a.example",
+            "0.0.0.0\ta.com  \n127.0.0.1   b.com\tc.com\n",
+        )];
+
+        let config = Config::for_test(r#"{ "hosts_sources": [] }"#);
+        let section = render_section(&sources, &config);
+        assert!(section.contains("0.0.0.0 a.com\n127.0.0.1 b.com c.com\n"));
+
+        let config = Config::for_test(r#"{ "hosts_sources": [], "normalize_whitespace": false }"#);
+        let section = render_section(&sources, &config);
+        assert!(section.contains("0.0.0.0\ta.com  \n127.0.0.1   b.com\tc.com\n"));
+    }
+
+    #[test]
+    fn test_verify_auto_section() {
+        let sources = vec![SourceResult::for_test("https://a.example", "127.0.0.1 a.com\n")];
+        let config = Config::for_test(r#"{ "hosts_sources": [] }"#);
+        let section = render_section(&sources, &config);
+        assert!(verify_auto_section(&section).is_ok());
+
+        let broken = section.replace("127.0.0.1 a.com", "127.0.0.1a.com");
+        assert!(verify_auto_section(&broken).is_err());
+    }
+
+    #[test]
+    fn test_resolve_backup_path_rejects_hosts_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let hosts_path = dir.path().join("hosts");
+        fs::write(&hosts_path, "127.0.0.1 localhost\n").unwrap();
+
+        let backup_path = Some(hosts_path.to_string_lossy().to_string());
+        assert!(resolve_backup_path(&backup_path, &hosts_path, "20240115_103000").is_err());
+
+        let backup_dir = Some(dir.path().to_string_lossy().to_string());
+        assert!(resolve_backup_path(&backup_dir, &hosts_path, "20240115_103000").is_err());
+    }
+
+    #[test]
+    fn test_resolve_backup_path_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let hosts_path = dir.path().join("etc").join("hosts");
+        let backup_dir = dir.path().join("backups");
+        fs::create_dir_all(&backup_dir).unwrap();
+
+        let backup_path = Some(backup_dir.to_string_lossy().to_string());
+        let resolved = resolve_backup_path(&backup_path, &hosts_path, "20240115_103000").unwrap();
+        assert_eq!(resolved, backup_dir.join("hosts.backup.20240115_103000"));
+
+        let new_dir = format!("{}/", dir.path().join("new_backups").to_string_lossy());
+        let resolved =
+            resolve_backup_path(&Some(new_dir), &hosts_path, "20240115_103000").unwrap();
+        assert!(resolved.ends_with("new_backups/hosts.backup.20240115_103000"));
+        assert!(dir.path().join("new_backups").is_dir());
+    }
+
+    #[test]
+    fn test_resolve_backup_path_existing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let hosts_path = dir.path().join("hosts");
+
+        let previous_backup = dir.path().join("hosts.backup");
+        fs::write(&previous_backup, "# 备份\n127.0.0.1 localhost\n").unwrap();
+        let backup_path = Some(previous_backup.to_string_lossy().to_string());
+        assert!(resolve_backup_path(&backup_path, &hosts_path, "20240115_103000").is_ok());
+
+        let important = dir.path().join("config.toml");
+        fs::write(&important, "hosts_sources = []\n").unwrap();
+        let backup_path = Some(important.to_string_lossy().to_string());
+        assert!(resolve_backup_path(&backup_path, &hosts_path, "20240115_103000").is_err());
+    }
+
+    #[test]
+    fn test_check_symlink_regular_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hosts");
+        fs::write(&path, "127.0.0.1 localhost\n").unwrap();
+
+        assert!(check_symlink(&path, false).is_ok());
+        assert!(check_symlink(&dir.path().join("missing"), false).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_symlink_refuses_link() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("real_hosts");
+        let link = dir.path().join("hosts");
+        fs::write(&target, "127.0.0.1 localhost\n").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        assert!(check_symlink(&link, false).is_err());
+        assert!(check_symlink(&link, true).is_ok());
+    }
 }
@@ -0,0 +1,530 @@
+//! 国际化模块
+//!
+//! 面向用户的提示文本（命令行输出、警告、校验错误等）统一通过 [`Msg`] 查表，
+//! 按配置中的 `locale` 输出中文或英文。tracing 日志保持中文。
+
+use crate::config::Locale;
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// 当前语言（`Locale` 的判别值）
+static LOCALE: AtomicU8 = AtomicU8::new(Locale::Zh as u8);
+
+/// 设置当前语言
+pub fn set_locale(locale: Locale) {
+    LOCALE.store(locale as u8, Ordering::Relaxed);
+}
+
+/// 获取当前语言
+pub fn locale() -> Locale {
+    match LOCALE.load(Ordering::Relaxed) {
+        v if v == Locale::En as u8 => Locale::En,
+        _ => Locale::Zh,
+    }
+}
+
+/// 面向用户的提示文本
+#[derive(Debug, Clone, Copy)]
+pub enum Msg<'a> {
+    /// 需要管理员权限（Windows）
+    NeedAdmin,
+    /// 以管理员身份运行的提示（Windows）
+    RunAsAdminHint,
+    /// 需要 root 权限
+    NeedRoot,
+    /// 使用 sudo 运行的提示
+    RunWithSudo(&'a str),
+    /// 已启用 tls_insecure
+    TlsInsecure,
+    /// 未找到配置文件
+    ConfigNotFound,
+    /// 询问是否生成示例配置
+    GenerateConfigPrompt(&'a str),
+    /// 生成示例配置后提示编辑数据源
+    EditGeneratedConfig(&'a str),
+    /// 未找到配置文件时的解决方法提示
+    MissingConfigHint,
+    /// 已添加数据源
+    SourceAdded { url: &'a str, path: &'a str },
+    /// 数据源已存在
+    SourceExists { url: &'a str, path: &'a str },
+    /// 已移除数据源
+    SourceRemoved { url: &'a str, path: &'a str },
+    /// 配置中不存在该数据源
+    SourceNotInConfig { url: &'a str, path: &'a str },
+    /// 未使用配置文件，按默认配置检查
+    NoConfigUsingDefaults(&'a str),
+    /// 数据源返回内容为空
+    EmptyContent(&'a str),
+    /// 数据源检查结果概要
+    TestSourceSummary {
+        url: &'a str,
+        status: u16,
+        lines: usize,
+        entries: usize,
+        problems: usize,
+    },
+    /// 数据源存在格式问题
+    SourceHasProblems { count: usize, url: &'a str },
+    /// 行号前缀，与原因拼接
+    LineError { line_num: usize, reason: &'a str },
+    /// 带来源的行错误
+    LineErrorWithSource {
+        line_num: usize,
+        reason: &'a str,
+        url: &'a str,
+    },
+    /// 缺少 IP 或域名
+    MissingIpOrDomain(&'a str),
+    /// IP 地址格式无效
+    InvalidIp(&'a str),
+    /// 域名格式无效
+    InvalidDomain(&'a str),
+    /// 包含非法控制字符
+    ControlChar(usize),
+    /// 上次写入的条目数
+    LastEntryCount(Option<usize>),
+    /// 数据源状态概况
+    SourcesSummary { total: usize, failed: usize },
+    /// 单个标签下的数据源数量
+    TagSummary {
+        tag: &'a str,
+        total: usize,
+        disabled: usize,
+        failed: usize,
+    },
+    /// 暂无数据源状态记录
+    NoSourceStatus,
+    /// hosts 文件最后更新时间
+    HostsLastUpdate {
+        last_update: &'a str,
+        age_hours: i64,
+    },
+    /// hosts 文件中没有更新时间
+    HostsNoLastUpdate,
+    /// hosts 文件长时间未更新
+    HostsStale {
+        last_update: &'a str,
+        age_hours: i64,
+        threshold_hours: u64,
+    },
+    /// 两个备份的自动管理区域相同
+    BackupsIdentical,
+    /// 新增域名数量
+    DomainsAdded(usize),
+    /// 移除域名数量
+    DomainsRemoved(usize),
+    /// 备份数量不足以对比
+    NotEnoughBackups(usize),
+    /// 备份目录中没有备份
+    NoBackups,
+    /// 已暂停定时更新（暂停标记文件路径）
+    UpdatesPaused(&'a str),
+    /// 定时更新已处于暂停状态
+    AlreadyPaused,
+    /// 已恢复定时更新
+    UpdatesResumed,
+    /// 定时更新未暂停
+    NotPaused,
+    /// 试运行检查：数据源获取失败
+    CheckSourceFailed { url: &'a str, error: &'a str },
+    /// 试运行检查：本次更新失败
+    CheckUpdateFailed(&'a str),
+    /// 试运行检查未发现问题
+    CheckPassed,
+    /// 试运行检查发现错误
+    CheckFailed { errors: usize, warnings: usize },
+    /// 部分数据源获取失败（`--error-on-partial`）
+    PartialFailure(&'a str),
+    /// 系统 hosts 文件检查结果汇总
+    LintSummary {
+        path: &'a str,
+        lines: usize,
+        problems: usize,
+    },
+    /// 系统 hosts 文件存在格式问题
+    HostsHasProblems { count: usize, path: &'a str },
+    /// 域名查找命中的条目
+    LookupMatch { ip: &'a str, source: Option<&'a str> },
+    /// 域名命中的域名规则
+    LookupSinkRule { pattern: &'a str, ip: &'a str },
+    /// 没有找到域名的条目
+    DomainNotFound { domain: &'a str, refresh: bool },
+    /// 有新版本可用
+    UpdateAvailable { current: &'a str, latest: &'a str },
+    /// 当前已是最新版本
+    UpToDate(&'a str),
+}
+
+impl Msg<'_> {
+    /// 按指定语言生成文本
+    pub fn localize(&self, locale: Locale) -> String {
+        struct InLocale<'a, 'b>(&'b Msg<'a>, bool);
+
+        impl fmt::Display for InLocale<'_, '_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.write_to(f, self.1)
+            }
+        }
+
+        InLocale(self, locale == Locale::En).to_string()
+    }
+
+    /// 写出文本，`en` 为 `true` 时使用英文
+    fn write_to(&self, f: &mut fmt::Formatter<'_>, en: bool) -> fmt::Result {
+        match *self {
+            Msg::NeedAdmin if en => {
+                write!(
+                    f,
+                    "Warning: administrator privileges are required to modify the system hosts file"
+                )
+            }
+            Msg::NeedAdmin => write!(f, "警告: 程序需要管理员权限才能修改系统 hosts 文件"),
+            Msg::RunAsAdminHint if en => {
+                write!(
+                    f,
+                    "Right-click the program and choose 'Run as administrator'"
+                )
+            }
+            Msg::RunAsAdminHint => write!(f, "请右键点击程序，选择 '以管理员身份运行'"),
+            Msg::NeedRoot if en => {
+                write!(
+                    f,
+                    "Warning: root privileges are required to modify the system hosts file"
+                )
+            }
+            Msg::NeedRoot => write!(f, "警告: 程序需要 root 权限才能修改系统 hosts 文件"),
+            Msg::RunWithSudo(exe) if en => write!(f, "Please run with sudo: sudo {}", exe),
+            Msg::RunWithSudo(exe) => write!(f, "请使用 sudo 运行: sudo {}", exe),
+            Msg::TlsInsecure if en => write!(
+                f,
+                "⚠️ tls_insecure is enabled: TLS certificates of sources will NOT be verified, \
+                 man-in-the-middle attacks are possible!"
+            ),
+            Msg::TlsInsecure => write!(
+                f,
+                "⚠️ 已启用 tls_insecure：将不校验数据源的 TLS 证书，存在中间人攻击风险！"
+            ),
+            Msg::ConfigNotFound if en => write!(f, "No config file found"),
+            Msg::ConfigNotFound => write!(f, "未找到配置文件"),
+            Msg::GenerateConfigPrompt(path) if en => {
+                write!(f, "No config file found. Generate a sample config at {}? [y/N]", path)
+            }
+            Msg::GenerateConfigPrompt(path) => {
+                write!(f, "未找到配置文件，是否在 {} 生成示例配置？[y/N]", path)
+            }
+            Msg::EditGeneratedConfig(path) if en => {
+                write!(f, "Edit hosts_sources in {} and run again", path)
+            }
+            Msg::EditGeneratedConfig(path) => {
+                write!(f, "请修改 {} 中的 hosts_sources 后重新运行", path)
+            }
+            Msg::MissingConfigHint if en => write!(
+                f,
+                "Run `hosts_updater_rs init -o config.toml` to generate a sample config, \
+                 or pass sources directly with `run --source <URL>`"
+            ),
+            Msg::MissingConfigHint => write!(
+                f,
+                "可运行 `hosts_updater_rs init -o config.toml` 生成示例配置，\
+                 或通过 `run --source <URL>` 直接指定数据源"
+            ),
+            Msg::SourceAdded { url, path } if en => write!(f, "Added source: {} ({})", url, path),
+            Msg::SourceAdded { url, path } => write!(f, "已添加数据源: {} ({})", url, path),
+            Msg::SourceExists { url, path } if en => {
+                write!(f, "Source already exists: {} ({})", url, path)
+            }
+            Msg::SourceExists { url, path } => write!(f, "数据源已存在: {} ({})", url, path),
+            Msg::SourceRemoved { url, path } if en => {
+                write!(f, "Removed source: {} ({})", url, path)
+            }
+            Msg::SourceRemoved { url, path } => write!(f, "已移除数据源: {} ({})", url, path),
+            Msg::SourceNotInConfig { url, path } if en => {
+                write!(f, "Source not found in config: {} ({})", url, path)
+            }
+            Msg::SourceNotInConfig { url, path } => {
+                write!(f, "配置中不存在该数据源: {} ({})", url, path)
+            }
+            Msg::NoConfigUsingDefaults(reason) if en => {
+                write!(
+                    f,
+                    "No config file used ({}), checking with defaults",
+                    reason
+                )
+            }
+            Msg::NoConfigUsingDefaults(reason) => {
+                write!(f, "未使用配置文件（{}），按默认配置检查", reason)
+            }
+            Msg::EmptyContent(url) if en => write!(f, "Source returned empty content: {}", url),
+            Msg::EmptyContent(url) => write!(f, "URL 返回内容为空: {}", url),
+            Msg::TestSourceSummary {
+                url,
+                status,
+                lines,
+                entries,
+                problems,
+            } if en => write!(
+                f,
+                "{}: status {}, {} lines, {} valid entries, {} problems",
+                url, status, lines, entries, problems
+            ),
+            Msg::TestSourceSummary {
+                url,
+                status,
+                lines,
+                entries,
+                problems,
+            } => write!(
+                f,
+                "{}: 状态 {}，共 {} 行，{} 条有效条目，{} 个问题",
+                url, status, lines, entries, problems
+            ),
+            Msg::SourceHasProblems { count, url } if en => {
+                write!(f, "Source has {} format problems: {}", count, url)
+            }
+            Msg::SourceHasProblems { count, url } => {
+                write!(f, "数据源存在 {} 个格式问题: {}", count, url)
+            }
+            Msg::LineError { line_num, reason } if en => {
+                write!(f, "line {}: {}", line_num, reason)
+            }
+            Msg::LineError { line_num, reason } => write!(f, "第 {} 行{}", line_num, reason),
+            Msg::LineErrorWithSource {
+                line_num,
+                reason,
+                url,
+            } if en => write!(f, "line {}: {} (source: {})", line_num, reason, url),
+            Msg::LineErrorWithSource {
+                line_num,
+                reason,
+                url,
+            } => write!(f, "第 {} 行{} (来源: {})", line_num, reason, url),
+            Msg::MissingIpOrDomain(line) if en => {
+                write!(f, "invalid format, missing IP or domain: {}", line)
+            }
+            Msg::MissingIpOrDomain(line) => write!(f, "格式无效，缺少 IP 或域名: {}", line),
+            Msg::InvalidIp(ip) if en => write!(f, "invalid IP address: {}", ip),
+            Msg::InvalidIp(ip) => write!(f, "IP 地址格式无效: {}", ip),
+            Msg::InvalidDomain(domain) if en => write!(f, "invalid domain: {}", domain),
+            Msg::InvalidDomain(domain) => write!(f, "域名格式无效: {}", domain),
+            Msg::ControlChar(pos) if en => {
+                write!(f, "contains illegal control character (position {})", pos)
+            }
+            Msg::ControlChar(pos) => write!(f, "包含非法控制字符 (位置 {})", pos),
+            Msg::LastEntryCount(count) => {
+                let count = count.map_or_else(|| "-".to_string(), |c| c.to_string());
+                if en {
+                    write!(f, "Entries written last time: {}", count)
+                } else {
+                    write!(f, "上次写入条目数: {}", count)
+                }
+            }
+            Msg::SourcesSummary { total, failed } if en => {
+                write!(f, "Sources: {}, {} failed on the last fetch", total, failed)
+            }
+            Msg::SourcesSummary { total, failed } => {
+                write!(f, "数据源: {} 个，其中 {} 个最近获取失败", total, failed)
+            }
+            Msg::TagSummary {
+                tag,
+                total,
+                disabled,
+                failed,
+            } if en => write!(
+                f,
+                "  tag {}: {} sources, {} disabled, {} failed on the last fetch",
+                tag, total, disabled, failed
+            ),
+            Msg::TagSummary {
+                tag,
+                total,
+                disabled,
+                failed,
+            } => write!(
+                f,
+                "  标签 {}: {} 个数据源，已停用 {} 个，最近获取失败 {} 个",
+                tag, total, disabled, failed
+            ),
+            Msg::NoSourceStatus if en => write!(f, "No source status recorded yet"),
+            Msg::NoSourceStatus => write!(f, "暂无数据源状态记录"),
+            Msg::HostsLastUpdate {
+                last_update,
+                age_hours,
+            } if en => write!(
+                f,
+                "Hosts last updated: {} ({} hours ago)",
+                last_update, age_hours
+            ),
+            Msg::HostsLastUpdate {
+                last_update,
+                age_hours,
+            } => write!(f, "hosts 最后更新: {}（{} 小时前）", last_update, age_hours),
+            Msg::HostsNoLastUpdate if en => {
+                write!(
+                    f,
+                    "No managed section with an update time found in the hosts file"
+                )
+            }
+            Msg::HostsNoLastUpdate => write!(f, "hosts 文件中没有带更新时间的自动管理区域"),
+            Msg::HostsStale {
+                last_update,
+                age_hours,
+                threshold_hours,
+            } if en => write!(
+                f,
+                "⚠️ hosts file has not been updated for {} hours (last updated: {}, threshold: {} hours), \
+                 the updater may have stalled",
+                age_hours, last_update, threshold_hours
+            ),
+            Msg::HostsStale {
+                last_update,
+                age_hours,
+                threshold_hours,
+            } => write!(
+                f,
+                "⚠️ hosts 文件已 {} 小时未更新（最后更新: {}，阈值: {} 小时），更新进程可能已停止",
+                age_hours, last_update, threshold_hours
+            ),
+            Msg::BackupsIdentical if en => {
+                write!(f, "The managed sections of the two backups are identical")
+            }
+            Msg::BackupsIdentical => write!(f, "两个备份的自动管理区域相同"),
+            Msg::DomainsAdded(count) if en => write!(f, "Added domains: {}", count),
+            Msg::DomainsAdded(count) => write!(f, "新增域名: {} 个", count),
+            Msg::DomainsRemoved(count) if en => write!(f, "Removed domains: {}", count),
+            Msg::DomainsRemoved(count) => write!(f, "移除域名: {} 个", count),
+            Msg::NotEnoughBackups(found) if en => write!(
+                f,
+                "At least 2 timestamped backups are needed to compare, found {}",
+                found
+            ),
+            Msg::NotEnoughBackups(found) => {
+                write!(f, "至少需要 2 个带时间戳的备份才能对比，当前找到 {} 个", found)
+            }
+            Msg::NoBackups if en => write!(f, "No timestamped backups found"),
+            Msg::NoBackups => write!(f, "没有找到带时间戳的备份"),
+            Msg::UpdatesPaused(path) if en => {
+                write!(f, "Scheduled updates paused (marker file: {})", path)
+            }
+            Msg::UpdatesPaused(path) => write!(f, "已暂停定时更新（标记文件: {}）", path),
+            Msg::AlreadyPaused if en => write!(f, "Scheduled updates are already paused"),
+            Msg::AlreadyPaused => write!(f, "定时更新已处于暂停状态"),
+            Msg::UpdatesResumed if en => write!(f, "Scheduled updates resumed"),
+            Msg::UpdatesResumed => write!(f, "已恢复定时更新"),
+            Msg::NotPaused if en => write!(f, "Scheduled updates are not paused"),
+            Msg::NotPaused => write!(f, "定时更新未暂停"),
+            Msg::CheckSourceFailed { url, error } if en => {
+                write!(f, "Failed to fetch source {}: {}", url, error)
+            }
+            Msg::CheckSourceFailed { url, error } => write!(f, "数据源获取失败 {}: {}", url, error),
+            Msg::CheckUpdateFailed(error) if en => write!(f, "Update failed: {}", error),
+            Msg::CheckUpdateFailed(error) => write!(f, "更新失败: {}", error),
+            Msg::CheckPassed if en => write!(f, "Check passed: no problems found"),
+            Msg::CheckPassed => write!(f, "检查通过，未发现问题"),
+            Msg::CheckFailed { errors, warnings } if en => {
+                write!(f, "Check found {} errors and {} warnings", errors, warnings)
+            }
+            Msg::CheckFailed { errors, warnings } => {
+                write!(f, "检查发现 {} 个错误、{} 个警告", errors, warnings)
+            }
+            Msg::PartialFailure(urls) if en => {
+                write!(f, "Some sources failed and were skipped in this update: {}", urls)
+            }
+            Msg::PartialFailure(urls) => write!(f, "本次更新中以下数据源获取失败并被跳过: {}", urls),
+            Msg::LintSummary {
+                path,
+                lines,
+                problems,
+            } if en => write!(f, "{}: {} lines, {} problems", path, lines, problems),
+            Msg::LintSummary {
+                path,
+                lines,
+                problems,
+            } => write!(f, "{}: 共 {} 行，{} 个问题", path, lines, problems),
+            Msg::HostsHasProblems { count, path } if en => {
+                write!(f, "Hosts file has {} format problems: {}", count, path)
+            }
+            Msg::HostsHasProblems { count, path } => {
+                write!(f, "hosts 文件存在 {} 个格式问题: {}", count, path)
+            }
+            Msg::LookupMatch { ip, source } if en => {
+                write!(f, "{}  (source: {})", ip, source.unwrap_or("unknown"))
+            }
+            Msg::LookupMatch { ip, source } => {
+                write!(f, "{}  （数据源: {}）", ip, source.unwrap_or("未知"))
+            }
+            Msg::LookupSinkRule { pattern, ip } if en => {
+                write!(f, "Matches sink rule {} -> {}", pattern, ip)
+            }
+            Msg::LookupSinkRule { pattern, ip } => {
+                write!(f, "匹配域名规则 {} → {}", pattern, ip)
+            }
+            Msg::DomainNotFound { domain, refresh: true } if en => {
+                write!(f, "{} was not found in the fetched sources", domain)
+            }
+            Msg::DomainNotFound { domain, refresh: false } if en => {
+                write!(f, "{} was not found in the managed section of the hosts file", domain)
+            }
+            Msg::DomainNotFound { domain, refresh: true } => {
+                write!(f, "重新获取的数据源中没有 {} 的条目", domain)
+            }
+            Msg::DomainNotFound { domain, refresh: false } => {
+                write!(f, "hosts 文件的自动管理区域中没有 {} 的条目", domain)
+            }
+            Msg::UpdateAvailable { current, latest } if en => {
+                write!(f, "A newer version is available: {} (current {})", latest, current)
+            }
+            Msg::UpdateAvailable { current, latest } => {
+                write!(f, "有新版本可用: {}（当前 {}）", latest, current)
+            }
+            Msg::UpToDate(current) if en => write!(f, "Already up to date: {}", current),
+            Msg::UpToDate(current) => write!(f, "当前已是最新版本: {}", current),
+        }
+    }
+}
+
+impl fmt::Display for Msg<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_to(f, locale() == Locale::En)
+    }
+}
+
+/// 数据源状态表格的表头
+pub fn sources_table_header(locale: Locale) -> [&'static str; 5] {
+    match locale {
+        Locale::Zh => ["数据源", "最后获取", "状态", "字节数", "条目数"],
+        Locale::En => ["Source", "Last fetched", "Status", "Bytes", "Entries"],
+    }
+}
+
+/// 备份列表表格的表头
+pub fn backups_table_header(locale: Locale) -> [&'static str; 4] {
+    match locale {
+        Locale::Zh => ["备份文件", "备份时间", "字节数", "gzip 压缩"],
+        Locale::En => ["Backup", "Time", "Bytes", "Gzip"],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_messages_follow_locale() {
+        let msg = Msg::InvalidIp("999.0.0.1");
+        assert_eq!(msg.localize(Locale::En), "invalid IP address: 999.0.0.1");
+        assert_eq!(msg.localize(Locale::Zh), "IP 地址格式无效: 999.0.0.1");
+
+        let line = Msg::LineError {
+            line_num: 3,
+            reason: &msg.localize(Locale::En),
+        };
+        assert_eq!(
+            line.localize(Locale::En),
+            "line 3: invalid IP address: 999.0.0.1"
+        );
+
+        assert_eq!(sources_table_header(Locale::En)[0], "Source");
+        assert_eq!(sources_table_header(Locale::Zh)[0], "数据源");
+    }
+}
@@ -0,0 +1,34 @@
+//! hosts_updater_rs 库
+//!
+//! 提供配置加载、hosts 内容获取与验证、hosts 文件管理等功能，
+//! 供命令行程序及其他应用复用。
+
+pub mod allowlist;
+pub mod backup_diff;
+pub mod cache;
+pub mod circuit;
+pub mod change_log;
+pub mod config;
+pub mod config_edit;
+pub mod conflict;
+pub mod dns;
+pub mod export;
+pub mod fetcher;
+pub mod git;
+pub mod hosts;
+pub mod i18n;
+pub mod log_limit;
+pub mod metered;
+pub mod metrics;
+pub mod paths;
+pub mod preprocess;
+pub mod public_suffix;
+pub mod release;
+pub mod remote_config;
+pub mod report;
+pub mod scheduler;
+#[cfg(feature = "serve")]
+pub mod server;
+pub mod state;
+pub mod update;
+pub mod validation;
@@ -0,0 +1,152 @@
+//! 日志限流模块
+//!
+//! 数据源持续不可用时，每个更新周期都会产生相同的错误。同一键（如数据源 URL）的相同错误
+//! 在一个时间窗口内只输出一次，之后每个窗口输出一条“仍在失败”的汇总；错误内容变化时立即输出，
+//! 恢复时输出一条恢复日志。
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+/// 进程内共享的失败记录
+static FAILURE_LOG: LazyLock<Mutex<FailureLog>> = LazyLock::new(Default::default);
+
+/// 对一次失败的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogAction {
+    /// 首次出现（或内容变化）的错误，正常输出
+    Log,
+    /// 窗口内已输出过相同错误，不输出
+    Skip,
+    /// 窗口已过，输出汇总，附带连续失败次数
+    Summary(u64),
+}
+
+/// 单个键的失败记录
+#[derive(Debug)]
+struct FailureEntry {
+    message: String,
+    attempts: u64,
+    last_logged: Instant,
+}
+
+/// 按键记录连续失败的情况
+#[derive(Debug, Default)]
+pub struct FailureLog {
+    entries: HashMap<String, FailureEntry>,
+}
+
+impl FailureLog {
+    /// 记录一次失败并决定是否输出
+    ///
+    /// `window` 为零时不限流，每次都正常输出。
+    pub fn record(
+        &mut self,
+        key: &str,
+        message: &str,
+        window: Duration,
+        now: Instant,
+    ) -> LogAction {
+        match self.entries.get_mut(key) {
+            Some(entry) if entry.message == message => {
+                entry.attempts += 1;
+                if window.is_zero() {
+                    LogAction::Log
+                } else if now.duration_since(entry.last_logged) >= window {
+                    entry.last_logged = now;
+                    LogAction::Summary(entry.attempts)
+                } else {
+                    LogAction::Skip
+                }
+            }
+            _ => {
+                self.entries.insert(
+                    key.to_string(),
+                    FailureEntry {
+                        message: message.to_string(),
+                        attempts: 1,
+                        last_logged: now,
+                    },
+                );
+                LogAction::Log
+            }
+        }
+    }
+
+    /// 清除失败记录，返回恢复前的连续失败次数
+    pub fn recover(&mut self, key: &str) -> Option<u64> {
+        self.entries.remove(key).map(|entry| entry.attempts)
+    }
+}
+
+/// 输出限流后的失败日志
+pub fn log_failure(key: &str, message: &str, window: Duration) {
+    let action = FAILURE_LOG
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .record(key, message, window, Instant::now());
+
+    match action {
+        LogAction::Log => tracing::error!("{}: {}", key, message),
+        LogAction::Skip => tracing::debug!("{}: {}（重复错误，已限流）", key, message),
+        LogAction::Summary(attempts) => {
+            tracing::error!("{}: 已连续失败 {} 次，仍在失败: {}", key, attempts, message)
+        }
+    }
+}
+
+/// 记录成功，之前处于失败状态时输出恢复日志
+pub fn log_recovery(key: &str) {
+    let attempts = FAILURE_LOG
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .recover(key);
+
+    if let Some(attempts) = attempts {
+        tracing::info!("{}: 已恢复（此前连续失败 {} 次）", key, attempts);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failure_log_rate_limit() {
+        let mut log = FailureLog::default();
+        let window = Duration::from_secs(3600);
+        let start = Instant::now();
+        let url = "https://a.example/hosts";
+
+        assert_eq!(log.record(url, "404", window, start), LogAction::Log);
+        assert_eq!(
+            log.record(url, "404", window, start + Duration::from_secs(60)),
+            LogAction::Skip
+        );
+        assert_eq!(
+            log.record(url, "404", window, start + window),
+            LogAction::Summary(3)
+        );
+        assert_eq!(
+            log.record(url, "404", window, start + window + Duration::from_secs(60)),
+            LogAction::Skip
+        );
+
+        // 错误内容变化时立即输出，其他键互不影响
+        assert_eq!(
+            log.record(url, "timeout", window, start + window),
+            LogAction::Log
+        );
+        assert_eq!(log.record("other", "404", window, start), LogAction::Log);
+
+        assert_eq!(log.recover(url), Some(1));
+        assert_eq!(log.recover(url), None);
+        assert_eq!(log.record(url, "timeout", window, start), LogAction::Log);
+
+        // 窗口为零时不限流
+        assert_eq!(
+            log.record(url, "timeout", Duration::ZERO, start),
+            LogAction::Log
+        );
+    }
+}
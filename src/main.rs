@@ -3,103 +3,1269 @@
 //! 一个用 Rust 编写的 Hosts 文件自动更新工具，定时从配置源获取 hosts 规则
 //! 并写入系统 hosts 文件，帮助实现域名访问加速。
 
-mod config;
-mod fetcher;
-mod hosts;
-mod scheduler;
-
 use anyhow::{Context, Result};
-use config::{load_config, validate_config, Config};
-use fetcher::fetch_all_hosts;
-use hosts::{
-    backup_hosts, check_admin_permission, get_hosts_path, read_hosts_content, write_hosts,
+use clap::{Parser, Subcommand};
+use hosts_updater_rs::backup_diff::diff_backups;
+use hosts_updater_rs::change_log::DomainChanges;
+use hosts_updater_rs::config::{
+    find_config_file, load_config, load_config_from, load_merged_config, parse_interval_hours,
+    validate_config, Config, ConfigFormat, ConfigNotFound, STDIN_PATH,
+};
+use hosts_updater_rs::config_edit::{add_source, remove_source, set_source_pins};
+use hosts_updater_rs::export::{export_entries, DnsmasqMode, ExportFormat};
+use hosts_updater_rs::fetcher::{
+    fetch_all_hosts, fetch_raw, fetch_source_sha256, FailedSource, FetchProgress,
+};
+use hosts_updater_rs::hosts::{
+    backup_info, check_admin_permission, count_entries, get_hosts_path, lint_hosts_content,
+    list_backups, managed_hosts_path, parse_managed_section, read_hosts_content, read_last_update,
+    read_managed_content, render_backups_table, render_hosts, ManagedSection, ManagedSource,
+    LAST_UPDATE_FORMAT,
 };
-use scheduler::Scheduler;
+use hosts_updater_rs::i18n::{set_locale, Msg};
+use hosts_updater_rs::log_limit::{log_failure, log_recovery};
+use hosts_updater_rs::metered::is_metered;
+use hosts_updater_rs::metrics::{write_metrics, Metrics};
+use hosts_updater_rs::preprocess::{json_domains_to_hosts, preprocess_content, rule_matches};
+use hosts_updater_rs::remote_config::load_remote_config;
+use hosts_updater_rs::report::{render_findings, Finding, ReportFormat, Severity};
+use hosts_updater_rs::scheduler::Scheduler;
+use hosts_updater_rs::state::{
+    load_state, pause_file_path, render_sources_table, set_paused, FetchStatus, SourceStatus,
+};
+use hosts_updater_rs::update::{self, UpdateOptions, UpdateOutcome};
+use hosts_updater_rs::validation::validate_hosts_content_collect;
 use std::boxed::Box;
+use std::collections::BTreeMap;
 use std::future::Future;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
-use tracing::{error, info, warn};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tracing::level_filters::LevelFilter;
+use tracing::{info, warn};
+use tokio_util::sync::CancellationToken;
+use tracing_subscriber::EnvFilter;
+
+/// `test-source` 默认的请求超时（秒）
+const TEST_SOURCE_TIMEOUT_SECS: u64 = 10;
+
+/// 命令行参数
+#[derive(Debug, Parser)]
+#[command(version, about = "Hosts 文件自动更新工具")]
+struct Cli {
+    /// 跳过条目数量异常下降保护，强制写入
+    #[arg(long, global = true)]
+    force: bool,
+
+    /// 单次更新（--dry-run 或 run --if-stale）中有数据源获取失败并被跳过（fail_fast = false）时，
+    /// 即使其余数据源已写入也以非零状态退出；只能用于这两种单次更新
+    #[arg(long, global = true)]
+    error_on_partial: bool,
+
+    /// 仅执行一次更新并输出结果，不写入 hosts 文件（生成的内容同样经过自检）
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// 与 --dry-run 一起使用：检查 hosts 文件（指定 run --output 时为该文件）是否过期、数据源是否获取失败，
+    /// 只输出发现的问题，有错误时以非零状态退出
+    #[arg(long, global = true, requires = "dry_run")]
+    check: bool,
+
+    /// --check 的输出格式（text、github），github 输出 GitHub Actions 注解
+    #[arg(long, global = true, value_name = "FORMAT", requires = "check")]
+    check_format: Option<ReportFormat>,
+
+    /// 本次运行强制备份 hosts 文件（覆盖配置）
+    #[arg(long, global = true, conflicts_with = "no_backup")]
+    backup: bool,
+
+    /// 本次运行不备份 hosts 文件（覆盖配置）
+    #[arg(long, global = true)]
+    no_backup: bool,
+
+    /// 启动内置 HTTP 服务，在 /hosts 提供生成的 hosts 条目
+    #[cfg(feature = "serve")]
+    #[arg(long, global = true, conflicts_with = "dry_run")]
+    serve: bool,
+
+    /// 分层合并所有找到的配置文件（系统 → 用户 → 当前目录）
+    #[arg(long, global = true)]
+    merge_configs: bool,
+
+    /// 指定配置文件路径，`-` 表示从标准输入读取
+    #[arg(long, global = true, value_name = "PATH", conflicts_with = "merge_configs")]
+    config: Option<String>,
+
+    /// 配置格式（json、toml、yaml）：指定 --config 时默认按扩展名判断，从标准输入读取时依次尝试；
+    /// 未指定 --config 时只查找该格式的配置文件
+    #[arg(long, global = true, value_name = "FORMAT")]
+    config_format: Option<ConfigFormat>,
+
+    /// 从远程 URL 下载配置（优先于本地配置文件），不可达时使用本地缓存的上次配置
+    #[arg(
+        long,
+        global = true,
+        value_name = "URL",
+        conflicts_with_all = ["config", "merge_configs"]
+    )]
+    remote_config: Option<String>,
+
+    /// 远程配置内容的 SHA-256 固定值，不一致时拒绝使用
+    #[arg(long, global = true, value_name = "HEX", requires = "remote_config")]
+    remote_config_sha256: Option<String>,
+
+    /// 选用配置文件 `profiles` 中的命名配置档，合并到共享配置之上
+    #[arg(long, global = true, value_name = "NAME", env = "HOSTS_UPDATER_PROFILE")]
+    profile: Option<String>,
+
+    /// 停用带有该标签的数据源（追加到配置的 disabled_tags），可重复指定
+    #[arg(long = "disable-tag", global = true, value_name = "TAG")]
+    disable_tags: Vec<String>,
+
+    /// 启用带有该标签的数据源（从配置的 disabled_tags 中移除），可重复指定，优先于 --disable-tag
+    #[arg(long = "enable-tag", global = true, value_name = "TAG")]
+    enable_tags: Vec<String>,
+
+    /// 每次更新后输出各数据源的状态表格
+    #[arg(long, global = true)]
+    print_sources_status: bool,
+
+    /// 获取各数据源的当前内容，将其 SHA-256 固定值写入配置文件后退出
+    #[arg(long, conflicts_with = "dry_run")]
+    update_pins: bool,
+
+    /// 仅输出错误日志
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// 输出更详细的日志（-v 为 debug，-vv 为 trace）
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// 子命令
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// 定时更新 hosts 文件（未指定子命令时的默认行为）
+    Run {
+        /// 数据源 URL，可重复指定；指定后不再读取配置文件
+        #[arg(long = "source", value_name = "URL")]
+        sources: Vec<String>,
+
+        /// 更新间隔（如 6h、1d），覆盖配置
+        #[arg(long, value_name = "INTERVAL", value_parser = parse_interval_arg)]
+        interval: Option<u64>,
+
+        /// 将更新后的完整 hosts 文件写入该路径，不修改系统 hosts 文件（无需管理员权限）
+        #[arg(long, value_name = "PATH")]
+        output: Option<std::path::PathBuf>,
+
+        /// 供 cron 频繁调用：自动管理区域的最后更新时间未超过该时长（如 6h、1d）时直接退出，
+        /// 否则只更新一次后退出
+        #[arg(
+            long,
+            value_name = "DURATION",
+            value_parser = parse_interval_arg,
+            conflicts_with = "output"
+        )]
+        if_stale: Option<u64>,
+
+        /// 获取数据源后只输出本次将新增到自动管理区域的域名（每行一个），不写入任何文件
+        #[arg(long, conflicts_with_all = ["output", "if_stale"])]
+        show_new: bool,
+    },
+    /// 获取并检查单个数据源，列出所有格式问题
+    TestSource {
+        /// 数据源 URL
+        url: String,
+        /// 请求超时（秒），默认比定时更新时更短，以便尽快得到结果
+        #[arg(long, value_name = "SECONDS", default_value_t = TEST_SOURCE_TIMEOUT_SECS)]
+        timeout: u64,
+    },
+    /// 查看运行状态
+    Status {
+        /// 以表格列出各数据源最近一次的获取状态
+        #[arg(long)]
+        sources: bool,
+    },
+    /// 生成带注释的示例配置
+    #[command(alias = "init")]
+    GenerateConfig {
+        /// 配置格式（json、toml、yaml）
+        #[arg(long, value_name = "FORMAT", default_value = "toml")]
+        format: ConfigFormat,
+
+        /// 写入的文件路径，未指定时输出到标准输出；文件已存在时拒绝覆盖
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<String>,
+    },
+    /// 获取所有数据源，将合并去重后的结果导出为其他工具使用的格式
+    Export {
+        /// 导出格式（hosts、dnsmasq、domains、adblock）
+        #[arg(long, value_name = "FORMAT", default_value = "hosts")]
+        format: ExportFormat,
+
+        /// dnsmasq 格式中屏蔽条目的写法：sink 解析到黑洞地址，nxdomain 返回 NXDOMAIN
+        #[arg(long, value_name = "MODE")]
+        dnsmasq_mode: Option<DnsmasqMode>,
+
+        /// 写入的文件路径，未指定时输出到标准输出；文件已存在时覆盖
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+    /// 检查系统 hosts 文件，列出所有格式有误的行
+    Lint,
+    /// 查找域名的条目，列出其 IP 与所属数据源
+    Lookup {
+        /// 要查找的域名
+        domain: String,
+
+        /// 在重新获取的数据源中查找，而不是系统 hosts 文件的自动管理区域
+        #[arg(long)]
+        refresh: bool,
+    },
+    /// 列出备份目录中的备份（从新到旧）
+    ListBackups,
+    /// 对比两个 hosts 备份的自动管理区域，列出新增与移除的域名
+    DiffBackups {
+        /// 较旧的备份文件
+        #[arg(required_unless_present = "latest", requires = "new")]
+        old: Option<PathBuf>,
+
+        /// 较新的备份文件
+        new: Option<PathBuf>,
+
+        /// 对比备份目录中最近的两个备份
+        #[arg(long, conflicts_with = "old")]
+        latest: bool,
+    },
+    /// 暂停定时更新：在缓存目录中创建暂停标记文件，运行中的定时任务随后跳过更新
+    Pause,
+    /// 恢复定时更新：删除暂停标记文件
+    Resume,
+    /// 查询项目发布页，提示是否有新版本（不会自动安装）
+    #[cfg(feature = "check-update")]
+    CheckUpdate,
+    /// 管理配置文件中的数据源
+    Source {
+        #[command(subcommand)]
+        action: SourceAction,
+    },
+}
+
+/// 数据源管理操作
+#[derive(Debug, Subcommand)]
+enum SourceAction {
+    /// 添加数据源
+    Add {
+        /// 数据源 URL
+        url: String,
+    },
+    /// 移除数据源
+    Remove {
+        /// 数据源 URL
+        url: String,
+    },
+    /// 列出当前生效的数据源
+    List,
+}
+
+impl Cli {
+    /// 将命令行参数中的覆盖项应用到配置
+    fn apply_overrides(&self, config: &mut Config) {
+        if self.backup {
+            config.backup_before_update = true;
+        } else if self.no_backup {
+            config.backup_before_update = false;
+        }
+
+        if let Some(Command::Run {
+            interval: Some(hours),
+            ..
+        }) = &self.command
+        {
+            config.update_interval_hours = *hours;
+        }
+    }
+
+    /// `run --source` 指定的数据源
+    fn sources(&self) -> &[String] {
+        match &self.command {
+            Some(Command::Run { sources, .. }) => sources,
+            _ => &[],
+        }
+    }
+
+    /// `run --output` 指定的输出路径
+    fn output(&self) -> Option<&std::path::Path> {
+        match &self.command {
+            Some(Command::Run { output, .. }) => output.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// `run --if-stale` 指定的最长时长（小时）
+    fn if_stale(&self) -> Option<u64> {
+        match &self.command {
+            Some(Command::Run { if_stale, .. }) => *if_stale,
+            _ => None,
+        }
+    }
+
+    /// 是否指定了 `run --show-new`
+    fn show_new(&self) -> bool {
+        matches!(&self.command, Some(Command::Run { show_new: true, .. }))
+    }
+
+    /// 根据 -q/-v 参数确定日志级别
+    fn log_level(&self) -> LevelFilter {
+        if self.quiet {
+            return LevelFilter::ERROR;
+        }
+
+        match self.verbose {
+            0 => LevelFilter::INFO,
+            1 => LevelFilter::DEBUG,
+            _ => LevelFilter::TRACE,
+        }
+    }
+}
+
+/// 解析 `--interval` 参数
+fn parse_interval_arg(value: &str) -> Result<u64, String> {
+    parse_interval_hours(value).map_err(|e| e.to_string())
+}
+
+/// 单次更新的运行选项
+#[derive(Debug, Clone, Default)]
+struct RunOptions {
+    /// 更新流程选项（缓存回退仅用于启动后首次更新）
+    update: UpdateOptions,
+    /// 更新后是否输出各数据源的状态表格
+    print_sources_status: bool,
+    /// 写入成功后向内置 HTTP 服务发布生成的条目
+    #[cfg(feature = "serve")]
+    served: Option<hosts_updater_rs::server::ServedHosts>,
+}
 
 /// 程序入口
 #[tokio::main]
 async fn main() -> Result<()> {
-    // 初始化日志
-    tracing_subscriber::fmt::init();
+    let cli = Cli::parse();
+
+    // 初始化日志（由命令行参数决定级别，覆盖 RUST_LOG）
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::new(cli.log_level().to_string()))
+        .init();
+
+    // 定时运行与其他子命令没有单次更新的退出状态，指定后不会生效
+    if cli.error_on_partial && !cli.dry_run && cli.if_stale().is_none() {
+        return Err(anyhow::anyhow!(
+            "--error-on-partial 只能与 --dry-run 或 run --if-stale 一起使用"
+        ));
+    }
+
+    match &cli.command {
+        Some(Command::Source { action }) => {
+            return run_source_command(action, &cli);
+        }
+        Some(Command::GenerateConfig { format, output }) => {
+            return run_generate_config(*format, output.as_deref());
+        }
+        Some(Command::Status { sources }) => {
+            return run_status(*sources, &cli);
+        }
+        Some(Command::TestSource { url, timeout }) => {
+            return tokio::task::block_in_place(|| run_test_source(url, *timeout, &cli));
+        }
+        Some(Command::Export {
+            format,
+            dnsmasq_mode,
+            output,
+        }) => {
+            let format = match (format, dnsmasq_mode) {
+                (ExportFormat::Dnsmasq(_), Some(mode)) => ExportFormat::Dnsmasq(*mode),
+                (_, Some(_)) => {
+                    return Err(anyhow::anyhow!("--dnsmasq-mode 只能用于 --format dnsmasq"));
+                }
+                (format, None) => *format,
+            };
+            return tokio::task::block_in_place(|| run_export(format, output.as_deref(), &cli));
+        }
+        Some(Command::Lint) => {
+            return run_lint();
+        }
+        #[cfg(feature = "check-update")]
+        Some(Command::CheckUpdate) => {
+            return tokio::task::block_in_place(|| run_check_update(&cli));
+        }
+        Some(Command::Lookup { domain, refresh }) => {
+            return tokio::task::block_in_place(|| run_lookup(domain, *refresh, &cli));
+        }
+        Some(Command::Pause) => {
+            return run_set_paused(true, &cli);
+        }
+        Some(Command::Resume) => {
+            return run_set_paused(false, &cli);
+        }
+        Some(Command::ListBackups) => {
+            return run_list_backups(&cli);
+        }
+        Some(Command::DiffBackups { old, new, latest }) => {
+            return run_diff_backups(old.as_deref(), new.as_deref(), *latest, &cli);
+        }
+        _ => {}
+    }
+    if cli.update_pins {
+        return tokio::task::block_in_place(|| run_update_pins(&cli));
+    }
 
     info!("hosts_updater_rs 启动");
 
-    // 检查管理员权限
-    if !check_admin_permission() {
+    // 加载配置（通过 --source 指定数据源时不读取配置文件）
+    let mut config = if !cli.sources().is_empty() {
+        info!("使用命令行指定的数据源，跳过配置文件");
+        Config::from_sources(cli.sources().to_vec())
+    } else {
+        match load_cli_config(&cli) {
+            Ok(config) => config,
+            Err(e) if e.is::<ConfigNotFound>() => return handle_missing_config(e),
+            Err(e) => return Err(e.context("加载配置文件失败")),
+        }
+    };
+    cli.apply_overrides(&mut config);
+    validate_config(&config).context("配置验证失败")?;
+
+    // 预览新增的域名：只读取 hosts 文件，不需要管理员权限
+    if cli.show_new() {
+        return tokio::task::block_in_place(|| run_show_new(&config));
+    }
+
+    // 检查管理员权限（在加载配置之后，以便提示文本按 locale 输出）；写入其他文件时不需要
+    if cli.output().is_none() && config.output_file.is_none() && !check_admin_permission() {
         warn!("程序未以管理员权限运行，可能无法修改系统 hosts 文件");
         #[cfg(target_os = "windows")]
         {
-            println!("警告: 程序需要管理员权限才能修改系统 hosts 文件");
-            println!("请右键点击程序，选择 '以管理员身份运行'");
+            eprintln!("{}", Msg::NeedAdmin);
+            eprintln!("{}", Msg::RunAsAdminHint);
         }
         #[cfg(not(target_os = "windows"))]
         {
-            println!("警告: 程序需要 root 权限才能修改系统 hosts 文件");
-            println!("请使用 sudo 运行: sudo {} ", std::env::current_exe()?.display());
+            eprintln!("{}", Msg::NeedRoot);
+            let exe = std::env::current_exe()?;
+            eprintln!("{}", Msg::RunWithSudo(&exe.display().to_string()));
         }
     }
 
-    // 加载配置
-    let config = load_config().context("加载配置文件失败")?;
-    validate_config(&config).context("配置验证失败")?;
-
     info!("配置加载成功，更新间隔: {} 小时", config.update_interval_hours);
     info!("数据源数量: {}", config.hosts_sources.len());
+    if config.tls_insecure {
+        warn!("{}", Msg::TlsInsecure);
+    }
+    if let Some(path) = &config.tls_ca_cert {
+        info!("已加载自定义 CA 证书: {}", path);
+    }
+    if let Some(version) = config.min_tls_version {
+        info!("获取数据源时要求的最低 TLS 版本: {}", version.as_str());
+    }
+    if let Some(resolver) = &config.dns_resolver {
+        info!(
+            "使用自定义 DNS 解析器（{}）: {}",
+            resolver.protocol.as_str(),
+            resolver.servers.join(", ")
+        );
+    }
+
+    // 检查 hosts 文件是否长时间未更新（之前的更新进程可能已停止）
+    match hosts_age(&config, &managed_hosts_path(&config)) {
+        Ok(Some((last_update, age_hours)))
+            if age_hours >= config.stale_threshold_hours() as i64 =>
+        {
+            warn!(
+                "{}",
+                Msg::HostsStale {
+                    last_update: &last_update,
+                    age_hours,
+                    threshold_hours: config.stale_threshold_hours(),
+                }
+            );
+        }
+        Ok(_) => {}
+        Err(e) => warn!("读取 hosts 最后更新时间失败: {:?}", e),
+    }
 
     // 创建更新任务
-    let update_task = create_update_task(config.clone());
+    let options = RunOptions {
+        update: UpdateOptions {
+            force: cli.force,
+            dry_run: cli.dry_run,
+            output: cli.output().map(std::path::Path::to_path_buf),
+            ..Default::default()
+        },
+        print_sources_status: cli.print_sources_status,
+        #[cfg(feature = "serve")]
+        served: cli.serve.then(|| start_server(config.serve_port)),
+    };
+
+    // 试运行检查：只输出发现的问题，供 CI 使用
+    if cli.check {
+        return run_check(&config, &options, cli.check_format.unwrap_or_default()).await;
+    }
+
+    // 区域未过期时跳过，过期时只更新一次（与试运行一样不进入定时任务）
+    if let Some(max_age_hours) = cli.if_stale() {
+        match hosts_age(&config, &managed_hosts_path(&config)) {
+            Ok(Some((last_update, age_hours))) if age_hours < max_age_hours as i64 => {
+                info!(
+                    "自动管理区域最后更新于 {}（{} 小时前），未超过 {} 小时，跳过本次更新",
+                    last_update, age_hours, max_age_hours
+                );
+                return Ok(());
+            }
+            Ok(_) => {}
+            Err(e) => warn!("读取 hosts 最后更新时间失败，照常更新: {:?}", e),
+        }
+        let outcome = run_update(&config, &options).await?;
+        return check_partial_failure(&outcome, cli.error_on_partial);
+    }
+
+    // 试运行：执行一次后退出
+    if options.update.dry_run {
+        let outcome = run_update(&config, &options).await?;
+        return check_partial_failure(&outcome, cli.error_on_partial);
+    }
+
+    let update_task = create_update_task(config.clone(), options);
 
     // 启动定时任务
-    let scheduler = Scheduler::new(config.update_interval_hours);
+    let scheduler = Scheduler::new(config.update_interval_hours)
+        .with_pause_file(pause_file_path(&config.cache_dir))
+        .with_window(config.update_window()?);
     scheduler.start(update_task).await;
 
     Ok(())
 }
 
+/// 单次更新的退出状态：`--error-on-partial` 时有数据源被跳过即返回错误
+fn check_partial_failure(outcome: &UpdateOutcome, error_on_partial: bool) -> Result<()> {
+    if error_on_partial && !outcome.failed_sources.is_empty() {
+        return Err(anyhow::anyhow!(
+            "{}",
+            Msg::PartialFailure(&outcome.failed_sources.join(", "))
+        ));
+    }
+    Ok(())
+}
+
+/// 试运行检查（`--dry-run --check`）：汇总 hosts 文件过期与数据源获取失败，按指定格式输出
+///
+/// 检查的 hosts 文件为 `run --output` 指定的文件（不存在时跳过过期检查），
+/// 未指定时为 `output_file` 或系统 hosts 文件。
+/// 过期只作为警告；有数据源获取失败或本次更新失败时以非零状态退出。
+async fn run_check(config: &Config, options: &RunOptions, format: ReportFormat) -> Result<()> {
+    let mut findings = Vec::new();
+
+    // 过期检查须在更新之前进行，试运行会覆盖 --output 指定的文件
+    let target = options.update.output.clone().unwrap_or_else(|| managed_hosts_path(config));
+    if target.exists() {
+        let file = target.display().to_string();
+        let threshold_hours = config.stale_threshold_hours();
+        match hosts_age(config, &target) {
+            Ok(Some((last_update, age_hours))) if age_hours >= threshold_hours as i64 => {
+                let message = Msg::HostsStale {
+                    last_update: &last_update,
+                    age_hours,
+                    threshold_hours,
+                };
+                findings.push(Finding::warning(message.to_string()).with_file(file));
+            }
+            Ok(_) => {}
+            Err(e) => findings.push(Finding::warning(format!("{:#}", e)).with_file(file)),
+        }
+    }
+
+    let cancel = CancellationToken::new();
+    match update::run_update(config, &options.update, &cancel, progress_reporter(config)).await {
+        Ok(outcome) => {
+            for url in &outcome.failed_sources {
+                let error = outcome
+                    .state
+                    .sources
+                    .iter()
+                    .find(|source| &source.url == url)
+                    .and_then(|source| source.error.as_deref())
+                    .unwrap_or_default();
+                findings.push(Finding::error(Msg::CheckSourceFailed { url, error }.to_string()));
+            }
+        }
+        Err(e) => {
+            let error = format!("{:#}", e);
+            findings.push(Finding::error(Msg::CheckUpdateFailed(&error).to_string()));
+        }
+    }
+
+    print!("{}", render_findings(&findings, format));
+    let errors = findings.iter().filter(|finding| finding.severity == Severity::Error).count();
+    if errors > 0 {
+        let warnings = findings.len() - errors;
+        return Err(anyhow::anyhow!("{}", Msg::CheckFailed { errors, warnings }));
+    }
+    if findings.is_empty() && format == ReportFormat::Text {
+        println!("{}", Msg::CheckPassed);
+    }
+    Ok(())
+}
+
+/// 更新任务在失败日志去重中使用的键
+const UPDATE_LOG_KEY: &str = "hosts 更新";
+
 /// 创建更新任务闭包
-fn create_update_task(config: Config) -> impl FnMut() -> Pin<Box<dyn Future<Output = ()> + Send>> {
+fn create_update_task(
+    config: Config,
+    options: RunOptions,
+) -> impl FnMut() -> Pin<Box<dyn Future<Output = ()> + Send>> {
+    let mut first_run = true;
+    let mut metrics = Metrics::default();
+    metrics.track_sources(config.hosts_sources.iter().map(|source| source.url()));
+    let metrics = Arc::new(Mutex::new(metrics));
     move || {
         let config = config.clone();
+        let mut options = options.clone();
+        options.update.cache_fallback = std::mem::take(&mut first_run);
+        let metrics = metrics.clone();
         Box::pin(async move {
-            if let Err(e) = run_update(&config).await {
-                error!("更新 hosts 失败: {:?}", e);
+            if config.skip_on_metered && tokio::task::block_in_place(is_metered) == Some(true) {
+                info!("当前网络按流量计费，跳过本次定时更新");
+                return;
+            }
+
+            let start = Instant::now();
+            let result = run_update(&config, &options).await;
+            if let Some(path) = &config.metrics_file {
+                update_metrics(&metrics, path, &result, start.elapsed());
+            }
+
+            // 持续失败时相同的错误在去重窗口内只输出一次
+            match result {
+                Ok(_) => log_recovery(UPDATE_LOG_KEY),
+                Err(e) => log_failure(
+                    UPDATE_LOG_KEY,
+                    &format!("更新 hosts 失败: {:#}", e),
+                    config.log_dedup_window(),
+                ),
             }
         })
     }
 }
 
-/// 执行一次更新
-async fn run_update(config: &Config) -> Result<()> {
-    info!("开始更新 hosts 文件...");
+/// 记录本次更新的结果并写入指标文件，写入失败仅输出警告
+fn update_metrics(
+    metrics: &Mutex<Metrics>,
+    path: &str,
+    result: &Result<UpdateOutcome>,
+    duration: std::time::Duration,
+) {
+    let mut metrics = metrics.lock().unwrap_or_else(|e| e.into_inner());
+    match result {
+        Ok(outcome) => {
+            let entries = outcome.state.last_entry_count.unwrap_or_default();
+            metrics.record_success(entries, duration, chrono::Utc::now());
+        }
+        Err(e) => {
+            let failed = e.downcast_ref::<FailedSource>().map(|failed| failed.url.as_str());
+            metrics.record_failure(failed, duration);
+        }
+    }
+
+    if let Err(e) = write_metrics(std::path::Path::new(path), &metrics.render()) {
+        warn!("写入指标文件失败: {:?}", e);
+    }
+}
+
+/// 按命令行参数加载配置，并按其中的 `locale` 设置输出语言
+///
+/// 优先使用 `--remote-config` 下载的远程配置与 `--config` 指定的文件（或标准输入），
+/// 其次按 `--merge-configs` 分层合并或查找配置文件。
+fn load_cli_config(cli: &Cli) -> Result<Config> {
+    let mut config = match (&cli.remote_config, &cli.config) {
+        (Some(url), _) => tokio::task::block_in_place(|| {
+            load_remote_config(url, cli.remote_config_sha256.as_deref(), None)
+        }),
+        (None, Some(path)) => load_config_from(path, cli.config_format),
+        (None, None) if cli.merge_configs => load_merged_config(cli.config_format),
+        (None, None) => load_config(cli.config_format),
+    }?;
+    config.config_path = match (&cli.remote_config, cli.config.as_deref()) {
+        (Some(url), _) => Some(url.clone()),
+        (None, Some(STDIN_PATH)) => Some("<stdin>".to_string()),
+        (None, Some(path)) => Some(path.to_string()),
+        (None, None) => find_config_file(cli.config_format),
+    };
+    if let Some(profile) = &cli.profile {
+        config = config.with_profile(profile)?;
+        info!("使用配置档: {}", profile);
+    }
+    config.apply_tag_overrides(&cli.enable_tags, &cli.disable_tags);
+    set_locale(config.locale);
+    Ok(config)
+}
+
+/// 执行数据源管理子命令
+///
+/// 修改的是 `--config` 指定的或当前生效（优先级最高）的配置文件，并按原格式写回。
+fn run_source_command(action: &SourceAction, cli: &Cli) -> Result<()> {
+    let config = load_cli_config(cli).context("加载配置文件失败");
+
+    match action {
+        SourceAction::Add { url } => {
+            let path = config_file(cli)?;
+            if add_source(&path, url)? {
+                info!("{}", Msg::SourceAdded { url, path: &path });
+            } else {
+                warn!("{}", Msg::SourceExists { url, path: &path });
+            }
+        }
+        SourceAction::Remove { url } => {
+            let path = config_file(cli)?;
+            if !remove_source(&path, url)? {
+                let msg = Msg::SourceNotInConfig { url, path: &path };
+                return Err(anyhow::anyhow!("{}", msg));
+            }
+            info!("{}", Msg::SourceRemoved { url, path: &path });
+        }
+        SourceAction::List => {
+            for source in &config?.hosts_sources {
+                println!("{}", source);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// 可修改的配置文件路径：`--config` 指定的文件或查找到的配置文件
+fn config_file(cli: &Cli) -> Result<String> {
+    if let Some(url) = &cli.remote_config {
+        return Err(anyhow::anyhow!("无法修改远程配置: {}", url));
+    }
+    match cli.config.as_deref() {
+        Some(STDIN_PATH) => Err(anyhow::anyhow!("无法修改从标准输入读取的配置")),
+        Some(path) => Ok(path.to_string()),
+        None => find_config_file(cli.config_format)
+            .ok_or_else(|| anyhow::anyhow!("{}", Msg::ConfigNotFound)),
+    }
+}
+
+/// 获取各数据源的当前内容，将其 SHA-256 写入配置文件
+fn run_update_pins(cli: &Cli) -> Result<()> {
+    let path = config_file(cli)?;
+    let config = load_cli_config(cli).context("加载配置文件失败")?;
+
+    let mut pins = std::collections::HashMap::new();
+    for source in &config.hosts_sources {
+        let sha256 = fetch_source_sha256(source, &config)
+            .with_context(|| format!("获取数据源失败: {}", source.url()))?;
+        info!("数据源 {} 的 SHA-256: {}", source.url(), sha256);
+        pins.insert(source.url().to_string(), sha256);
+    }
+
+    if set_source_pins(&path, &pins)? {
+        info!("已更新配置文件中的 SHA-256 固定值: {}", path);
+    } else {
+        info!("SHA-256 固定值均未变化: {}", path);
+    }
+
+    Ok(())
+}
+
+/// 未找到配置文件时首次运行生成的配置文件
+const DEFAULT_CONFIG_PATH: &str = "config.toml";
+
+/// 未找到配置文件时的引导
+///
+/// 在终端中交互运行时询问是否在当前目录生成示例配置，生成后提示修改数据源并退出；
+/// 非交互运行或用户拒绝时，返回附带解决方法提示的错误。
+fn handle_missing_config(error: anyhow::Error) -> Result<()> {
+    use std::io::{BufRead, IsTerminal, Write};
+
+    if std::io::stdin().is_terminal() && std::io::stderr().is_terminal() {
+        eprint!("{} ", Msg::GenerateConfigPrompt(DEFAULT_CONFIG_PATH));
+        std::io::stderr().flush()?;
+        let mut answer = String::new();
+        std::io::stdin().lock().read_line(&mut answer)?;
+        if matches!(answer.trim(), "y" | "Y" | "yes") {
+            run_generate_config(ConfigFormat::Toml, Some(DEFAULT_CONFIG_PATH))?;
+            eprintln!("{}", Msg::EditGeneratedConfig(DEFAULT_CONFIG_PATH));
+            return Ok(());
+        }
+    }
+
+    Err(anyhow::anyhow!("{}\n{}", error, Msg::MissingConfigHint))
+}
+
+/// 生成示例配置，写入指定文件或输出到标准输出
+fn run_generate_config(format: ConfigFormat, output: Option<&str>) -> Result<()> {
+    let Some(path) = output else {
+        print!("{}", format.sample());
+        return Ok(());
+    };
+
+    if std::path::Path::new(path).exists() {
+        return Err(anyhow::anyhow!("文件已存在，未覆盖: {}", path));
+    }
+    std::fs::write(path, format.sample()).with_context(|| format!("写入示例配置失败: {}", path))?;
+    info!("已生成示例配置: {}", path);
+
+    Ok(())
+}
+
+/// 检查单个数据源并输出所有格式问题
+///
+/// 存在配置文件时沿用其中的 TLS 与预处理设置，否则使用默认配置。
+/// URL 属于已配置的数据源时，同样按该数据源的格式转换内容并应用逐行替换规则。
+/// 请求使用 `timeout` 秒的超时，而不是定时更新时的默认超时。
+fn run_test_source(url: &str, timeout: u64, cli: &Cli) -> Result<()> {
+    let mut config = load_cli_config(cli).unwrap_or_else(|e| {
+        warn!("{}", Msg::NoConfigUsingDefaults(&e.to_string()));
+        Config::from_sources(vec![url.to_string()])
+    });
+    config.fetch_timeout_secs = Some(timeout);
+    let source = config.hosts_sources.iter().find(|s| s.urls().iter().any(|u| u == url));
+    let rewrite = match source {
+        Some(source) => source.line_rewrite()?,
+        None => None,
+    };
+
+    let (status, content) = fetch_raw(url, &config)?;
+    let content = match source.and_then(|source| source.json_pointer()) {
+        Some(pointer) => json_domains_to_hosts(&content, pointer, url)?,
+        None => content,
+    };
+    let content = match &rewrite {
+        Some(rewrite) => preprocess_content(&rewrite.apply(&content), &config),
+        None => preprocess_content(&content, &config),
+    };
+    if content.trim().is_empty() {
+        return Err(anyhow::anyhow!("{}", Msg::EmptyContent(url)));
+    }
+
+    let errors = validate_hosts_content_collect(&content);
+    println!(
+        "{}",
+        Msg::TestSourceSummary {
+            url,
+            status,
+            lines: content.lines().count(),
+            entries: count_entries(&content) - errors.len(),
+            problems: errors.len(),
+        }
+    );
+    for error in &errors {
+        println!("  {}", error);
+        println!("    > {}", error.content.trim_end());
+    }
+
+    if !errors.is_empty() {
+        return Err(anyhow::anyhow!(
+            "{}",
+            Msg::SourceHasProblems { count: errors.len(), url }
+        ));
+    }
+
+    Ok(())
+}
 
-    let hosts_path = get_hosts_path();
-    info!("目标 hosts 文件: {:?}", hosts_path);
+/// 执行一次更新，并按运行选项输出结果或发布生成的条目
+async fn run_update(config: &Config, options: &RunOptions) -> Result<UpdateOutcome> {
+    let outcome = update::run_update(
+        config,
+        &options.update,
+        &CancellationToken::new(),
+        progress_reporter(config),
+    )
+    .await?;
 
-    // 备份现有 hosts
-    if config.backup_before_update {
-        let backup_path = backup_hosts(&config.backup_path)?;
-        info!("已备份 hosts 文件到: {}", backup_path);
+    if !options.update.dry_run {
+        publish_hosts(options, outcome.content.clone());
+    } else if options.update.output.is_none() {
+        println!("{}", outcome.content);
     }
+    if options.print_sources_status {
+        println!("{}", render_sources_table(&outcome.state.sources));
+    }
+
+    Ok(outcome)
+}
+
+/// 在后台启动内置 HTTP 服务，返回与更新任务共享的内容
+#[cfg(feature = "serve")]
+fn start_server(port: u16) -> hosts_updater_rs::server::ServedHosts {
+    let served = hosts_updater_rs::server::ServedHosts::default();
+    let server_hosts = served.clone();
+    tokio::spawn(async move {
+        if let Err(e) = hosts_updater_rs::server::serve(port, server_hosts).await {
+            tracing::error!("HTTP 服务启动失败: {:?}", e);
+        }
+    });
+    served
+}
 
-    // 获取当前 hosts 内容
-    let current_content = read_hosts_content()?;
-    info!("当前 hosts 文件大小: {} 字节", current_content.len());
+/// 向内置 HTTP 服务发布本次生成的条目
+#[cfg(feature = "serve")]
+fn publish_hosts(options: &RunOptions, entries: String) {
+    if let Some(served) = &options.served {
+        served.publish(entries);
+    }
+}
 
-    // 从所有数据源获取 hosts 内容
-    info!("开始从 {} 个数据源获取 hosts...", config.hosts_sources.len());
-    let sources_content = fetch_all_hosts(&config.hosts_sources)?;
-    info!("成功获取 {} 个数据源的内容", sources_content.len());
+/// 未启用 `serve` 特性时无需发布
+#[cfg(not(feature = "serve"))]
+fn publish_hosts(_options: &RunOptions, _entries: String) {}
 
-    // 生成最后更新时间
-    let last_update = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+/// 读取 hosts 文件的最后更新时间，返回格式化的时间与距今小时数
+///
+/// 文件尚不存在时（如首次写入前的 `output_file`）返回 `None`。
+fn hosts_age(config: &Config, path: &Path) -> Result<Option<(String, i64)>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(read_last_update(path, &config.timestamp_format)?.map(|time| {
+        let age_hours = (config.timestamp_tz.now().naive_local() - time).num_hours();
+        (time.format(LAST_UPDATE_FORMAT).to_string(), age_hours)
+    }))
+}
 
-    // 写入 hosts 文件
-    write_hosts(&sources_content, &last_update)?;
-    info!("hosts 文件更新成功");
+/// 创建或删除暂停标记文件（`pause` / `resume` 子命令）
+fn run_set_paused(paused: bool, cli: &Cli) -> Result<()> {
+    let config = load_cli_config(cli).unwrap_or_else(|_| Config::from_sources(Vec::new()));
+    let changed = set_paused(&config.cache_dir, paused)?;
+    let path = pause_file_path(&config.cache_dir).display().to_string();
 
+    match (paused, changed) {
+        (true, true) => println!("{}", Msg::UpdatesPaused(&path)),
+        (true, false) => println!("{}", Msg::AlreadyPaused),
+        (false, true) => println!("{}", Msg::UpdatesResumed),
+        (false, false) => println!("{}", Msg::NotPaused),
+    }
     Ok(())
 }
+
+/// 输出运行状态，`--sources` 时附带各数据源状态表格
+///
+/// hosts 文件超过过期阈值未更新时以非零状态退出，便于监控发现停止工作的更新进程。
+fn run_status(show_sources: bool, cli: &Cli) -> Result<()> {
+    let config = load_cli_config(cli).unwrap_or_else(|_| Config::from_sources(Vec::new()));
+    let state = load_state(&config.cache_dir)?;
+
+    let age = hosts_age(&config, &managed_hosts_path(&config)).unwrap_or_else(|e| {
+        warn!("读取 hosts 最后更新时间失败: {:?}", e);
+        None
+    });
+    match &age {
+        Some((last_update, age_hours)) => println!(
+            "{}",
+            Msg::HostsLastUpdate { last_update, age_hours: *age_hours }
+        ),
+        None => println!("{}", Msg::HostsNoLastUpdate),
+    }
+    println!("{}", Msg::LastEntryCount(state.last_entry_count));
+    if pause_file_path(&config.cache_dir).exists() {
+        println!("{}", Msg::AlreadyPaused);
+    }
+    let failed = state
+        .sources
+        .iter()
+        .filter(|source| source.status == FetchStatus::Failed)
+        .count();
+    println!(
+        "{}",
+        Msg::SourcesSummary { total: state.sources.len(), failed }
+    );
+    for (tag, counts) in tag_counts(&config, &state.sources) {
+        println!(
+            "{}",
+            Msg::TagSummary {
+                tag,
+                total: counts.total,
+                disabled: counts.disabled,
+                failed: counts.failed,
+            }
+        );
+    }
+
+    if show_sources {
+        if state.sources.is_empty() {
+            println!("{}", Msg::NoSourceStatus);
+        } else {
+            println!("{}", render_sources_table(&state.sources));
+        }
+    }
+
+    let threshold_hours = config.stale_threshold_hours();
+    if let Some((last_update, age_hours)) = age
+        && age_hours >= threshold_hours as i64
+    {
+        return Err(anyhow::anyhow!(
+            "{}",
+            Msg::HostsStale { last_update: &last_update, age_hours, threshold_hours }
+        ));
+    }
+
+    Ok(())
+}
+
+/// 单个标签下的数据源数量
+#[derive(Debug, Default)]
+struct TagCounts {
+    /// 带有该标签的数据源
+    total: usize,
+    /// 因停用标签而跳过的数据源
+    disabled: usize,
+    /// 最近一次获取失败的数据源
+    failed: usize,
+}
+
+/// 按标签（字母顺序）统计配置中的数据源，获取失败的数量来自运行状态
+fn tag_counts<'a>(config: &'a Config, sources: &[SourceStatus]) -> BTreeMap<&'a str, TagCounts> {
+    let mut counts: BTreeMap<&str, TagCounts> = BTreeMap::new();
+    for source in &config.hosts_sources {
+        let disabled = config.disabled_tag(source).is_some();
+        let failed = sources
+            .iter()
+            .any(|status| status.url == source.url() && status.status == FetchStatus::Failed);
+        for tag in source.tags() {
+            let entry = counts.entry(tag.as_str()).or_default();
+            entry.total += 1;
+            entry.disabled += usize::from(disabled);
+            entry.failed += usize::from(failed);
+        }
+    }
+    counts
+}
+
+/// 检查系统 hosts 文件并输出所有格式有误的行，存在问题时以非零状态退出
+fn run_lint() -> Result<()> {
+    let path = get_hosts_path().display().to_string();
+    let content = read_hosts_content()?;
+
+    let errors = lint_hosts_content(&content);
+    println!(
+        "{}",
+        Msg::LintSummary {
+            path: &path,
+            lines: content.lines().count(),
+            problems: errors.len(),
+        }
+    );
+    for error in &errors {
+        println!("  {}", error);
+        println!("    > {}", error.content.trim_end());
+    }
+
+    if !errors.is_empty() {
+        return Err(anyhow::anyhow!(
+            "{}",
+            Msg::HostsHasProblems { count: errors.len(), path: &path }
+        ));
+    }
+
+    Ok(())
+}
+
+/// 获取所有数据源并导出合并去重后的结果
+fn run_export(format: ExportFormat, output: Option<&Path>, cli: &Cli) -> Result<()> {
+    let config = load_cli_config(cli).context("加载配置文件失败")?;
+    let sources = fetch_all_hosts(&config)?;
+    let content = export_entries(&sources, &config, format)?;
+
+    match output {
+        Some(path) => {
+            std::fs::write(path, &content)
+                .with_context(|| format!("写入导出文件失败: {:?}", path))?;
+            info!("已导出 {} 行到: {:?}", content.lines().count(), path);
+        }
+        None => print!("{}", content),
+    }
+
+    Ok(())
+}
+
+/// 获取数据源并生成新的自动管理区域，输出其中当前区域没有的域名
+///
+/// 与写入时一样处理冲突、去重、白名单与域名规则，但不写入 hosts 文件、缓存或运行状态。
+fn run_show_new(config: &Config) -> Result<()> {
+    let sources = fetch_all_hosts(config)?;
+    let existing = read_managed_content(config)?;
+    let previous = parse_managed_section(&existing, &config.timestamp_format);
+    let rendered = render_hosts(&sources, "", config)?;
+    let current = parse_managed_section(&rendered, &config.timestamp_format);
+
+    let changes = DomainChanges::between_sections(&previous, &current);
+    for domain in &changes.added {
+        println!("{}", domain);
+    }
+    info!(
+        "本次将新增 {} 个域名、移除 {} 个域名（未写入 hosts 文件）",
+        changes.added.len(),
+        changes.removed.len()
+    );
+
+    Ok(())
+}
+
+/// 查找域名所在的条目，输出 IP 与所属数据源，以及命中的域名规则
+///
+/// 默认在自动管理区域（系统 hosts 文件或 `output_file`）中查找；`refresh` 时重新获取配置中的数据源并在其中查找。
+/// 没有找到条目时以非零状态退出。
+fn run_lookup(domain: &str, refresh: bool, cli: &Cli) -> Result<()> {
+    let (config, section) = if refresh {
+        let config = load_cli_config(cli).context("加载配置文件失败")?;
+        let sources = fetch_all_hosts(&config)?;
+        let section = ManagedSection {
+            last_update: None,
+            sources: sources
+                .iter()
+                .map(|source| ManagedSource::from_content(&source.url, &source.content))
+                .collect(),
+        };
+        (config, section)
+    } else {
+        let config = load_cli_config(cli).unwrap_or_else(|_| Config::from_sources(Vec::new()));
+        let content = read_managed_content(&config)?;
+        let section = parse_managed_section(&content, &config.timestamp_format);
+        (config, section)
+    };
+
+    let matches = section.lookup(domain);
+    for m in &matches {
+        println!("{}", Msg::LookupMatch { ip: m.ip, source: m.source });
+    }
+    // 与预处理一致，只有第一条命中的规则生效
+    if let Some(rule) = config.sink_rules.iter().find(|rule| rule_matches(&rule.pattern, domain)) {
+        println!("{}", Msg::LookupSinkRule { pattern: &rule.pattern, ip: &rule.ip });
+    }
+
+    if matches.is_empty() {
+        return Err(anyhow::anyhow!("{}", Msg::DomainNotFound { domain, refresh }));
+    }
+
+    Ok(())
+}
+
+/// 查询最新发布的版本并与当前版本比较，只输出提示
+#[cfg(feature = "check-update")]
+fn run_check_update(cli: &Cli) -> Result<()> {
+    use hosts_updater_rs::release::{fetch_latest_release, CURRENT_VERSION, DEFAULT_RELEASE_API_URL};
+
+    let config = load_cli_config(cli).unwrap_or_else(|_| Config::from_sources(Vec::new()));
+    let api_url = config.release_api_url.as_deref().unwrap_or(DEFAULT_RELEASE_API_URL);
+    let release = fetch_latest_release(api_url, &config)?;
+
+    if release.is_newer()? {
+        println!(
+            "{}",
+            Msg::UpdateAvailable { current: CURRENT_VERSION, latest: &release.version }
+        );
+        if let Some(url) = &release.url {
+            println!("  {}", url);
+        }
+    } else {
+        println!("{}", Msg::UpToDate(CURRENT_VERSION));
+    }
+
+    Ok(())
+}
+
+/// 以表格列出备份目录中的备份，最新的在前
+fn run_list_backups(cli: &Cli) -> Result<()> {
+    let config = load_cli_config(cli).unwrap_or_else(|_| Config::from_sources(Vec::new()));
+    let backups = list_backups(&config.backup_path)?
+        .iter()
+        .rev()
+        .map(|path| backup_info(path))
+        .collect::<Result<Vec<_>>>()?;
+
+    if backups.is_empty() {
+        println!("{}", Msg::NoBackups);
+    } else {
+        println!("{}", render_backups_table(&backups));
+    }
+
+    Ok(())
+}
+
+/// 对比两个备份的自动管理区域，`latest` 时取备份目录中最近的两个备份
+fn run_diff_backups(old: Option<&Path>, new: Option<&Path>, latest: bool, cli: &Cli) -> Result<()> {
+    let (old, new) = match (old, new) {
+        (Some(old), Some(new)) if !latest => (old.to_path_buf(), new.to_path_buf()),
+        _ => {
+            let config =
+                load_cli_config(cli).unwrap_or_else(|_| Config::from_sources(Vec::new()));
+            let mut backups = list_backups(&config.backup_path)?;
+            if backups.len() < 2 {
+                return Err(anyhow::anyhow!("{}", Msg::NotEnoughBackups(backups.len())));
+            }
+            let new = backups.pop().unwrap_or_default();
+            let old = backups.pop().unwrap_or_default();
+            (old, new)
+        }
+    };
+
+    let read = |path: &Path| {
+        std::fs::read_to_string(path).with_context(|| format!("读取备份文件失败: {:?}", path))
+    };
+    let diff = diff_backups(
+        &read(&old)?,
+        &read(&new)?,
+        &old.to_string_lossy(),
+        &new.to_string_lossy(),
+    );
+
+    if diff.is_empty() {
+        println!("{}", Msg::BackupsIdentical);
+        return Ok(());
+    }
+    print!("{}", diff.unified);
+    println!();
+    println!("{}", Msg::DomainsAdded(diff.added.len()));
+    for domain in &diff.added {
+        println!("+ {}", domain);
+    }
+    println!("{}", Msg::DomainsRemoved(diff.removed.len()));
+    for domain in &diff.removed {
+        println!("- {}", domain);
+    }
+
+    Ok(())
+}
+
+/// 创建获取进度回调，启用 `progress` 特性时显示进度条
+#[cfg(feature = "progress")]
+fn progress_reporter(config: &Config) -> impl Fn(FetchProgress) + Send + 'static {
+    // 回调随获取结束被释放时清除进度条
+    let bar = indicatif::ProgressBar::new(config.hosts_sources.len() as u64)
+        .with_finish(indicatif::ProgressFinish::AndClear);
+    if let Ok(style) = indicatif::ProgressStyle::with_template("{bar:30} {pos}/{len} {wide_msg}") {
+        bar.set_style(style);
+    }
+
+    move |progress| {
+        bar.set_position(progress.completed as u64);
+        bar.set_message(progress.url);
+    }
+}
+
+/// 创建获取进度回调，以 debug 日志记录进度
+#[cfg(not(feature = "progress"))]
+fn progress_reporter(_config: &Config) -> impl Fn(FetchProgress) + Send + 'static {
+    |progress| {
+        tracing::debug!(
+            "获取进度: {}/{} {}",
+            progress.completed, progress.total, progress.url
+        );
+    }
+}
@@ -0,0 +1,91 @@
+//! 按流量计费网络检测模块
+//!
+//! 启用 `skip_on_metered` 时，定时更新前检查当前网络是否按流量计费：Linux 通过 NetworkManager
+//! 的 `Metered` 属性（使用 `busctl` 读取），Windows 通过网络成本 API（使用 PowerShell 读取）。
+//! 其他平台或无法获取状态时视为未知，照常更新。
+
+use std::process::Command;
+
+/// 检测当前网络是否按流量计费，无法获取时返回 `None`
+pub fn is_metered() -> Option<bool> {
+    if cfg!(target_os = "linux") {
+        let output = command_output(
+            "busctl",
+            &[
+                "get-property",
+                "org.freedesktop.NetworkManager",
+                "/org/freedesktop/NetworkManager",
+                "org.freedesktop.NetworkManager",
+                "Metered",
+            ],
+        )?;
+        parse_network_manager_metered(&output)
+    } else if cfg!(target_os = "windows") {
+        let output = command_output(
+            "powershell",
+            &[
+                "-NoProfile",
+                "-NonInteractive",
+                "-Command",
+                "[Windows.Networking.Connectivity.NetworkInformation,Windows.Networking.Connectivity,\
+                 ContentType=WindowsRuntime]::GetInternetConnectionProfile()\
+                 .GetConnectionCost().NetworkCostType",
+            ],
+        )?;
+        parse_network_cost_type(&output)
+    } else {
+        None
+    }
+}
+
+/// 执行命令并返回标准输出，命令不存在或执行失败时返回 `None`
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        tracing::debug!(
+            "{} 执行失败: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// 解析 `busctl` 输出的 NetworkManager `Metered` 属性（如 `u 4`）
+///
+/// 取值为 NMMetered：1 是、2 否、3 推测是、4 推测否，0 表示未知。
+fn parse_network_manager_metered(output: &str) -> Option<bool> {
+    match output.split_whitespace().nth(1)? {
+        "1" | "3" => Some(true),
+        "2" | "4" => Some(false),
+        _ => None,
+    }
+}
+
+/// 解析 Windows 网络成本类型：`Fixed` 与 `Variable` 为按流量计费，`Unrestricted` 不计费
+fn parse_network_cost_type(output: &str) -> Option<bool> {
+    match output.trim() {
+        "Fixed" | "Variable" => Some(true),
+        "Unrestricted" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_metered_status() {
+        assert_eq!(parse_network_manager_metered("u 1\n"), Some(true));
+        assert_eq!(parse_network_manager_metered("u 3\n"), Some(true));
+        assert_eq!(parse_network_manager_metered("u 4\n"), Some(false));
+        assert_eq!(parse_network_manager_metered("u 0\n"), None);
+        assert_eq!(parse_network_manager_metered(""), None);
+
+        assert_eq!(parse_network_cost_type("Variable\r\n"), Some(true));
+        assert_eq!(parse_network_cost_type("Unrestricted\r\n"), Some(false));
+        assert_eq!(parse_network_cost_type("Unknown\r\n"), None);
+    }
+}
@@ -0,0 +1,162 @@
+//! 监控指标模块
+//!
+//! 以 Prometheus 文本格式输出更新相关的指标，供 node_exporter 的 textfile collector 采集。
+//! 指标文件先写入同目录下的临时文件再重命名，采集时不会读到写了一半的内容。
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+use std::time::Duration;
+
+/// 进程运行期间累计的更新指标
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// 最近一次成功更新的时间
+    last_success: Option<DateTime<Utc>>,
+    /// 最近一次成功写入的条目总数
+    entries: usize,
+    /// 各数据源累计的获取失败次数
+    source_failures: BTreeMap<String, u64>,
+    /// 最近一次更新的耗时
+    last_duration: Duration,
+}
+
+impl Metrics {
+    /// 确保数据源在失败计数中存在，使从未失败的数据源也输出值为 0 的指标
+    pub fn track_sources<'a>(&mut self, urls: impl IntoIterator<Item = &'a str>) {
+        for url in urls {
+            self.source_failures.entry(url.to_string()).or_default();
+        }
+    }
+
+    /// 记录一次成功的更新
+    pub fn record_success(&mut self, entries: usize, duration: Duration, now: DateTime<Utc>) {
+        self.last_success = Some(now);
+        self.entries = entries;
+        self.last_duration = duration;
+    }
+
+    /// 记录一次失败的更新，`failed_url` 为导致失败的数据源
+    pub fn record_failure(&mut self, failed_url: Option<&str>, duration: Duration) {
+        if let Some(url) = failed_url {
+            *self.source_failures.entry(url.to_string()).or_default() += 1;
+        }
+        self.last_duration = duration;
+    }
+
+    /// 渲染为 Prometheus 文本格式
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+
+        write_metric(
+            &mut output,
+            "hosts_updater_last_success_timestamp",
+            "最近一次成功更新的 Unix 时间戳（秒），尚未成功时为 0",
+            "gauge",
+        );
+        let timestamp = self.last_success.map_or(0, |time| time.timestamp());
+        let _ = writeln!(output, "hosts_updater_last_success_timestamp {}", timestamp);
+
+        write_metric(
+            &mut output,
+            "hosts_updater_entries_total",
+            "最近一次成功写入的条目总数",
+            "gauge",
+        );
+        let _ = writeln!(output, "hosts_updater_entries_total {}", self.entries);
+
+        write_metric(
+            &mut output,
+            "hosts_updater_source_failures_total",
+            "各数据源累计的获取失败次数",
+            "counter",
+        );
+        for (url, failures) in &self.source_failures {
+            let _ = writeln!(
+                output,
+                "hosts_updater_source_failures_total{{url=\"{}\"}} {}",
+                escape_label(url),
+                failures
+            );
+        }
+
+        write_metric(
+            &mut output,
+            "hosts_updater_update_duration_seconds",
+            "最近一次更新的耗时（秒）",
+            "gauge",
+        );
+        let _ = writeln!(
+            output,
+            "hosts_updater_update_duration_seconds {:.3}",
+            self.last_duration.as_secs_f64()
+        );
+
+        output
+    }
+}
+
+/// 输出指标的 HELP 与 TYPE 行
+fn write_metric(output: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(output, "# HELP {} {}", name, help);
+    let _ = writeln!(output, "# TYPE {} {}", name, kind);
+}
+
+/// 转义标签值中的反斜杠、双引号与换行
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// 原子地写入指标文件
+///
+/// 临时文件名不以 `.prom` 结尾，textfile collector 不会采集到它。
+pub fn write_metrics(path: &Path, content: &str) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "metrics.prom".to_string());
+    let temp_path = path.with_file_name(format!("{}.tmp", file_name));
+
+    std::fs::write(&temp_path, content)
+        .with_context(|| format!("写入指标临时文件失败: {:?}", temp_path))?;
+    std::fs::rename(&temp_path, path).with_context(|| {
+        let _ = std::fs::remove_file(&temp_path);
+        format!("写入指标文件失败: {:?}", path)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_and_write_metrics() {
+        let mut metrics = Metrics::default();
+        metrics.track_sources(["https://a.example/hosts", "https://b.example/\"x\""]);
+        metrics.record_failure(Some("https://a.example/hosts"), Duration::from_millis(1500));
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        metrics.record_success(42, Duration::from_millis(250), now);
+
+        let content = metrics.render();
+        assert!(content.contains("hosts_updater_last_success_timestamp 1700000000\n"));
+        assert!(content.contains("hosts_updater_entries_total 42\n"));
+        assert!(content.contains(
+            "hosts_updater_source_failures_total{url=\"https://a.example/hosts\"} 1\n"
+        ));
+        assert!(content.contains(
+            "hosts_updater_source_failures_total{url=\"https://b.example/\\\"x\\\"\"} 0\n"
+        ));
+        assert!(content.contains("hosts_updater_update_duration_seconds 0.250\n"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hosts_updater.prom");
+        write_metrics(&path, &content).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), content);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,71 @@
+//! 默认路径模块
+//!
+//! 按平台约定（Linux 上遵循 `XDG_CONFIG_HOME`、`XDG_CACHE_HOME`、`XDG_DATA_HOME`）解析用户配置、
+//! 缓存与数据目录，作为未配置 `cache_dir` / `backup_path` 时的默认位置。
+
+use directories::ProjectDirs;
+use std::path::{Path, PathBuf};
+
+/// 各平台目录中使用的应用名
+const APP_NAME: &str = "hosts_updater";
+
+/// 旧版本默认使用的缓存目录（相对于当前目录）
+const LEGACY_CACHE_DIR: &str = "./cache";
+
+/// 旧版本默认使用的备份目录（相对于当前目录）
+const LEGACY_BACKUP_DIR: &str = "./backup";
+
+fn project_dirs() -> Option<ProjectDirs> {
+    ProjectDirs::from("", "", APP_NAME)
+}
+
+/// 用户配置目录，如 `$XDG_CONFIG_HOME/hosts_updater`（默认 `~/.config/hosts_updater`）
+pub fn user_config_dir() -> Option<PathBuf> {
+    project_dirs().map(|dirs| dirs.config_dir().to_path_buf())
+}
+
+/// 默认缓存目录，如 `$XDG_CACHE_HOME/hosts_updater`（默认 `~/.cache/hosts_updater`）
+///
+/// 当前目录下已存在旧版本的 `cache` 目录时继续使用，避免升级后丢失缓存与运行状态。
+pub fn default_cache_dir() -> PathBuf {
+    resolve_dir(
+        Path::new(LEGACY_CACHE_DIR),
+        project_dirs().map(|dirs| dirs.cache_dir().to_path_buf()),
+    )
+}
+
+/// 默认备份目录，如 `$XDG_DATA_HOME/hosts_updater/backup`（默认 `~/.local/share/hosts_updater/backup`）
+///
+/// 当前目录下已存在旧版本的 `backup` 目录时继续使用。
+pub fn default_backup_dir() -> PathBuf {
+    resolve_dir(
+        Path::new(LEGACY_BACKUP_DIR),
+        project_dirs().map(|dirs| dirs.data_dir().join("backup")),
+    )
+}
+
+/// 旧目录存在时使用旧目录，否则使用平台目录；无法确定主目录时回退到旧目录
+fn resolve_dir(legacy: &Path, platform: Option<PathBuf>) -> PathBuf {
+    match platform {
+        Some(dir) if !legacy.is_dir() => dir,
+        _ => legacy.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let legacy = dir.path().join("cache");
+        let platform = dir.path().join("xdg/hosts_updater");
+
+        assert_eq!(resolve_dir(&legacy, Some(platform.clone())), platform);
+        assert_eq!(resolve_dir(&legacy, None), legacy);
+
+        std::fs::create_dir(&legacy).unwrap();
+        assert_eq!(resolve_dir(&legacy, Some(platform)), legacy);
+    }
+}
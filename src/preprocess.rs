@@ -0,0 +1,355 @@
+//! 预处理模块
+//!
+//! 在验证之前对数据源内容逐行进行转换，如国际化域名转换、去除域名末尾的点、
+//! 按域名规则改写目标 IP，以及为 IPv4 黑洞条目补充对应的 IPv6 条目。
+
+use crate::config::{Config, SinkRule};
+use anyhow::{Context, Result};
+
+/// JSON 数据源中的域名转换成的条目所指向的 IP
+const JSON_DOMAIN_SINK_IP: &str = "0.0.0.0";
+
+/// 将 JSON 数据源转换为 hosts 纯文本
+///
+/// `pointer` 为 JSON Pointer（RFC 6901），须指向由域名字符串组成的数组，
+/// 每个域名转换为一行指向 0.0.0.0 的条目；结构不符时返回说明原因的错误。
+pub fn json_domains_to_hosts(content: &str, pointer: &str, url: &str) -> Result<String> {
+    let document: serde_json::Value =
+        serde_json::from_str(content).with_context(|| format!("解析 JSON 数据源失败: {}", url))?;
+    let location = if pointer.is_empty() { "/" } else { pointer };
+    let value = document.pointer(pointer).ok_or_else(|| {
+        anyhow::anyhow!("JSON 数据源中不存在 json_pointer 指向的位置 {}: {}", location, url)
+    })?;
+    let domains = value.as_array().ok_or_else(|| {
+        anyhow::anyhow!("JSON 数据源中 {} 处不是数组: {}", location, url)
+    })?;
+
+    let mut result = String::new();
+    for (i, domain) in domains.iter().enumerate() {
+        let domain = domain.as_str().map(str::trim).ok_or_else(|| {
+            anyhow::anyhow!("JSON 数据源中 {} 处的第 {} 项不是字符串: {}", location, i + 1, url)
+        })?;
+        if domain.is_empty() {
+            continue;
+        }
+        result.push_str(JSON_DOMAIN_SINK_IP);
+        result.push(' ');
+        result.push_str(domain);
+        result.push('\n');
+    }
+    Ok(result)
+}
+
+/// 对数据源内容进行预处理
+///
+/// 空行、注释行以及无法解析的行原样保留，交由后续验证处理。
+pub fn preprocess_content(content: &str, config: &Config) -> String {
+    if !config.idn
+        && !config.strip_trailing_dot
+        && config.sink_rules.is_empty()
+        && !config.dual_stack_sink
+    {
+        return content.to_string();
+    }
+
+    let mut result = String::with_capacity(content.len());
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            result.push_str(line);
+            result.push('\n');
+            continue;
+        }
+
+        match transform_entry(trimmed, config) {
+            Some(rewritten) => result.push_str(&rewritten),
+            None => {
+                result.push_str(line);
+                result.push('\n');
+            }
+        }
+    }
+
+    result
+}
+
+/// 转换单行 hosts 条目
+///
+/// 条目未发生任何变化时返回 `None`，以便原样保留该行。行尾注释保留在改写后的第一行。
+/// 启用 `dual_stack_sink` 时，指向 IPv4 黑洞地址的每一行之后紧跟同样域名的 IPv6 行。
+fn transform_entry(line: &str, config: &Config) -> Option<String> {
+    let (entry, comment) = match line.find('#') {
+        Some(pos) => (&line[..pos], Some(line[pos..].trim_end())),
+        None => (line, None),
+    };
+    let mut parts = entry.split_whitespace();
+    let ip = parts.next()?;
+
+    let mut changed = false;
+    let domains: Vec<String> = parts
+        .map(|domain| {
+            let mut domain = domain.to_string();
+            if config.idn
+                && let Some(ascii) = to_ascii_domain(&domain)
+            {
+                changed = true;
+                domain = ascii;
+            }
+            if config.strip_trailing_dot && domain.len() > 1 && domain.ends_with('.') {
+                changed = true;
+                domain.pop();
+            }
+            domain
+        })
+        .collect();
+    if domains.is_empty() {
+        return None;
+    }
+
+    let domains: Vec<&str> = domains.iter().map(String::as_str).collect();
+    let groups = match group_by_sink_rules(ip, &domains, &config.sink_rules) {
+        Some(groups) => groups,
+        None if changed || (config.dual_stack_sink && ipv6_sink(ip).is_some()) => {
+            vec![(ip, domains)]
+        }
+        None => return None,
+    };
+
+    let mut rewritten = String::new();
+    for (index, (target, group)) in groups.into_iter().enumerate() {
+        rewritten.push_str(target);
+        rewritten.push(' ');
+        rewritten.push_str(&group.join(" "));
+        if index == 0
+            && let Some(comment) = comment
+        {
+            rewritten.push(' ');
+            rewritten.push_str(comment);
+        }
+        rewritten.push('\n');
+        if config.dual_stack_sink
+            && let Some(ipv6) = ipv6_sink(target)
+        {
+            rewritten.push_str(ipv6);
+            rewritten.push(' ');
+            rewritten.push_str(&group.join(" "));
+            rewritten.push('\n');
+        }
+    }
+
+    Some(rewritten)
+}
+
+/// IPv4 黑洞地址对应的 IPv6 地址：`0.0.0.0` 对应 `::`，`127.0.0.1` 对应 `::1`
+fn ipv6_sink(ip: &str) -> Option<&'static str> {
+    match ip {
+        "0.0.0.0" => Some("::"),
+        "127.0.0.1" => Some("::1"),
+        _ => None,
+    }
+}
+
+/// 将国际化域名转换为 punycode 形式
+///
+/// 纯 ASCII 域名或无法转换的域名返回 `None`，无法转换的域名交由后续验证报错。
+pub fn to_ascii_domain(domain: &str) -> Option<String> {
+    if domain.is_ascii() {
+        return None;
+    }
+
+    idna::domain_to_ascii(domain).ok()
+}
+
+/// 按规则将域名分组到目标 IP
+///
+/// 没有任何域名命中规则时返回 `None`；否则按目标 IP 分组，
+/// 每组保持域名的原有顺序。
+fn group_by_sink_rules<'a>(
+    ip: &'a str,
+    domains: &[&'a str],
+    rules: &'a [SinkRule],
+) -> Option<Vec<(&'a str, Vec<&'a str>)>> {
+    let mut matched = false;
+    let mut groups: Vec<(&str, Vec<&str>)> = Vec::new();
+
+    for &domain in domains {
+        let target = match rules
+            .iter()
+            .find(|rule| rule_matches(&rule.pattern, domain))
+        {
+            Some(rule) => {
+                matched = true;
+                rule.ip.as_str()
+            }
+            None => ip,
+        };
+
+        match groups.iter_mut().find(|(group_ip, _)| *group_ip == target) {
+            Some((_, group)) => group.push(domain),
+            None => groups.push((target, vec![domain])),
+        }
+    }
+
+    matched.then_some(groups)
+}
+
+/// 判断域名是否匹配规则模式
+///
+/// `*.example.com` 匹配 `example.com` 及其所有子域名，其他模式精确匹配，均不区分大小写。
+pub fn rule_matches(pattern: &str, domain: &str) -> bool {
+    match pattern.strip_prefix("*.") {
+        Some(suffix) => {
+            if domain.eq_ignore_ascii_case(suffix) {
+                return true;
+            }
+            domain.len() > suffix.len()
+                && domain.as_bytes()[domain.len() - suffix.len() - 1] == b'.'
+                && domain[domain.len() - suffix.len()..].eq_ignore_ascii_case(suffix)
+        }
+        None => domain.eq_ignore_ascii_case(pattern),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_matches() {
+        assert!(rule_matches("*.doubleclick.net", "doubleclick.net"));
+        assert!(rule_matches("*.doubleclick.net", "ad.doubleclick.net"));
+        assert!(rule_matches("*.doubleclick.net", "A.B.DoubleClick.net"));
+        assert!(!rule_matches("*.doubleclick.net", "notdoubleclick.net"));
+        assert!(rule_matches(
+            "telemetry.internal.corp",
+            "telemetry.internal.corp"
+        ));
+        assert!(!rule_matches(
+            "telemetry.internal.corp",
+            "a.telemetry.internal.corp"
+        ));
+    }
+
+    #[test]
+    fn test_dual_stack_sink() {
+        let config = Config::for_test(r#"{ "hosts_sources": [], "dual_stack_sink": true }"#);
+        let content = preprocess_content(
+            "0.0.0.0 a.com b.com\n127.0.0.1 c.com\n10.0.0.1 d.com\n",
+            &config,
+        );
+        assert_eq!(
+            content,
+            "0.0.0.0 a.com b.com\n:: a.com b.com\n127.0.0.1 c.com\n::1 c.com\n10.0.0.1 d.com\n"
+        );
+        crate::validation::validate_hosts_content(&content, "https://a.example").unwrap();
+
+        // 去重按 IP 与域名区分，IPv4 与 IPv6 条目都会保留
+        let sources = vec![crate::fetcher::SourceResult::for_test("https://a.example", &content)];
+        assert_eq!(crate::conflict::dedup_sources(&sources)[0].content, content);
+    }
+
+    #[test]
+    fn test_sink_rules_first_match_wins() {
+        let config = Config::for_test(
+            r#"{
+                "hosts_sources": [],
+                "sink_rules": [
+                    { "pattern": "telemetry.internal.corp", "ip": "10.0.0.5" },
+                    { "pattern": "*.internal.corp", "ip": "0.0.0.0" }
+                ]
+            }"#,
+        );
+
+        assert_eq!(
+            transform_entry("1.2.3.4 telemetry.internal.corp", &config).as_deref(),
+            Some("10.0.0.5 telemetry.internal.corp\n")
+        );
+        assert_eq!(
+            transform_entry("1.2.3.4 other.internal.corp", &config).as_deref(),
+            Some("0.0.0.0 other.internal.corp\n")
+        );
+    }
+
+    #[test]
+    fn test_sink_rules_split_line() {
+        let config = Config::for_test(
+            r#"{
+                "hosts_sources": [],
+                "sink_rules": [{ "pattern": "*.doubleclick.net", "ip": "0.0.0.0" }]
+            }"#,
+        );
+
+        assert_eq!(
+            transform_entry("1.2.3.4 a.com ad.doubleclick.net b.com", &config).as_deref(),
+            Some("1.2.3.4 a.com b.com\n0.0.0.0 ad.doubleclick.net\n")
+        );
+        assert_eq!(transform_entry("1.2.3.4 a.com", &config), None);
+    }
+
+    #[test]
+    fn test_to_ascii_domain() {
+        assert_eq!(
+            to_ascii_domain("例え.jp").as_deref(),
+            Some("xn--r8jz45g.jp")
+        );
+        assert_eq!(to_ascii_domain("example.com"), None);
+    }
+
+    #[test]
+    fn test_preprocess_idn() {
+        let config = Config::for_test(r#"{ "hosts_sources": [], "idn": true }"#);
+        let content = "# 注释\n127.0.0.1 例え.jp example.com\n";
+
+        assert_eq!(
+            preprocess_content(content, &config),
+            "# 注释\n127.0.0.1 xn--r8jz45g.jp example.com\n"
+        );
+    }
+
+    #[test]
+    fn test_preprocess_strip_trailing_dot() {
+        let config = Config::for_test(r#"{ "hosts_sources": [] }"#);
+        let content = "0.0.0.0 example.com. a.com # FQDN.\n0.0.0.0 b.com\n";
+
+        assert_eq!(
+            preprocess_content(content, &config),
+            "0.0.0.0 example.com a.com # FQDN.\n0.0.0.0 b.com\n"
+        );
+
+        let config = Config::for_test(r#"{ "hosts_sources": [], "strip_trailing_dot": false }"#);
+        assert_eq!(preprocess_content(content, &config), content);
+    }
+
+    #[test]
+    fn test_preprocess_idn_disabled() {
+        let config = Config::for_test(r#"{ "hosts_sources": [] }"#);
+        let content = "127.0.0.1 例え.jp\n";
+
+        assert_eq!(preprocess_content(content, &config), content);
+    }
+
+    #[test]
+    fn test_json_domains_to_hosts() {
+        let url = "https://example.com/domains.json";
+        let content = r#"{
+            "version": 2,
+            "data": { "blocklist": { "domains": ["ads.example.com", " track.example.com ", ""] } }
+        }"#;
+
+        let hosts = json_domains_to_hosts(content, "/data/blocklist/domains", url).unwrap();
+        assert_eq!(hosts, "0.0.0.0 ads.example.com\n0.0.0.0 track.example.com\n");
+        assert_eq!(
+            json_domains_to_hosts(r#"["a.example.com"]"#, "", url).unwrap(),
+            "0.0.0.0 a.example.com\n"
+        );
+
+        let missing = json_domains_to_hosts(content, "/data/domains", url).unwrap_err();
+        assert!(missing.to_string().contains("/data/domains"));
+        let not_array = json_domains_to_hosts(content, "/data/blocklist", url).unwrap_err();
+        assert!(not_array.to_string().contains("不是数组"));
+        let not_string = json_domains_to_hosts(r#"{"d": ["a.example.com", 1]}"#, "/d", url);
+        assert!(not_string.unwrap_err().to_string().contains("第 2 项"));
+        assert!(json_domains_to_hosts("not json", "", url).is_err());
+    }
+}
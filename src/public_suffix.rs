@@ -0,0 +1,150 @@
+//! 公共后缀校验模块
+//!
+//! 启用 `require_public_suffix` 时，丢弃数据源中没有已知公共后缀的域名（如 `foo.invalidtld`），
+//! 过滤格式合法但实际无效的条目。公共后缀列表下载后缓存在缓存目录中，之后可离线使用。
+
+use crate::config::Config;
+use crate::fetcher::fetch_raw;
+use crate::state::get_cache_dir;
+use anyhow::{Context, Result};
+use publicsuffix::{List, Psl};
+use std::path::Path;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// 公共后缀列表的下载地址
+pub const PUBLIC_SUFFIX_LIST_URL: &str = "https://publicsuffix.org/list/public_suffix_list.dat";
+
+/// 缓存目录中的公共后缀列表文件名
+const PUBLIC_SUFFIX_FILE_NAME: &str = "public_suffix_list.dat";
+
+/// 缓存的公共后缀列表超过该时长后重新下载
+const PUBLIC_SUFFIX_MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// 加载公共后缀列表
+///
+/// 缓存未过期时直接使用缓存；否则重新下载并更新缓存，下载失败时退回使用过期的缓存。
+pub fn load_public_suffix_list(config: &Config) -> Result<List> {
+    let dir = get_cache_dir(&config.cache_dir);
+    let path = dir.join(PUBLIC_SUFFIX_FILE_NAME);
+
+    if is_fresh(&path) {
+        return read_list(&path);
+    }
+
+    let downloaded = fetch_raw(PUBLIC_SUFFIX_LIST_URL, config).and_then(|(_, content)| {
+        let list = parse_list(&content)?;
+        std::fs::create_dir_all(&dir).with_context(|| format!("创建缓存目录失败: {:?}", dir))?;
+        std::fs::write(&path, &content)
+            .with_context(|| format!("写入公共后缀列表缓存失败: {:?}", path))?;
+        Ok(list)
+    });
+    match downloaded {
+        Ok(list) => {
+            info!("已下载公共后缀列表: {}", PUBLIC_SUFFIX_LIST_URL);
+            Ok(list)
+        }
+        Err(e) if path.exists() => {
+            warn!("下载公共后缀列表失败，使用本地缓存: {:?}", e);
+            read_list(&path)
+        }
+        Err(e) => Err(e.context("下载公共后缀列表失败，且本地没有缓存")),
+    }
+}
+
+/// 缓存文件是否存在且未过期
+fn is_fresh(path: &Path) -> bool {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age < PUBLIC_SUFFIX_MAX_AGE)
+}
+
+/// 读取缓存的公共后缀列表
+fn read_list(path: &Path) -> Result<List> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("读取公共后缀列表缓存失败: {:?}", path))?;
+    parse_list(&content)
+}
+
+/// 解析公共后缀列表
+fn parse_list(content: &str) -> Result<List> {
+    content
+        .parse()
+        .map_err(|e| anyhow::anyhow!("解析公共后缀列表失败: {}", e))
+}
+
+/// 域名是否具有已知的公共后缀
+pub fn has_public_suffix(list: &List, domain: &str) -> bool {
+    let domain = domain.strip_suffix('.').unwrap_or(domain).to_ascii_lowercase();
+    list.suffix(domain.as_bytes())
+        .is_some_and(|suffix| suffix.is_known())
+}
+
+/// 丢弃没有已知公共后缀的域名，返回过滤后的内容与丢弃的域名数量
+///
+/// 条目的域名全部被丢弃时删除整行；部分被丢弃时保留其余域名与行尾注释。
+pub fn filter_public_suffix(content: &str, list: &List) -> (String, usize) {
+    let mut result = String::with_capacity(content.len());
+    let mut removed = 0;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            result.push_str(line);
+            result.push('\n');
+            continue;
+        }
+
+        let (entry, comment) = match trimmed.find('#') {
+            Some(pos) => (&trimmed[..pos], Some(&trimmed[pos..])),
+            None => (trimmed, None),
+        };
+        let mut parts = entry.split_whitespace();
+        let Some(ip) = parts.next() else {
+            continue;
+        };
+        let domains: Vec<&str> = parts.collect();
+        let kept: Vec<&str> = domains
+            .iter()
+            .copied()
+            .filter(|domain| has_public_suffix(list, domain))
+            .collect();
+        removed += domains.len() - kept.len();
+
+        if kept.len() == domains.len() {
+            result.push_str(line);
+        } else if kept.is_empty() {
+            continue;
+        } else {
+            result.push_str(ip);
+            result.push(' ');
+            result.push_str(&kept.join(" "));
+            if let Some(comment) = comment {
+                result.push(' ');
+                result.push_str(comment);
+            }
+        }
+        result.push('\n');
+    }
+
+    (result, removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_public_suffix() {
+        let list = parse_list("// ===BEGIN ICANN DOMAINS===\ncom\nco.uk\n").unwrap();
+        assert!(has_public_suffix(&list, "ads.example.CO.UK."));
+        assert!(!has_public_suffix(&list, "foo.invalidtld"));
+
+        let content = "# 注释\n0.0.0.0 a.com\n0.0.0.0 b.com foo.invalidtld # 行尾\n0.0.0.0 junk\n";
+        let (filtered, removed) = filter_public_suffix(content, &list);
+        assert_eq!(filtered, "# 注释\n0.0.0.0 a.com\n0.0.0.0 b.com # 行尾\n");
+        assert_eq!(removed, 2);
+    }
+}
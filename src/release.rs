@@ -0,0 +1,136 @@
+//! 新版本检查模块
+//!
+//! 查询项目发布页的最新版本，与编译时的版本比较，供 `check-update` 子命令提示是否有新版本。
+//! 只提示，不会下载或替换程序。实际查询需启用 `check-update` 特性。
+
+use crate::config::Config;
+use anyhow::Result;
+
+/// 默认查询的最新发布 API（GitHub Releases）
+pub const DEFAULT_RELEASE_API_URL: &str =
+    "https://api.github.com/repos/wangmingfa/hosts_updater_rs/releases/latest";
+
+/// 编译时的程序版本
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// 发布页中的最新版本
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LatestRelease {
+    /// 版本号（去掉标签的 `v` 前缀）
+    pub version: String,
+    /// 发布页地址
+    pub url: Option<String>,
+}
+
+impl LatestRelease {
+    /// 是否比当前运行的版本更新
+    pub fn is_newer(&self) -> Result<bool> {
+        is_newer_version(&self.version, CURRENT_VERSION)
+    }
+}
+
+/// 比较两个 `主.次.修订[-预发布]` 形式的版本号，`latest` 更新时返回 true
+///
+/// 版本号相同时，正式版比预发布版更新；预发布版之间不比较先后。
+pub fn is_newer_version(latest: &str, current: &str) -> Result<bool> {
+    let (latest_core, latest_pre) = parse_version(latest)?;
+    let (current_core, current_pre) = parse_version(current)?;
+    Ok(latest_core > current_core || (latest_core == current_core && current_pre && !latest_pre))
+}
+
+/// 解析版本号，返回数字部分与是否为预发布版
+fn parse_version(version: &str) -> Result<([u64; 3], bool)> {
+    let version = version.trim().trim_start_matches('v');
+    // 构建元数据（`+` 之后）不参与比较
+    let without_build = version.split('+').next().unwrap_or_default();
+    let (core, pre) = match without_build.split_once('-') {
+        Some((core, _)) => (core, true),
+        None => (without_build, false),
+    };
+
+    let mut parts = [0; 3];
+    for (i, part) in core.split('.').enumerate() {
+        let slot = parts
+            .get_mut(i)
+            .ok_or_else(|| anyhow::anyhow!("无效的版本号: {}", version))?;
+        *slot = part
+            .parse()
+            .map_err(|_| anyhow::anyhow!("无效的版本号: {}", version))?;
+    }
+
+    Ok((parts, pre))
+}
+
+/// 查询最新发布的版本
+///
+/// `api_url` 须返回 GitHub Releases API 格式的 JSON（含 `tag_name`，可选 `html_url`）。
+#[cfg(feature = "check-update")]
+pub fn fetch_latest_release(api_url: &str, config: &Config) -> Result<LatestRelease> {
+    use anyhow::Context;
+
+    #[derive(serde::Deserialize)]
+    struct Release {
+        tag_name: String,
+        html_url: Option<String>,
+    }
+
+    let client = crate::fetcher::build_client(config)?;
+    let response = client
+        .get(api_url)
+        .header(
+            reqwest::header::USER_AGENT,
+            concat!("hosts_updater_rs/", env!("CARGO_PKG_VERSION")),
+        )
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+        .send()
+        .with_context(|| format!("查询最新版本失败: {}", api_url))?;
+
+    if !response.status().is_success() {
+        return Err(anyhow::anyhow!(
+            "查询最新版本失败，HTTP 状态码: {}",
+            response.status()
+        ));
+    }
+
+    let body = response
+        .text()
+        .with_context(|| format!("读取发布信息失败: {}", api_url))?;
+    let release: Release =
+        serde_json::from_str(&body).with_context(|| format!("解析发布信息失败: {}", api_url))?;
+    let version = release.tag_name.trim_start_matches('v').to_string();
+    parse_version(&version)?;
+
+    Ok(LatestRelease {
+        version,
+        url: release.html_url,
+    })
+}
+
+/// 未启用 `check-update` 特性时无法查询最新版本
+#[cfg(not(feature = "check-update"))]
+pub fn fetch_latest_release(api_url: &str, _config: &Config) -> Result<LatestRelease> {
+    Err(anyhow::anyhow!(
+        "检查新版本需要启用 check-update 特性: {}",
+        api_url
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_newer_version() {
+        assert!(is_newer_version("v0.2.0", "0.1.9").unwrap());
+        assert!(is_newer_version("1.0.0", "0.9.12").unwrap());
+        assert!(is_newer_version("0.1.10", "0.1.9").unwrap());
+        assert!(!is_newer_version("0.1.0", "0.1.0").unwrap());
+        assert!(!is_newer_version("0.1.0", "0.2.0").unwrap());
+        assert!(is_newer_version("0.2.0", "0.2.0-beta.1").unwrap());
+        assert!(!is_newer_version("0.2.0-rc.1", "0.2.0").unwrap());
+        assert!(!is_newer_version("0.2.0+build.5", "0.2.0").unwrap());
+        assert!(is_newer_version("0.2", "0.1.0").unwrap());
+        assert!(is_newer_version("latest", "0.1.0").is_err());
+        assert!(is_newer_version("1.2.3.4", "0.1.0").is_err());
+    }
+}
@@ -0,0 +1,234 @@
+//! 远程配置模块
+//!
+//! 通过 `--remote-config` 从中心 HTTPS 地址下载配置，便于统一管理多台主机。
+//! 下载并验证通过的配置会缓存到本地，远程地址不可达时回退到缓存的配置；
+//! 可固定配置内容的 SHA-256，拒绝中心服务器下发被篡改的配置。
+
+use crate::config::{parse_config_with_format, validate_config, Config, ConfigFormat};
+use crate::fetcher::{sha256_hex, DEFAULT_TIMEOUT_SECS};
+use crate::state::get_cache_dir;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use reqwest::blocking::Client;
+use reqwest::header::CONTENT_TYPE;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// 远程配置缓存文件名（位于默认缓存目录，远程配置加载前无法得知其中的 `cache_dir`）
+const REMOTE_CONFIG_CACHE_FILE: &str = "remote_config.json";
+
+/// 本地缓存的远程配置
+#[derive(Debug, Serialize, Deserialize)]
+struct RemoteConfigCache {
+    /// 配置的远程地址，与本次地址不同时不使用该缓存
+    url: String,
+    /// 下载时判断出的配置格式（扩展名），未能判断时依次尝试所有格式
+    format: Option<String>,
+    /// 下载时间
+    fetched_at: DateTime<Utc>,
+    /// 配置原文
+    content: String,
+}
+
+/// 远程配置缓存文件路径
+pub fn remote_config_cache_path() -> PathBuf {
+    get_cache_dir(&None).join(REMOTE_CONFIG_CACHE_FILE)
+}
+
+/// 从远程地址加载配置
+///
+/// 格式优先使用 `format`，其次按 URL 扩展名与响应的 Content-Type 判断，都无法判断时依次尝试。
+/// 配置了 `sha256` 时内容须与之一致；解析与验证通过后才写入本地缓存。
+/// 仅在远程地址不可达时回退到缓存，校验、解析或验证失败时直接返回错误。
+pub fn load_remote_config(
+    url: &str,
+    sha256: Option<&str>,
+    format: Option<ConfigFormat>,
+) -> Result<Config> {
+    load_remote_config_with_cache(url, sha256, format, &remote_config_cache_path())
+}
+
+fn load_remote_config_with_cache(
+    url: &str,
+    sha256: Option<&str>,
+    format: Option<ConfigFormat>,
+    cache_path: &Path,
+) -> Result<Config> {
+    let (content, content_type) = match download_config(url) {
+        Ok(downloaded) => downloaded,
+        Err(e) => {
+            warn!("下载远程配置失败，尝试使用本地缓存: {:?}", e);
+            return load_cached_remote_config(url, sha256, cache_path)
+                .with_context(|| format!("远程配置不可用且没有可用的本地缓存: {}", url));
+        }
+    };
+
+    let format = format.or_else(|| url_format(url)).or_else(|| {
+        content_type
+            .as_deref()
+            .and_then(ConfigFormat::from_content_type)
+    });
+    let config = parse_remote_config(url, &content, sha256, format)?;
+
+    let cache = RemoteConfigCache {
+        url: url.to_string(),
+        format: format.map(|format| format.extension().to_string()),
+        fetched_at: Utc::now(),
+        content,
+    };
+    if let Err(e) = save_remote_config_cache(&cache, cache_path) {
+        warn!("保存远程配置缓存失败: {:?}", e);
+    }
+
+    info!("已加载远程配置: {}", url);
+    Ok(config)
+}
+
+/// 下载远程配置，返回内容与响应的 Content-Type
+fn download_config(url: &str) -> Result<(String, Option<String>)> {
+    let client = Client::builder()
+        .timeout(Duration::from_secs(DEFAULT_TIMEOUT_SECS))
+        .build()
+        .context("创建 HTTP 客户端失败")?;
+
+    let response = client
+        .get(url)
+        .send()
+        .with_context(|| format!("请求远程配置失败: {}", url))?;
+
+    if !response.status().is_success() {
+        return Err(anyhow::anyhow!(
+            "请求远程配置失败，HTTP 状态码: {}",
+            response.status()
+        ));
+    }
+
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let content = response
+        .text()
+        .with_context(|| format!("读取远程配置失败: {}", url))?;
+
+    Ok((content, content_type))
+}
+
+/// 按 URL 路径的扩展名判断配置格式（忽略查询参数）
+fn url_format(url: &str) -> Option<ConfigFormat> {
+    let url = reqwest::Url::parse(url).ok()?;
+    ConfigFormat::from_path(url.path())
+}
+
+/// 校验固定的 SHA-256 后解析并验证远程配置
+fn parse_remote_config(
+    url: &str,
+    content: &str,
+    sha256: Option<&str>,
+    format: Option<ConfigFormat>,
+) -> Result<Config> {
+    if let Some(expected) = sha256 {
+        let actual = sha256_hex(content);
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(anyhow::anyhow!(
+                "远程配置的 SHA-256 不匹配，拒绝使用: {}（期望 {}，实际 {}）",
+                url,
+                expected,
+                actual
+            ));
+        }
+    }
+
+    let config = parse_config_with_format(content, format, url)?;
+    validate_config(&config).with_context(|| format!("远程配置验证失败: {}", url))?;
+    Ok(config)
+}
+
+/// 读取本地缓存的远程配置，缓存内容同样须通过 SHA-256 校验与配置验证
+fn load_cached_remote_config(url: &str, sha256: Option<&str>, cache_path: &Path) -> Result<Config> {
+    let content = fs::read_to_string(cache_path)
+        .with_context(|| format!("读取远程配置缓存失败: {:?}", cache_path))?;
+    let cache: RemoteConfigCache = serde_json::from_str(&content)
+        .with_context(|| format!("解析远程配置缓存失败: {:?}", cache_path))?;
+
+    if cache.url != url {
+        return Err(anyhow::anyhow!("远程配置缓存来自其他地址: {}", cache.url));
+    }
+
+    let format = cache.format.as_deref().and_then(|ext| ext.parse().ok());
+    let config = parse_remote_config(url, &cache.content, sha256, format)?;
+    warn!(
+        "使用本地缓存的远程配置（下载于 {}）: {}",
+        cache
+            .fetched_at
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M:%S"),
+        url
+    );
+    Ok(config)
+}
+
+/// 保存远程配置缓存
+fn save_remote_config_cache(cache: &RemoteConfigCache, cache_path: &Path) -> Result<()> {
+    if let Some(dir) = cache_path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("创建缓存目录失败: {:?}", dir))?;
+    }
+    let content = serde_json::to_string_pretty(cache).context("序列化远程配置缓存失败")?;
+    fs::write(cache_path, content)
+        .with_context(|| format!("写入远程配置缓存失败: {:?}", cache_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remote_config_cache_fallback() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = dir.path().join(REMOTE_CONFIG_CACHE_FILE);
+        let url = "http://127.0.0.1:1/hosts_updater.toml";
+        let content = "hosts_sources = [\"https://example.com/hosts\"]\n";
+
+        // 远程不可达且没有缓存
+        assert!(load_remote_config_with_cache(url, None, None, &cache_path).is_err());
+
+        let cache = RemoteConfigCache {
+            url: url.to_string(),
+            format: Some("toml".to_string()),
+            fetched_at: Utc::now(),
+            content: content.to_string(),
+        };
+        save_remote_config_cache(&cache, &cache_path).unwrap();
+
+        let sha256 = sha256_hex(content);
+        let config = load_remote_config_with_cache(url, Some(&sha256), None, &cache_path).unwrap();
+        assert_eq!(config.hosts_sources[0].url(), "https://example.com/hosts");
+
+        // 固定值不匹配或地址不同时拒绝使用缓存
+        assert!(load_remote_config_with_cache(url, Some("00"), None, &cache_path).is_err());
+        let other = "http://127.0.0.1:1/other.toml";
+        assert!(load_remote_config_with_cache(other, None, None, &cache_path).is_err());
+    }
+
+    #[test]
+    fn test_remote_config_format() {
+        assert_eq!(
+            url_format("https://example.com/a/config.yaml?token=1"),
+            Some(ConfigFormat::Yaml)
+        );
+        assert_eq!(url_format("https://example.com/config"), None);
+        assert_eq!(
+            ConfigFormat::from_content_type("application/json; charset=utf-8"),
+            Some(ConfigFormat::Json)
+        );
+        assert_eq!(
+            ConfigFormat::from_content_type("application/x-yaml"),
+            Some(ConfigFormat::Yaml)
+        );
+        assert_eq!(ConfigFormat::from_content_type("text/plain"), None);
+    }
+}
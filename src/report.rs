@@ -0,0 +1,147 @@
+//! 检查报告模块
+//!
+//! 汇总试运行检查（`--dry-run --check`）发现的问题，按纯文本或 GitHub Actions 工作流命令
+//! （`::error::` / `::warning::`）格式输出，便于在 CI 界面中直接显示。
+
+use anyhow::Result;
+
+/// 检查报告的输出格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// 纯文本，每行一个问题：`error: ...` / `warning: ...`
+    #[default]
+    Text,
+    /// GitHub Actions 工作流命令，CI 界面中显示为注解
+    Github,
+}
+
+impl std::str::FromStr for ReportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(ReportFormat::Text),
+            "github" => Ok(ReportFormat::Github),
+            _ => Err(anyhow::anyhow!(
+                "不支持的报告格式: {}（可选 text、github）",
+                s
+            )),
+        }
+    }
+}
+
+/// 问题的严重程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// 错误：检查以非零状态退出
+    Error,
+    /// 警告：仅提示
+    Warning,
+}
+
+/// 检查发现的单个问题
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// 严重程度
+    pub severity: Severity,
+    /// 问题说明
+    pub message: String,
+    /// 问题所在的文件，GitHub 格式中用于将注解关联到该文件
+    pub file: Option<String>,
+}
+
+impl Finding {
+    /// 错误
+    pub fn error(message: impl Into<String>) -> Self {
+        Finding {
+            severity: Severity::Error,
+            message: message.into(),
+            file: None,
+        }
+    }
+
+    /// 警告
+    pub fn warning(message: impl Into<String>) -> Self {
+        Finding {
+            severity: Severity::Warning,
+            message: message.into(),
+            file: None,
+        }
+    }
+
+    /// 关联到指定文件
+    pub fn with_file(mut self, file: impl Into<String>) -> Self {
+        self.file = Some(file.into());
+        self
+    }
+}
+
+/// 按指定格式输出所有问题，每个问题一行
+pub fn render_findings(findings: &[Finding], format: ReportFormat) -> String {
+    let mut output = String::new();
+    for finding in findings {
+        // 文本格式的前缀与工作流命令名相同
+        let level = match finding.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        let line = match format {
+            ReportFormat::Text => match &finding.file {
+                Some(file) => format!("{}: {}: {}\n", level, file, finding.message),
+                None => format!("{}: {}\n", level, finding.message),
+            },
+            ReportFormat::Github => {
+                let mut properties = vec!["title=hosts_updater_rs".to_string()];
+                if let Some(file) = &finding.file {
+                    properties.push(format!("file={}", escape_property(file)));
+                }
+                format!(
+                    "::{} {}::{}\n",
+                    level,
+                    properties.join(","),
+                    escape_data(&finding.message)
+                )
+            }
+        };
+        output.push_str(&line);
+    }
+    output
+}
+
+/// 转义工作流命令的消息内容，多行消息在注解中保持换行
+fn escape_data(value: &str) -> String {
+    value
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// 转义工作流命令的属性值，其中的 `:` 与 `,` 会被当作分隔符
+fn escape_property(value: &str) -> String {
+    escape_data(value).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_findings() {
+        let findings = vec![
+            Finding::warning("hosts 文件已 30 小时未更新").with_file("hosts.snapshot"),
+            Finding::error("获取失败: https://a.example/hosts\n100% 超时"),
+        ];
+
+        assert_eq!(
+            render_findings(&findings, ReportFormat::Text),
+            "warning: hosts.snapshot: hosts 文件已 30 小时未更新\n\
+             error: 获取失败: https://a.example/hosts\n100% 超时\n"
+        );
+        assert_eq!(
+            render_findings(&findings, ReportFormat::Github),
+            "::warning title=hosts_updater_rs,file=hosts.snapshot::hosts 文件已 30 小时未更新\n\
+             ::error title=hosts_updater_rs::获取失败: https://a.example/hosts%0A100%25 超时\n"
+        );
+        assert!("json".parse::<ReportFormat>().is_err());
+    }
+}
@@ -2,14 +2,20 @@
 //!
 //! 提供定时执行任务的功能。
 
+use crate::config::MAX_UPDATE_INTERVAL_HOURS;
+use anyhow::Result;
+use chrono::{Datelike, Local, NaiveDateTime, NaiveTime, Weekday};
 use std::future::Future;
+use std::path::PathBuf;
 use std::pin::Pin;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 use tokio::time;
 
 /// 定时任务配置
 pub struct Scheduler {
     interval_hours: u64,
+    pause_file: Option<PathBuf>,
+    window: Option<UpdateWindow>,
 }
 
 impl Scheduler {
@@ -19,16 +25,43 @@ impl Scheduler {
     ///
     /// * `interval_hours` - 更新间隔时间（小时）
     pub fn new(interval_hours: u64) -> Self {
-        Self { interval_hours }
+        Self {
+            interval_hours,
+            pause_file: None,
+            window: None,
+        }
+    }
+
+    /// 设置暂停标记文件，该文件存在期间跳过每次到期的任务（由 `pause` / `resume` 子命令管理）
+    pub fn with_pause_file(mut self, path: PathBuf) -> Self {
+        self.pause_file = Some(path);
+        self
+    }
+
+    /// 设置允许更新的时段，到期时不在时段内的任务推迟到下一个时段开始时执行
+    pub fn with_window(mut self, window: Option<UpdateWindow>) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// 暂停标记文件是否存在
+    fn is_paused(&self) -> bool {
+        self.pause_file.as_ref().is_some_and(|path| path.exists())
     }
 
-    /// 获取更新间隔时间
+    /// 获取更新间隔时间，最长 [`MAX_UPDATE_INTERVAL_HOURS`] 小时
     pub fn interval(&self) -> Duration {
-        Duration::from_secs(self.interval_hours * 3600)
+        Duration::from_secs(self.interval_hours.min(MAX_UPDATE_INTERVAL_HOURS) * 3600)
     }
 
     /// 启动定时任务
     ///
+    /// 下次运行时间按墙上时钟计算（本次开始时间加上间隔），等待时每隔一段时间检查一次时钟，
+    /// 因此系统休眠唤醒后能及时发现已错过的更新。无论错过多少个周期都只立即补执行一次，
+    /// 之后按补执行的时间重新计算下次运行时间；时钟被回拨时等待时间不超过一个间隔。
+    /// 暂停期间到期的任务直接跳过，仍按间隔计算下次运行时间。
+    /// 设置了允许更新的时段时，到期的任务等到下一个时段开始才执行，并从实际执行时间重新计算。
+    ///
     /// # Arguments
     ///
     /// * `task` - 要定时执行的任务闭包
@@ -41,19 +74,132 @@ impl Scheduler {
             self.interval_hours
         );
 
-        // 立即执行一次
-        task().await;
+        loop {
+            self.wait_for_window().await;
+            let started = SystemTime::now();
+            if self.is_paused() {
+                tracing::info!("定时更新已暂停，跳过本次更新");
+            } else {
+                task().await;
+            }
+
+            // 间隔最长 365 天，加到当前时间上不会溢出
+            let mut next = started + self.interval();
+            while let Some(remaining) = remaining_until(&mut next, SystemTime::now(), self.interval())
+            {
+                time::sleep(remaining.min(WALL_CLOCK_POLL)).await;
+            }
+        }
+    }
+
+    /// 当前不在允许更新的时段内时，等待到下一个时段开始
+    async fn wait_for_window(&self) {
+        let Some(window) = &self.window else {
+            return;
+        };
 
-        // 定时执行
-        let mut interval = time::interval(self.interval());
+        let mut logged = false;
         loop {
-            interval.tick().await;
-            task().await;
+            let now = Local::now().naive_local();
+            let Some(start) = window.next_start(now) else {
+                return;
+            };
+            if !logged {
+                tracing::info!("当前不在允许的更新时段内，本次更新推迟到 {}", start);
+                logged = true;
+            }
+            let remaining = (start - now).to_std().unwrap_or_default();
+            time::sleep(remaining.min(WALL_CLOCK_POLL)).await;
+        }
+    }
+}
+
+/// 允许定时更新的时段：每天的时间范围与星期几，均按本地时间判断
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpdateWindow {
+    /// 每天允许更新的时间范围 `[开始, 结束)`，结束早于开始时跨越午夜
+    hours: Option<(NaiveTime, NaiveTime)>,
+    /// 允许更新的星期几，为空时不限制
+    days: Vec<Weekday>,
+}
+
+impl UpdateWindow {
+    /// 解析 `allowed_hours`（如 `"02:00-05:00"`）与 `allowed_days`（如 `["sat", "sun"]`），均未配置时返回 `None`
+    pub fn parse(hours: Option<&str>, days: &[String]) -> Result<Option<Self>> {
+        let hours = hours.map(parse_hours).transpose()?;
+        let days = days
+            .iter()
+            .map(|day| {
+                day.trim()
+                    .parse::<Weekday>()
+                    .map_err(|_| anyhow::anyhow!("allowed_days 中的星期无效: {}", day))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        if hours.is_none() && days.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(UpdateWindow { hours, days }))
+    }
+
+    /// 指定时间是否位于允许更新的时段内
+    ///
+    /// 跨越午夜的时间范围中，午夜之后的部分按当天（而不是前一天）的星期判断。
+    pub fn contains(&self, time: NaiveDateTime) -> bool {
+        let day_allowed = self.days.is_empty() || self.days.contains(&time.weekday());
+        let hour_allowed = self.hours.is_none_or(|(start, end)| {
+            let t = time.time();
+            if start < end {
+                start <= t && t < end
+            } else {
+                t >= start || t < end
+            }
+        });
+        day_allowed && hour_allowed
+    }
+
+    /// 下一个时段的开始时间，`now` 已位于时段内时返回 `None`
+    pub fn next_start(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        if self.contains(now) {
+            return None;
         }
+
+        // 时段只可能从每天的开始时间或午夜开始，一周之内必然出现
+        let start = self.hours.map_or(NaiveTime::MIN, |(start, _)| start);
+        (0..=7)
+            .filter_map(|offset| now.date().checked_add_days(chrono::Days::new(offset)))
+            .flat_map(|date| [date.and_time(NaiveTime::MIN), date.and_time(start)])
+            .filter(|candidate| *candidate > now && self.contains(*candidate))
+            .min()
     }
+}
 
+/// 解析 `HH:MM-HH:MM` 形式的时间范围
+fn parse_hours(hours: &str) -> Result<(NaiveTime, NaiveTime)> {
+    let invalid = || anyhow::anyhow!("allowed_hours 格式无效（应为 HH:MM-HH:MM）: {}", hours);
+    let (start, end) = hours.split_once('-').ok_or_else(invalid)?;
+    let start = NaiveTime::parse_from_str(start.trim(), "%H:%M").map_err(|_| invalid())?;
+    let end = NaiveTime::parse_from_str(end.trim(), "%H:%M").map_err(|_| invalid())?;
+    if start == end {
+        return Err(anyhow::anyhow!("allowed_hours 的开始与结束时间不能相同: {}", hours));
+    }
+    Ok((start, end))
 }
 
+/// 等待期间检查墙上时钟的最长间隔，休眠唤醒后最多延迟该时长即开始补执行
+const WALL_CLOCK_POLL: Duration = Duration::from_secs(60);
+
+/// 计算距下次运行的剩余时间，已到达或已错过下次运行时间时返回 `None`
+///
+/// 剩余时间超过一个间隔说明时钟被回拨，此时将下次运行时间调整为当前时间加一个间隔。
+fn remaining_until(next: &mut SystemTime, now: SystemTime, interval: Duration) -> Option<Duration> {
+    let remaining = next.duration_since(now).ok().filter(|remaining| !remaining.is_zero())?;
+    if remaining > interval {
+        *next = now + interval;
+        return Some(interval);
+    }
+    Some(remaining)
+}
 
 #[cfg(test)]
 mod tests {
@@ -61,6 +207,73 @@ mod tests {
     use std::sync::atomic::{AtomicUsize, Ordering};
     use std::sync::Arc;
 
+    #[test]
+    fn test_remaining_until() {
+        let interval = Duration::from_secs(3600);
+        let now = SystemTime::now();
+
+        let mut next = now + Duration::from_secs(600);
+        assert_eq!(remaining_until(&mut next, now, interval), Some(Duration::from_secs(600)));
+
+        // 休眠错过了多个周期：只返回 None 一次，由调用方立即补执行
+        let mut next = now - interval * 5;
+        assert_eq!(remaining_until(&mut next, now, interval), None);
+
+        // 时钟被回拨：最多再等待一个间隔
+        let mut next = now + interval * 3;
+        assert_eq!(remaining_until(&mut next, now, interval), Some(interval));
+        assert_eq!(next, now + interval);
+    }
+
+    #[test]
+    fn test_interval_is_capped() {
+        assert_eq!(Scheduler::new(2).interval(), Duration::from_secs(2 * 3600));
+        let max = Duration::from_secs(MAX_UPDATE_INTERVAL_HOURS * 3600);
+        assert_eq!(Scheduler::new(u64::MAX).interval(), max);
+
+        let mut config = crate::config::Config::from_sources(vec!["https://a.example".to_string()]);
+        config.update_interval_hours = MAX_UPDATE_INTERVAL_HOURS;
+        assert!(crate::config::validate_config(&config).is_ok());
+        config.update_interval_hours = MAX_UPDATE_INTERVAL_HOURS + 1;
+        assert!(crate::config::validate_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_scheduler_pause_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("paused");
+
+        assert!(!Scheduler::new(1).is_paused());
+        let scheduler = Scheduler::new(1).with_pause_file(path.clone());
+        assert!(!scheduler.is_paused());
+        std::fs::write(&path, "").unwrap();
+        assert!(scheduler.is_paused());
+    }
+
+    #[test]
+    fn test_update_window() {
+        let at = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+        assert_eq!(UpdateWindow::parse(None, &[]).unwrap(), None);
+        assert!(UpdateWindow::parse(Some("2:00"), &[]).is_err());
+        assert!(UpdateWindow::parse(Some("02:00-02:00"), &[]).is_err());
+        assert!(UpdateWindow::parse(None, &["someday".to_string()]).is_err());
+
+        // 2024-01-01 是星期一
+        let overnight = UpdateWindow::parse(Some("02:00-05:00"), &[]).unwrap().unwrap();
+        assert_eq!(overnight.next_start(at("2024-01-01 03:00")), None);
+        assert_eq!(overnight.next_start(at("2024-01-01 01:00")), Some(at("2024-01-01 02:00")));
+        assert_eq!(overnight.next_start(at("2024-01-01 05:00")), Some(at("2024-01-02 02:00")));
+
+        // 跨越午夜且只在周末
+        let days = ["sat".to_string(), "Sunday".to_string()];
+        let weekend = UpdateWindow::parse(Some("22:00-06:00"), &days).unwrap().unwrap();
+        assert!(weekend.contains(at("2024-01-06 23:00")));
+        assert!(weekend.contains(at("2024-01-07 05:00")));
+        assert!(!weekend.contains(at("2024-01-08 05:00")));
+        assert_eq!(weekend.next_start(at("2024-01-03 12:00")), Some(at("2024-01-06 00:00")));
+        assert_eq!(weekend.next_start(at("2024-01-06 12:00")), Some(at("2024-01-06 22:00")));
+    }
+
     #[tokio::test]
     async fn test_scheduler_run() {
         let counter = Arc::new(AtomicUsize::new(0));
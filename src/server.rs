@@ -0,0 +1,180 @@
+//! HTTP 服务模块
+//!
+//! 启用 `serve` 特性后，在 `/hosts` 路径提供最近一次生成的 hosts 条目，
+//! 供局域网内的其他实例作为数据源使用。响应带有 `ETag` 与 `Last-Modified`，
+//! 支持条件请求。
+
+use anyhow::{Context, Result};
+use axum::Router;
+use axum::extract::State;
+use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use chrono::{DateTime, Utc};
+use std::sync::{Arc, RwLock};
+
+/// HTTP 日期格式（RFC 7231）
+const HTTP_DATE_FORMAT: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+/// 对外提供的 hosts 内容快照
+#[derive(Debug, Clone)]
+struct Snapshot {
+    content: String,
+    etag: String,
+    last_modified: DateTime<Utc>,
+}
+
+/// 在更新任务与 HTTP 服务之间共享的 hosts 内容
+#[derive(Debug, Clone, Default)]
+pub struct ServedHosts {
+    inner: Arc<RwLock<Option<Snapshot>>>,
+}
+
+impl ServedHosts {
+    /// 发布新生成的内容
+    ///
+    /// 内容未变化时保持原有的 `ETag` 与 `Last-Modified`，下游可继续命中缓存。
+    pub fn publish(&self, content: String) {
+        let etag = compute_etag(&content);
+        let mut guard = self.inner.write().unwrap_or_else(|e| e.into_inner());
+
+        if guard.as_ref().is_some_and(|snapshot| snapshot.etag == etag) {
+            return;
+        }
+
+        *guard = Some(Snapshot {
+            content,
+            etag,
+            last_modified: Utc::now(),
+        });
+    }
+
+    /// 获取当前快照
+    fn snapshot(&self) -> Option<Snapshot> {
+        self.inner.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+/// 启动 HTTP 服务，监听所有网卡的指定端口
+pub async fn serve(port: u16, hosts: ServedHosts) -> Result<()> {
+    let app = Router::new()
+        .route("/hosts", get(get_hosts))
+        .with_state(hosts);
+
+    let listener = tokio::net::TcpListener::bind(("0.0.0.0", port))
+        .await
+        .with_context(|| format!("监听端口失败: {}", port))?;
+    tracing::info!("HTTP 服务已启动: http://0.0.0.0:{}/hosts", port);
+
+    axum::serve(listener, app)
+        .await
+        .context("HTTP 服务异常退出")
+}
+
+/// 处理 `/hosts` 请求
+async fn get_hosts(State(hosts): State<ServedHosts>, headers: HeaderMap) -> Response {
+    let Some(snapshot) = hosts.snapshot() else {
+        return (StatusCode::SERVICE_UNAVAILABLE, "hosts 内容尚未生成\n").into_response();
+    };
+
+    let last_modified = snapshot.last_modified.format(HTTP_DATE_FORMAT).to_string();
+    let mut response = if is_not_modified(&headers, &snapshot) {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        snapshot.content.into_response()
+    };
+
+    let response_headers = response.headers_mut();
+    if let Ok(value) = HeaderValue::from_str(&snapshot.etag) {
+        response_headers.insert(header::ETAG, value);
+    }
+    if let Ok(value) = HeaderValue::from_str(&last_modified) {
+        response_headers.insert(header::LAST_MODIFIED, value);
+    }
+
+    response
+}
+
+/// 判断条件请求是否命中
+///
+/// 优先使用 `If-None-Match`，仅在未提供时才比较 `If-Modified-Since`。
+fn is_not_modified(headers: &HeaderMap, snapshot: &Snapshot) -> bool {
+    if let Some(value) = headers.get(header::IF_NONE_MATCH) {
+        return value.to_str().is_ok_and(|tags| {
+            tags.split(',')
+                .map(|tag| tag.trim().trim_start_matches("W/"))
+                .any(|tag| tag == "*" || tag == snapshot.etag)
+        });
+    }
+
+    headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| DateTime::parse_from_rfc2822(value).ok())
+        .is_some_and(|since| snapshot.last_modified.timestamp() <= since.timestamp())
+}
+
+/// 根据内容计算 ETag
+///
+/// 取内容 SHA-256 的前 32 个十六进制字符，相同内容在重新编译与重启后得到相同的 ETag。
+fn compute_etag(content: &str) -> String {
+    format!("\"{}\"", &crate::fetcher::sha256_hex(content)[..32])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(name: header::HeaderName, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_compute_etag() {
+        // 固定值：与编译器版本和进程无关
+        assert_eq!(compute_etag(""), "\"e3b0c44298fc1c149afbf4c8996fb924\"");
+    }
+
+    #[test]
+    fn test_publish_keeps_etag_for_same_content() {
+        let hosts = ServedHosts::default();
+        hosts.publish("0.0.0.0 a.com\n".to_string());
+        let first = hosts.snapshot().unwrap();
+
+        hosts.publish("0.0.0.0 a.com\n".to_string());
+        let second = hosts.snapshot().unwrap();
+        assert_eq!(first.etag, second.etag);
+        assert_eq!(first.last_modified, second.last_modified);
+
+        hosts.publish("0.0.0.0 b.com\n".to_string());
+        assert_ne!(hosts.snapshot().unwrap().etag, first.etag);
+    }
+
+    #[test]
+    fn test_is_not_modified() {
+        let hosts = ServedHosts::default();
+        hosts.publish("0.0.0.0 a.com\n".to_string());
+        let snapshot = hosts.snapshot().unwrap();
+
+        let matching = headers(header::IF_NONE_MATCH, &snapshot.etag);
+        assert!(is_not_modified(&matching, &snapshot));
+        let stale = headers(header::IF_NONE_MATCH, "\"0000\"");
+        assert!(!is_not_modified(&stale, &snapshot));
+
+        let since = snapshot.last_modified.format(HTTP_DATE_FORMAT).to_string();
+        assert!(is_not_modified(
+            &headers(header::IF_MODIFIED_SINCE, &since),
+            &snapshot
+        ));
+        let earlier = (snapshot.last_modified - chrono::Duration::hours(1))
+            .format(HTTP_DATE_FORMAT)
+            .to_string();
+        assert!(!is_not_modified(
+            &headers(header::IF_MODIFIED_SINCE, &earlier),
+            &snapshot
+        ));
+        assert!(!is_not_modified(&HeaderMap::new(), &snapshot));
+    }
+}
@@ -0,0 +1,363 @@
+//! 运行状态模块
+//!
+//! 提供跨运行持久化的状态数据（如上次写入的条目数、各数据源的获取状态）的读写功能。
+
+use crate::fetcher::SourceResult;
+use crate::i18n::{locale, sources_table_header};
+use crate::paths::default_cache_dir;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, Utc};
+use comfy_table::{Table, presets::UTF8_FULL};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// 状态文件名
+const STATE_FILE_NAME: &str = "state.json";
+
+/// 暂停标记文件名，存在时定时任务跳过更新
+const PAUSE_FILE_NAME: &str = "paused";
+
+/// 持久化的运行状态
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct State {
+    /// 上次成功写入的条目总数
+    #[serde(default)]
+    pub last_entry_count: Option<usize>,
+    /// 上次成功写入的数据源内容（预处理后）总字节数
+    #[serde(default)]
+    pub last_content_bytes: Option<usize>,
+    /// 各数据源最近一次的获取状态
+    #[serde(default)]
+    pub sources: Vec<SourceStatus>,
+}
+
+/// 数据源获取结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FetchStatus {
+    /// 获取成功
+    Ok,
+    /// 内容未变化（HTTP 304）
+    NotModified,
+    /// 获取失败后使用了本地缓存
+    Cached,
+    /// 获取失败
+    Failed,
+}
+
+impl std::fmt::Display for FetchStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            FetchStatus::Ok => "ok",
+            FetchStatus::NotModified => "304",
+            FetchStatus::Cached => "cached",
+            FetchStatus::Failed => "failed",
+        };
+        f.write_str(text)
+    }
+}
+
+/// 单个数据源最近一次的获取状态
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceStatus {
+    /// 数据源 URL
+    pub url: String,
+    /// 获取时间
+    pub fetched_at: DateTime<Utc>,
+    /// 获取结果
+    pub status: FetchStatus,
+    /// 内容字节数（失败时为 0）
+    pub bytes: usize,
+    /// 有效条目数（失败时为 0）
+    pub entries: usize,
+    /// 失败原因
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl SourceStatus {
+    /// 由获取结果生成状态
+    pub fn from_result(source: &SourceResult) -> Self {
+        let status = if source.from_cache {
+            FetchStatus::Cached
+        } else if source.status == 304 {
+            FetchStatus::NotModified
+        } else {
+            FetchStatus::Ok
+        };
+
+        SourceStatus {
+            url: source.url.clone(),
+            fetched_at: source.fetched_at,
+            status,
+            bytes: source.bytes,
+            entries: source.entry_count(),
+            error: None,
+        }
+    }
+}
+
+impl State {
+    /// 用本次写入的数据源替换全部状态
+    ///
+    /// 使用缓存写入时保留之前记录的失败原因与时间，仅补充缓存中的数据源。
+    pub fn record_sources(&mut self, sources: &[SourceResult]) {
+        let previous = std::mem::take(&mut self.sources);
+        self.sources = sources
+            .iter()
+            .map(|source| {
+                let failed = previous
+                    .iter()
+                    .find(|s| s.url == source.url && s.status == FetchStatus::Failed);
+                match failed {
+                    Some(failed) if source.from_cache => failed.clone(),
+                    _ => SourceStatus::from_result(source),
+                }
+            })
+            .collect();
+    }
+
+    /// 记录数据源获取失败，其余数据源的状态保持不变
+    pub fn record_failure(&mut self, url: &str, error: &str) {
+        let status = SourceStatus {
+            url: url.to_string(),
+            fetched_at: Utc::now(),
+            status: FetchStatus::Failed,
+            bytes: 0,
+            entries: 0,
+            error: Some(error.to_string()),
+        };
+
+        match self.sources.iter_mut().find(|s| s.url == url) {
+            Some(existing) => *existing = status,
+            None => self.sources.push(status),
+        }
+    }
+}
+
+/// 将数据源状态渲染为对齐的文本表格
+pub fn render_sources_table(sources: &[SourceStatus]) -> String {
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    table.set_header(sources_table_header(locale()));
+
+    for source in sources {
+        let failed = source.status == FetchStatus::Failed;
+        let number = |n: usize| if failed { "-".to_string() } else { n.to_string() };
+        table.add_row(vec![
+            source.url.clone(),
+            source
+                .fetched_at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string(),
+            source.status.to_string(),
+            number(source.bytes),
+            number(source.entries),
+        ]);
+    }
+
+    table.to_string()
+}
+
+/// 获取缓存目录，未配置时使用默认缓存目录（见 [`default_cache_dir`]）
+pub fn get_cache_dir(cache_dir: &Option<String>) -> PathBuf {
+    match cache_dir {
+        Some(dir) => PathBuf::from(dir),
+        None => default_cache_dir(),
+    }
+}
+
+/// 暂停标记文件路径
+pub fn pause_file_path(cache_dir: &Option<String>) -> PathBuf {
+    get_cache_dir(cache_dir).join(PAUSE_FILE_NAME)
+}
+
+/// 创建或删除暂停标记文件，返回暂停状态是否发生了变化
+pub fn set_paused(cache_dir: &Option<String>, paused: bool) -> Result<bool> {
+    let path = pause_file_path(cache_dir);
+    if path.exists() == paused {
+        return Ok(false);
+    }
+
+    if paused {
+        let dir = get_cache_dir(cache_dir);
+        fs::create_dir_all(&dir).with_context(|| format!("创建缓存目录失败: {:?}", dir))?;
+        let content = format!("{}\n", Local::now().format("%Y-%m-%d %H:%M:%S"));
+        fs::write(&path, content).with_context(|| format!("创建暂停标记文件失败: {:?}", path))?;
+    } else {
+        fs::remove_file(&path).with_context(|| format!("删除暂停标记文件失败: {:?}", path))?;
+    }
+    Ok(true)
+}
+
+/// 读取运行状态
+///
+/// 状态文件不存在时返回默认状态。
+pub fn load_state(cache_dir: &Option<String>) -> Result<State> {
+    let path = get_cache_dir(cache_dir).join(STATE_FILE_NAME);
+
+    if !path.exists() {
+        return Ok(State::default());
+    }
+
+    let content =
+        fs::read_to_string(&path).with_context(|| format!("读取状态文件失败: {:?}", path))?;
+    serde_json::from_str(&content).with_context(|| format!("解析状态文件失败: {:?}", path))
+}
+
+/// 保存运行状态
+pub fn save_state(cache_dir: &Option<String>, state: &State) -> Result<()> {
+    let dir = get_cache_dir(cache_dir);
+    if !dir.exists() {
+        fs::create_dir_all(&dir).with_context(|| format!("创建缓存目录失败: {:?}", dir))?;
+    }
+
+    let path = dir.join(STATE_FILE_NAME);
+    let content = serde_json::to_string_pretty(state).context("序列化状态失败")?;
+    fs::write(&path, content).with_context(|| format!("写入状态文件失败: {:?}", path))
+}
+
+/// 检查条目数量是否异常下降
+///
+/// 当本次条目数低于上次条目数的 `min_ratio` 倍时返回错误。
+/// 没有上次记录（或上次为 0）时不做检查。
+pub fn check_entry_ratio(previous: Option<usize>, current: usize, min_ratio: f64) -> Result<()> {
+    let previous = match previous {
+        Some(count) if count > 0 => count,
+        _ => return Ok(()),
+    };
+
+    if (current as f64) < (previous as f64) * min_ratio {
+        return Err(anyhow::anyhow!(
+            "条目数量异常下降: 上次 {} 条，本次 {} 条（最小比例 {}），如确认无误请使用 --force",
+            previous,
+            current,
+            min_ratio
+        ));
+    }
+
+    Ok(())
+}
+
+/// 检查内容大小是否异常缩小
+///
+/// 当本次内容字节数比上次缩小超过 `max_shrink` 比例时返回错误。
+/// 没有上次记录（或上次为 0）时不做检查。
+pub fn check_shrink_ratio(previous: Option<usize>, current: usize, max_shrink: f64) -> Result<()> {
+    let previous = match previous {
+        Some(bytes) if bytes > 0 => bytes,
+        _ => return Ok(()),
+    };
+
+    if (current as f64) < (previous as f64) * (1.0 - max_shrink) {
+        return Err(anyhow::anyhow!(
+            "内容大小异常缩小: 上次 {} 字节，本次 {} 字节（最大缩小比例 {}），如确认无误请使用 --force",
+            previous,
+            current,
+            max_shrink
+        ));
+    }
+
+    Ok(())
+}
+
+/// 检查条目总数与内容大小是否超过规模阈值
+///
+/// 任一项超过阈值时返回说明当前规模与缩减建议的错误，调用方据此输出警告或拒绝写入。
+pub fn check_hosts_scale(
+    entries: usize,
+    bytes: usize,
+    max_entries: Option<usize>,
+    max_bytes: Option<u64>,
+) -> Result<()> {
+    let too_many = max_entries.is_some_and(|max| entries > max);
+    let too_large = max_bytes.is_some_and(|max| bytes as u64 > max);
+    if !too_many && !too_large {
+        return Ok(());
+    }
+
+    Err(anyhow::anyhow!(
+        "hosts 条目规模过大: 本次 {} 条、{} 字节（阈值 {} 条、{} 字节），过大的 hosts 文件会拖慢每次域名解析，\
+         部分系统还会截断或拒绝读取；可启用 minimize 去除注释与空行，为大型数据源配置 max_entries，或减少数据源",
+        entries,
+        bytes,
+        max_entries.map_or_else(|| "-".to_string(), |max| max.to_string()),
+        max_bytes.map_or_else(|| "-".to_string(), |max| max.to_string())
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_sources_and_failure() {
+        let mut state = State::default();
+        state.record_sources(&[
+            SourceResult::for_test("https://a.example/hosts", "0.0.0.0 a.com\n"),
+            SourceResult::for_test("https://b.example/hosts", "0.0.0.0 b.com\n"),
+        ]);
+        state.record_failure("https://b.example/hosts", "连接超时");
+
+        assert_eq!(state.sources.len(), 2);
+        assert_eq!(state.sources[0].status, FetchStatus::Ok);
+        assert_eq!(state.sources[0].entries, 1);
+        assert_eq!(state.sources[1].status, FetchStatus::Failed);
+        assert_eq!(state.sources[1].error.as_deref(), Some("连接超时"));
+
+        // 使用缓存写入时保留失败记录
+        let cached = |url, content| SourceResult {
+            from_cache: true,
+            ..SourceResult::for_test(url, content)
+        };
+        state.record_sources(&[
+            cached("https://a.example/hosts", "0.0.0.0 a.com\n"),
+            cached("https://b.example/hosts", "0.0.0.0 b.com\n"),
+        ]);
+        assert_eq!(state.sources[0].status, FetchStatus::Cached);
+        assert_eq!(state.sources[1].status, FetchStatus::Failed);
+
+        let table = render_sources_table(&state.sources);
+        assert!(table.contains("https://a.example/hosts"));
+        assert!(table.contains("cached"));
+        assert!(table.contains("failed"));
+    }
+
+    #[test]
+    fn test_check_entry_ratio_no_previous() {
+        assert!(check_entry_ratio(None, 0, 0.5).is_ok());
+        assert!(check_entry_ratio(Some(0), 0, 0.5).is_ok());
+    }
+
+    #[test]
+    fn test_check_entry_ratio_within_threshold() {
+        assert!(check_entry_ratio(Some(100), 100, 0.5).is_ok());
+        assert!(check_entry_ratio(Some(100), 50, 0.5).is_ok());
+        assert!(check_entry_ratio(Some(100), 200, 0.5).is_ok());
+    }
+
+    #[test]
+    fn test_check_shrink_ratio() {
+        assert!(check_shrink_ratio(None, 0, 0.3).is_ok());
+        assert!(check_shrink_ratio(Some(1000), 700, 0.3).is_ok());
+        assert!(check_shrink_ratio(Some(1000), 2000, 0.3).is_ok());
+        assert!(check_shrink_ratio(Some(1000), 699, 0.3).is_err());
+    }
+
+    #[test]
+    fn test_check_hosts_scale() {
+        assert!(check_hosts_scale(100, 5000, None, None).is_ok());
+        assert!(check_hosts_scale(100, 5000, Some(100), Some(5000)).is_ok());
+        let error = check_hosts_scale(101, 5000, Some(100), None).unwrap_err().to_string();
+        assert!(error.contains("101 条") && error.contains("minimize"));
+        assert!(check_hosts_scale(10, 5001, Some(100), Some(5000)).is_err());
+    }
+
+    #[test]
+    fn test_check_entry_ratio_dropped() {
+        assert!(check_entry_ratio(Some(100), 49, 0.5).is_err());
+        assert!(check_entry_ratio(Some(100), 0, 0.5).is_err());
+    }
+}
@@ -0,0 +1,466 @@
+//! 更新流程模块
+//!
+//! 执行一次完整的 hosts 更新：获取数据源、检查条目数量、备份并写入 hosts 文件、记录状态与缓存。
+//! 更新可通过 [`spawn_update`] 在后台任务中运行并随时取消，便于图形界面等应用集成。
+
+use crate::cache::{load_cache, save_cache};
+use crate::change_log::{DomainChanges, append_change_log};
+use crate::config::Config;
+use crate::fetcher::{FailedSource, FetchProgress, SourceResult, fetch_sources_cancellable};
+use crate::hosts::{
+    backup_hosts, count_entries, get_hosts_path, managed_hosts_path, parse_managed_section,
+    post_check_hosts, read_managed_content, render_hosts, restore_hosts, write_hosts,
+    write_hosts_to, write_include_file,
+};
+use crate::state::{
+    State, check_entry_ratio, check_hosts_scale, check_shrink_ratio, load_state, save_state,
+};
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use std::time::Instant;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+/// 单次更新的选项
+#[derive(Debug, Clone, Default)]
+pub struct UpdateOptions {
+    /// 是否跳过条目数量异常下降保护
+    pub force: bool,
+    /// 所有数据源获取失败时是否允许使用本地缓存写入
+    pub cache_fallback: bool,
+    /// 是否仅生成结果而不写入
+    pub dry_run: bool,
+    /// 将生成的完整 hosts 文件写入该路径，而不是系统 hosts 文件（此时不备份）
+    pub output: Option<PathBuf>,
+}
+
+/// 单次更新的结果
+#[derive(Debug)]
+pub struct UpdateOutcome {
+    /// 本次使用的数据源内容
+    pub sources: Vec<SourceResult>,
+    /// 写入的数据源条目区块；试运行时为生成的完整 hosts 内容
+    pub content: String,
+    /// 更新后的运行状态
+    pub state: State,
+    /// `fail_fast = false` 时被跳过的失败数据源 URL
+    pub failed_sources: Vec<String>,
+}
+
+/// 更新在写入 hosts 文件前被取消
+///
+/// 作为 [`run_update`] 返回的错误，调用方可通过 `downcast_ref::<Cancelled>()` 判断。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "更新已取消")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// 后台更新任务的句柄
+#[derive(Debug)]
+pub struct UpdateHandle {
+    cancel: CancellationToken,
+    task: JoinHandle<Result<UpdateOutcome>>,
+}
+
+impl UpdateHandle {
+    /// 请求取消更新
+    ///
+    /// 尚未开始写入 hosts 文件时，更新立即以 [`Cancelled`] 结束且不修改 hosts 文件；
+    /// 已开始写入时会等待写入及状态记录完成。
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    /// 更新任务是否已结束
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// 等待更新结束并返回结果
+    pub async fn await_result(self) -> Result<UpdateOutcome> {
+        self.task.await.context("更新任务异常终止")?
+    }
+}
+
+/// 在后台任务中执行一次更新，返回可取消的句柄
+///
+/// 必须在 tokio 运行时中调用。
+pub fn spawn_update(config: Config, options: UpdateOptions) -> UpdateHandle {
+    let cancel = CancellationToken::new();
+    let token = cancel.clone();
+    let task = tokio::spawn(async move { run_update(&config, &options, &token, |_| {}).await });
+
+    UpdateHandle { cancel, task }
+}
+
+/// 执行一次更新
+///
+/// 数据源在阻塞线程中获取，每个数据源完成时回调 `on_progress`。`cancel` 被取消时，
+/// 写入开始前的任何阶段都会立即返回 [`Cancelled`]，hosts 文件与备份均不会被修改。
+pub async fn run_update(
+    config: &Config,
+    options: &UpdateOptions,
+    cancel: &CancellationToken,
+    on_progress: impl Fn(FetchProgress) + Send + 'static,
+) -> Result<UpdateOutcome> {
+    info!("开始更新 hosts 文件...");
+    let started = Instant::now();
+
+    let hosts_path = managed_hosts_path(config);
+    info!("目标 hosts 文件: {:?}", hosts_path);
+
+    // 获取当前 hosts 文件大小（不读取内容，避免大文件占用内存）
+    let current_size = std::fs::metadata(&hosts_path).map_or(0, |m| m.len());
+    info!("当前 hosts 文件大小: {} 字节", current_size);
+
+    // 从所有数据源获取 hosts 内容
+    info!(
+        "开始从 {} 个数据源获取 hosts...",
+        config.hosts_sources.len()
+    );
+    let mut from_cache = false;
+    let fetch_config = config.clone();
+    let fetch_cancel = cancel.clone();
+    // 获取过程使用阻塞 HTTP 客户端；取消时不等待进行中的请求，其结果会被丢弃
+    let fetch = tokio::task::spawn_blocking(move || {
+        fetch_sources_cancellable(&fetch_config, &fetch_cancel, on_progress)
+    });
+    let fetched = tokio::select! {
+        result = fetch => result.context("获取数据源的任务异常终止")?,
+        _ = cancel.cancelled() => return Err(Cancelled.into()),
+    };
+    let mut failed_sources = Vec::new();
+    let sources_content = match fetched {
+        Ok(fetched) => {
+            failed_sources = fetched.failed;
+            fetched.sources
+        }
+        Err(e) if e.is::<Cancelled>() => return Err(e),
+        Err(e) => {
+            if !options.dry_run {
+                record_fetch_failure(config, &e);
+            }
+
+            let cached = if options.cache_fallback {
+                load_cache(&config.cache_dir, config.cache_ttl_hours).unwrap_or_else(|cache_err| {
+                    warn!("读取本地缓存失败: {:?}", cache_err);
+                    None
+                })
+            } else {
+                None
+            };
+            match cached {
+                Some(cached) => {
+                    warn!("获取数据源失败，使用本地缓存写入 hosts: {}", e);
+                    from_cache = true;
+                    cached
+                }
+                None => return Err(e),
+            }
+        }
+    };
+    for source in &sources_content {
+        info!(
+            "数据源 {}: 状态 {}，{} 字节，{} 条{}",
+            source.url,
+            source.status,
+            source.bytes,
+            source.entry_count(),
+            if source.from_cache {
+                "（缓存）"
+            } else {
+                ""
+            }
+        );
+    }
+    let empty_sources = sources_content
+        .iter()
+        .filter(|source| source.entry_count() == 0)
+        .count();
+    info!(
+        "成功获取 {} 个数据源的内容，其中 {} 个无有效条目",
+        sources_content.len(),
+        empty_sources
+    );
+
+    // 条目数量异常下降保护
+    let mut state = load_state(&config.cache_dir)?;
+    state.record_sources(&sources_content);
+    for (source, error) in &failed_sources {
+        state.record_failure(&source.url, error);
+    }
+    let failed_sources: Vec<String> =
+        failed_sources.into_iter().map(|(source, _)| source.url).collect();
+    if !failed_sources.is_empty() {
+        warn!(
+            "{} 个数据源获取失败，已跳过: {}",
+            failed_sources.len(),
+            failed_sources.join(", ")
+        );
+    }
+    let entry_count: usize = sources_content
+        .iter()
+        .map(|source| source.entry_count())
+        .sum();
+    info!(
+        "条目数量: 上次 {} 条，本次 {} 条",
+        state
+            .last_entry_count
+            .map_or_else(|| "-".to_string(), |count| count.to_string()),
+        entry_count
+    );
+    if let Err(e) = check_entry_ratio(state.last_entry_count, entry_count, config.min_entry_ratio) {
+        if !options.force {
+            return Err(e);
+        }
+        warn!("{}，已通过 --force 强制写入", e);
+    }
+
+    // 内容大小异常缩小保护：数据源返回被截断但仍能通过验证的内容时拒绝写入
+    let content_bytes: usize = sources_content
+        .iter()
+        .map(|source| source.content.len())
+        .sum();
+    if let Some(max_shrink) = config.max_shrink_ratio
+        && let Err(e) = check_shrink_ratio(state.last_content_bytes, content_bytes, max_shrink)
+    {
+        if !options.force {
+            return Err(e);
+        }
+        warn!("{}，已通过 --force 强制写入", e);
+    }
+
+    // 规模保护：超过上限时拒绝写入，超过警告阈值时仅提示
+    if let Err(e) = check_hosts_scale(
+        entry_count,
+        content_bytes,
+        config.max_entry_count,
+        config.max_content_bytes,
+    ) {
+        if !options.force {
+            return Err(e);
+        }
+        warn!("{}，已通过 --force 强制写入", e);
+    } else if let Err(e) = check_hosts_scale(
+        entry_count,
+        content_bytes,
+        Some(config.warn_entry_count),
+        Some(config.warn_content_bytes),
+    ) {
+        warn!("{}", e);
+    }
+
+    // 生成最后更新时间
+    let last_update = config
+        .timestamp_tz
+        .now()
+        .format(&config.timestamp_format)
+        .to_string();
+
+    // 试运行：仅生成结果，指定输出路径时写入该文件
+    if options.dry_run {
+        let content = render_hosts(&sources_content, &last_update, config)?;
+        match &options.output {
+            Some(output) => {
+                std::fs::write(output, &content)
+                    .with_context(|| format!("写入输出文件失败: {:?}", output))?;
+                info!("试运行完成，结果已写入: {:?}", output);
+            }
+            None => info!("试运行完成，未写入 hosts 文件"),
+        }
+        return Ok(UpdateOutcome {
+            sources: sources_content,
+            content,
+            state,
+            failed_sources,
+        });
+    }
+
+    // 此后开始修改文件，不再响应取消
+    if cancel.is_cancelled() {
+        return Err(Cancelled.into());
+    }
+
+    // 记录写入前的自动管理区域，写入后与新条目比较，用于汇总日志与变更日志
+    let previous_section = match read_managed_content(config) {
+        Ok(content) => Some(parse_managed_section(&content, &config.timestamp_format)),
+        Err(e) => {
+            warn!("读取写入前的自动管理区域失败，本次不统计域名变化: {:?}", e);
+            None
+        }
+    };
+
+    let content = match &options.output {
+        Some(output) => {
+            let content = if config.output_file.is_some() {
+                write_include_file(output, &sources_content, &last_update, config)?
+            } else {
+                write_hosts_to(output, &sources_content, &last_update, config)?
+            };
+            info!("已将更新后的 hosts 文件写入: {:?}", output);
+            content
+        }
+        // 写入独立的 include 文件时不修改系统 hosts 文件，也无需备份
+        None if config.output_file.is_some() => {
+            let content = write_include_file(&hosts_path, &sources_content, &last_update, config)?;
+            info!("已将自动管理区域写入 include 文件: {:?}", hosts_path);
+            content
+        }
+        None => {
+            // 备份现有 hosts
+            if config.backup_before_update {
+                let backup_path = backup_hosts(&config.backup_path, config.timestamp_tz)?;
+                info!("已备份 hosts 文件到: {}", backup_path);
+            }
+
+            // 写入 hosts 文件
+            let content = if config.transactional {
+                write_hosts_transactional(&sources_content, &last_update, config)?
+            } else {
+                write_hosts(&sources_content, &last_update, config)?
+            };
+            info!("hosts 文件更新成功");
+            content
+        }
+    };
+
+    // 记录本次条目数量
+    state.last_entry_count = Some(entry_count);
+    state.last_content_bytes = Some(content_bytes);
+    save_state(&config.cache_dir, &state)?;
+
+    // 更新本地缓存
+    if !from_cache {
+        save_cache(&config.cache_dir, &sources_content)?;
+    }
+
+    // 汇总本次更新：域名变化、写入的条目总数与耗时
+    let changes = previous_section
+        .as_ref()
+        .map(|previous| DomainChanges::between(previous, &content));
+    let total = count_entries(&content);
+    let elapsed = started.elapsed().as_secs_f64();
+    match &changes {
+        Some(changes) => info!(
+            added = changes.added.len(),
+            removed = changes.removed.len(),
+            total,
+            elapsed_secs = elapsed,
+            "更新完成: +{} 新增, -{} 移除, {} 总计, 耗时 {:.1}s",
+            changes.added.len(),
+            changes.removed.len(),
+            total,
+            elapsed
+        ),
+        None => info!(
+            total,
+            elapsed_secs = elapsed,
+            "更新完成: {} 总计, 耗时 {:.1}s",
+            total,
+            elapsed
+        ),
+    }
+
+    // 追加变更日志，写入失败仅输出警告
+    if let (Some(path), Some(changes)) = (&config.change_log, &changes) {
+        let path = std::path::Path::new(path);
+        if let Err(e) = append_change_log(path, changes, &last_update, config.change_log_max_bytes)
+        {
+            warn!("写入变更日志失败: {:?}", e);
+        }
+    }
+
+    Ok(UpdateOutcome {
+        sources: sources_content,
+        content,
+        state,
+        failed_sources,
+    })
+}
+
+/// 以事务方式写入系统 hosts 文件
+///
+/// 写入前在内存中保存原内容，写入失败或写入后检查失败时恢复原内容并返回错误。
+fn write_hosts_transactional(
+    sources: &[SourceResult],
+    last_update: &str,
+    config: &Config,
+) -> Result<String> {
+    let hosts_path = get_hosts_path();
+    let original = std::fs::read(&hosts_path)
+        .with_context(|| format!("读取 hosts 文件失败: {:?}", hosts_path))?;
+    info!("事务更新: 已保存写入前的 hosts 内容（{} 字节）", original.len());
+
+    let result = write_hosts(sources, last_update, config).and_then(|content| {
+        info!("事务更新: 写入完成，开始写入后检查");
+        post_check_hosts(&content, config)?;
+        Ok(content)
+    });
+
+    match result {
+        Ok(content) => {
+            info!("事务更新: 写入后检查通过，提交本次更新");
+            Ok(content)
+        }
+        Err(e) => {
+            error!("事务更新: 写入或检查失败，回滚 hosts 文件: {:?}", e);
+            restore_hosts(&original).context("回滚 hosts 文件失败")?;
+            info!("事务更新: 已恢复写入前的 hosts 内容");
+            Err(e.context("事务更新失败，hosts 文件已回滚"))
+        }
+    }
+}
+
+/// 在运行状态中记录获取失败的数据源，记录失败仅输出警告
+fn record_fetch_failure(config: &Config, error: &anyhow::Error) {
+    let Some(failed) = error.downcast_ref::<FailedSource>() else {
+        return;
+    };
+
+    let result = load_state(&config.cache_dir).and_then(|mut state| {
+        state.record_failure(&failed.url, &error.root_cause().to_string());
+        save_state(&config.cache_dir, &state)
+    });
+    if let Err(e) = result {
+        warn!("记录数据源状态失败: {:?}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[tokio::test]
+    async fn test_cancel_update_during_fetch() {
+        // 接受连接但从不响应的数据源，使获取一直挂起
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hosts", listener.local_addr().unwrap());
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut config = Config::from_sources(vec![url]);
+        config.cache_dir = Some(dir.path().to_string_lossy().to_string());
+        let options = UpdateOptions {
+            dry_run: true,
+            ..Default::default()
+        };
+
+        let start = Instant::now();
+        let handle = spawn_update(config, options);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!handle.is_finished());
+        handle.cancel();
+
+        let error = handle.await_result().await.unwrap_err();
+        assert!(error.is::<Cancelled>());
+        assert!(start.elapsed() < Duration::from_secs(5));
+        drop(listener);
+    }
+}
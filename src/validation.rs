@@ -0,0 +1,388 @@
+//! hosts 内容校验模块
+//!
+//! 提供 IP、域名、单行 hosts 条目与完整 hosts 内容的格式校验，数据源获取、写入自检与 `lint`
+//! 均使用这些函数；作为库使用时也可用于校验用户输入。
+
+use crate::i18n::Msg;
+use anyhow::Result;
+
+/// 验证 hosts 内容格式
+///
+/// 内容为空（或只有空白）、包含控制字符或任一条目行格式错误时返回错误，空行与注释行被跳过。
+/// `url` 只用于错误信息中标明内容来源。
+///
+/// ```
+/// use hosts_updater_rs::validation::validate_hosts_content;
+///
+/// let content = "# 注释\n127.0.0.1 localhost\n0.0.0.0 ads.example.com tracker.example.com\n";
+/// assert!(validate_hosts_content(content, "https://example.com/hosts").is_ok());
+/// assert!(validate_hosts_content("", "https://example.com/hosts").is_err());
+/// ```
+pub fn validate_hosts_content(content: &str, url: &str) -> Result<()> {
+    if content.trim().is_empty() {
+        return Err(anyhow::anyhow!("URL 返回内容为空: {}", url));
+    }
+
+    // 检查是否包含非法字符（控制字符等）
+    if let Some(i) = find_control_char(content) {
+        return Err(anyhow::anyhow!(
+            "URL 返回内容包含非法控制字符 (位置 {}): {}",
+            i,
+            url
+        ));
+    }
+
+    // 逐行检查 hosts 格式
+    for (line_num, line) in content.lines().enumerate() {
+        let line = line.trim_start();
+
+        // 跳过空行和注释行
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        // 检查是否为有效的 hosts 格式: IP + 域名
+        validate_hosts_line(line, line_num + 1, url)?;
+    }
+
+    Ok(())
+}
+
+/// 查找第一个非法控制字符，返回其字符位置
+///
+/// 按字节单次扫描：ASCII 控制字符（除换行、回车、制表符外）为单字节，
+/// C1 控制字符（U+0080 - U+009F）在 UTF-8 中编码为 `0xC2 0x80..=0x9F`。
+fn find_control_char(content: &str) -> Option<usize> {
+    let bytes = content.as_bytes();
+
+    for (i, &byte) in bytes.iter().enumerate() {
+        let is_control = match byte {
+            b'\n' | b'\r' | b'\t' => false,
+            0x00..=0x1F | 0x7F => true,
+            0xC2 => matches!(bytes.get(i + 1), Some(0x80..=0x9F)),
+            _ => false,
+        };
+
+        if is_control {
+            // 仅在出错时计算字符位置
+            return Some(content[..i].chars().count());
+        }
+    }
+
+    None
+}
+
+/// 验证单行 hosts 配置格式
+///
+/// `line` 应为一个 IP 后跟至少一个域名，以空白分隔；调用方需自行跳过空行与注释行。
+/// `line_num` 与 `url` 只用于错误信息。
+///
+/// ```
+/// use hosts_updater_rs::validation::validate_hosts_line;
+///
+/// assert!(validate_hosts_line("192.168.1.2 nas.lan nas", 1, "用户输入").is_ok());
+/// assert!(validate_hosts_line("192.168.1.2", 2, "用户输入").is_err());
+/// assert!(validate_hosts_line("999.1.1.1 nas.lan", 3, "用户输入").is_err());
+/// ```
+pub fn validate_hosts_line(line: &str, line_num: usize, url: &str) -> Result<()> {
+    match check_hosts_line(line) {
+        Some(reason) => Err(anyhow::anyhow!(
+            "{}",
+            Msg::LineErrorWithSource {
+                line_num,
+                reason: &reason,
+                url
+            }
+        )),
+        None => Ok(()),
+    }
+}
+
+/// 检查单行 hosts 配置格式，返回错误原因
+fn check_hosts_line(line: &str) -> Option<String> {
+    let mut parts = line.split_whitespace();
+
+    let (ip, first_domain) = match (parts.next(), parts.next()) {
+        (Some(ip), Some(domain)) => (ip, domain),
+        _ => return Some(Msg::MissingIpOrDomain(line.trim_end()).to_string()),
+    };
+
+    // 验证 IP 地址格式
+    if !is_valid_ip(ip) {
+        return Some(Msg::InvalidIp(ip).to_string());
+    }
+
+    // 验证每个域名格式
+    std::iter::once(first_domain)
+        .chain(parts)
+        .find(|domain| !is_valid_domain(domain))
+        .map(|domain| Msg::InvalidDomain(domain).to_string())
+}
+
+/// 单行验证错误
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineError {
+    /// 行号（从 1 开始）
+    pub line_num: usize,
+    /// 该行原始内容
+    pub content: String,
+    /// 错误原因
+    pub reason: String,
+}
+
+impl std::fmt::Display for LineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Msg::LineError {
+            line_num: self.line_num,
+            reason: &self.reason,
+        }
+        .fmt(f)
+    }
+}
+
+/// 验证 hosts 内容格式，收集所有出错的行
+///
+/// 与 `validate_hosts_content` 不同，遇到错误不会中止，便于一次性修复所有问题。
+/// 内容是否为空需由调用方另行判断。
+pub fn validate_hosts_content_collect(content: &str) -> Vec<LineError> {
+    let mut errors = Vec::new();
+
+    for (line_num, line) in content.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        let reason = match find_control_char(line) {
+            Some(i) => Some(Msg::ControlChar(i).to_string()),
+            None => check_hosts_line(trimmed),
+        };
+
+        if let Some(reason) = reason {
+            errors.push(LineError {
+                line_num: line_num + 1,
+                content: line.to_string(),
+                reason,
+            });
+        }
+    }
+
+    errors
+}
+
+/// 验证域名格式
+///
+/// 总长度不超过 253 字符，每段标签 1 到 63 个字母、数字或连字符且不以连字符开头或结尾；
+/// 允许完全限定形式末尾的单个点。
+///
+/// ```
+/// use hosts_updater_rs::validation::is_valid_domain;
+///
+/// assert!(is_valid_domain("sub.example.com"));
+/// assert!(is_valid_domain("example.com."));
+/// assert!(!is_valid_domain("-bad.example.com"));
+/// assert!(!is_valid_domain("under_score.com"));
+/// ```
+pub fn is_valid_domain(domain: &str) -> bool {
+    // 允许完全限定形式末尾的单个点（如 `example.com.`）
+    let domain = domain.strip_suffix('.').unwrap_or(domain);
+
+    // 域名不能为空
+    if domain.is_empty() {
+        return false;
+    }
+
+    // 域名长度限制（总长度 253 字符以内）
+    if domain.len() > 253 {
+        return false;
+    }
+
+    // 每段标签长度限制（1-63 字符）
+    for label in domain.split('.') {
+        let bytes = label.as_bytes();
+        if bytes.is_empty() || bytes.len() > 63 {
+            return false;
+        }
+
+        // 标签必须以字母或数字开头和结尾
+        if !is_alphanumeric_byte(bytes[0]) || !is_alphanumeric_byte(bytes[bytes.len() - 1]) {
+            return false;
+        }
+
+        // 标签只能包含字母、数字和连字符
+        if !bytes
+            .iter()
+            .all(|&byte| byte == b'-' || is_alphanumeric_byte(byte))
+        {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// 判断单个字节是否为字母或数字
+///
+/// ASCII 字节走快速路径，其余字节按其 Latin-1 字符判断，与逐字符检查保持一致。
+fn is_alphanumeric_byte(byte: u8) -> bool {
+    if byte.is_ascii() {
+        byte.is_ascii_alphanumeric()
+    } else {
+        (byte as char).is_alphanumeric()
+    }
+}
+
+/// 验证 IP 地址格式（支持 IPv4 和 IPv6，IPv6 地址可带方括号）
+///
+/// ```
+/// use hosts_updater_rs::validation::is_valid_ip;
+///
+/// assert!(is_valid_ip("0.0.0.0"));
+/// assert!(is_valid_ip("::1"));
+/// assert!(is_valid_ip("[::1]"));
+/// assert!(!is_valid_ip("256.1.1.1"));
+/// ```
+pub fn is_valid_ip(ip: &str) -> bool {
+    // IPv4 检查
+    if ip.parse::<std::net::Ipv4Addr>().is_ok() {
+        return true;
+    }
+
+    // IPv6 检查（方括号格式）
+    if ip.starts_with('[') && ip.ends_with(']') {
+        let ipv6 = &ip[1..ip.len() - 1];
+        return ipv6.parse::<std::net::Ipv6Addr>().is_ok();
+    }
+
+    // 纯 IPv6 检查
+    if ip.parse::<std::net::Ipv6Addr>().is_ok() {
+        return true;
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hosts::count_entries;
+
+    #[test]
+    fn test_validate_hosts_content_valid() {
+        let content = r#"
+# 注释行
+127.0.0.1 localhost
+192.168.1.100 example.com
+"#;
+
+        assert!(validate_hosts_content(content, "https://example.com").is_ok());
+    }
+
+    #[test]
+    fn test_validate_hosts_content_empty() {
+        let content = "";
+        assert!(validate_hosts_content(content, "https://example.com").is_err());
+    }
+
+    #[test]
+    fn test_validate_hosts_content_comments_only() {
+        let content = "# 占位列表\n# 暂无条目\n";
+        assert!(validate_hosts_content(content, "https://example.com").is_ok());
+        assert_eq!(count_entries(content), 0);
+    }
+
+    #[test]
+    fn test_validate_hosts_content_whitespace_only() {
+        let content = "  \n\t\n";
+        assert!(validate_hosts_content(content, "https://example.com").is_err());
+    }
+
+    #[test]
+    fn test_validate_hosts_content_with_control_chars() {
+        let content = "127.0.0.1 localhost\x00";
+        assert!(validate_hosts_content(content, "https://example.com").is_err());
+    }
+
+    #[test]
+    fn test_validate_hosts_line_valid_ipv4() {
+        assert!(is_valid_ip("127.0.0.1"));
+        assert!(is_valid_ip("192.168.1.100"));
+        assert!(is_valid_ip("0.0.0.0"));
+    }
+
+    #[test]
+    fn test_validate_hosts_line_valid_ipv6() {
+        assert!(is_valid_ip("::1"));
+        assert!(is_valid_ip("2001:0db8:85a3:0000:0000:8a2e:0370:7334"));
+        assert!(is_valid_ip("[::1]"));
+    }
+
+    #[test]
+    fn test_validate_hosts_line_invalid_ip() {
+        assert!(!is_valid_ip("invalid"));
+        assert!(!is_valid_ip("256.1.1.1"));
+        assert!(!is_valid_ip("abc.def.ghi.jkl"));
+    }
+
+    #[test]
+    fn test_validate_hosts_content_invalid_line() {
+        let content = "127.0.0.1\ninvalid_line_without_ip\n192.168.1.100 example.com";
+        assert!(validate_hosts_content(content, "https://example.com").is_err());
+    }
+
+    #[test]
+    fn test_validate_hosts_content_missing_domain() {
+        let content = "127.0.0.1";
+        assert!(validate_hosts_content(content, "https://example.com").is_err());
+    }
+
+    #[test]
+    fn test_is_valid_domain_valid() {
+        assert!(is_valid_domain("example.com"));
+        assert!(is_valid_domain("sub.example.com"));
+        assert!(is_valid_domain("localhost"));
+        assert!(is_valid_domain("my-server-123.com"));
+        assert!(is_valid_domain("a1b2c3.com"));
+        assert!(is_valid_domain("example.com."));
+    }
+
+    #[test]
+    fn test_is_valid_domain_invalid() {
+        assert!(!is_valid_domain(""));
+        assert!(!is_valid_domain("-invalid.com"));
+        assert!(!is_valid_domain("invalid-.com"));
+        assert!(!is_valid_domain("invalid..com"));
+        assert!(!is_valid_domain("invalid_domain.com"));
+        assert!(!is_valid_domain("exam ple.com"));
+        assert!(!is_valid_domain("."));
+        assert!(!is_valid_domain("example.com.."));
+    }
+
+    #[test]
+    fn test_find_control_char() {
+        assert_eq!(find_control_char("127.0.0.1 localhost\r\n\t"), None);
+        assert_eq!(find_control_char("例え\x07"), Some(2));
+        assert_eq!(find_control_char("a\u{85}b"), Some(1));
+        assert_eq!(find_control_char("a\x7F"), Some(1));
+        assert_eq!(find_control_char("é ü"), None);
+    }
+
+    #[test]
+    fn test_validate_hosts_content_collect() {
+        let content = "# 注释\n127.0.0.1 a.com\n999.0.0.1 b.com\n127.0.0.1\n\n127.0.0.1 -bad.com\n";
+        let errors = validate_hosts_content_collect(content);
+
+        let lines: Vec<usize> = errors.iter().map(|e| e.line_num).collect();
+        assert_eq!(lines, vec![3, 4, 6]);
+        assert_eq!(errors[0].content, "999.0.0.1 b.com");
+        assert!(errors[0].reason.contains("IP"));
+        assert!(errors[2].to_string().starts_with("第 6 行域名格式无效"));
+        assert!(validate_hosts_content_collect("127.0.0.1 a.com\n").is_empty());
+    }
+
+    #[test]
+    fn test_validate_hosts_content_invalid_domain() {
+        let content = "127.0.0.1 -invalid.com";
+        assert!(validate_hosts_content(content, "https://example.com").is_err());
+    }
+}